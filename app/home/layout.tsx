@@ -1,7 +1,7 @@
 "use client";
 import Link from "next/link";
 import { useEffect, useState } from "react";
-import { getCookie } from "cookies-next";
+import { invoke } from "@tauri-apps/api/tauri";
 function Loader() {
   return (
     <div className="text-md flex flex-row gap-4">
@@ -21,8 +21,9 @@ export default function RootLayout({
 
   useEffect(() => {
     const checkAuthentication = async () => {
-      const token = getCookie("token");
-      if (token) setIsAuthenticated(true);
+      // The session lives in the app, the window only learns whether there is one
+      const authorized = await invoke<boolean>("is_authorized").catch(() => false);
+      setIsAuthenticated(authorized);
       setLoading(false);
     };
 
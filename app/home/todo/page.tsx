@@ -12,11 +12,11 @@ import { Popover, PopoverContent, PopoverTrigger } from "@/components/ui/popover
 import { Dialog, DialogContent, DialogDescription, DialogHeader, DialogTitle } from "@/components/ui/dialog";
 import { Separator } from "@/components/ui/separator";
 import { Progress } from "@/components/ui/progress";
-import { cn } from "@/lib/utils";
+import { cn, errorMessage } from "@/lib/utils";
 import { Calendar } from "@/components/ui/calendar";
-import { getCookie } from "cookies-next";
+import { invoke } from "@tauri-apps/api/tauri";
 import { useToast } from "@/components/ui/use-toast";
-import { format } from "date-fns";
+import { format, parseISO } from "date-fns";
 
 const formSchema = z.object({
   title: z.string().min(2).max(50),
@@ -32,8 +32,9 @@ interface Task {
   checked: boolean;
   title: string;
   description: string;
-  date?: string;
-  isLate?: boolean;
+  due_date?: string;
+  due_time?: string;
+  overdue: boolean;
   duration?: number;
   priority?: number;
 }
@@ -59,28 +60,10 @@ const TodoPage = () => {
 
   const loadTodo = () => {
     // Get user's todo list
-    let email = getCookie("email");
-    let token = getCookie("token");
-
-    fetch(`http://localhost:4875/tasks/${email}`, {
-      headers: {
-        "Content-Type": "application/json",
-        Authorization: `Bearer ${token}`,
-      },
-    })
-      .then(handleResponse)
+    invoke<Task[]>("get_tasks")
       .then((data) => {
-        // Update isLate if the task is late
-        let today = new Date();
-        let updatedData = data.map((task: Task) => {
-          let taskDate = task.date ? new Date(task.date) : null;
-          return {
-            ...task,
-            isLate: taskDate && taskDate < today,
-          };
-        });
-
-        setTodo(updatedData);
+        // The server flags overdue tasks using the user's timezone
+        setTodo(data);
       })
       .catch((error) => handleError(error));
   };
@@ -103,32 +86,24 @@ const TodoPage = () => {
 
   async function handleAddTask(values: z.infer<typeof formSchema>) {
     toast({ title: "Adding task..." });
-    const token = getCookie("token");
-    let email = getCookie("email");
+    const user = await invoke<{ email: string } | null>("current_user");
 
-    if (!token) {
+    if (!user) {
       toast({ title: "You must be logged in to manage tasks ❌" });
       return;
     }
 
-    // Prepare body for request to server
-    const requestBody = {
-      ...values,
-      user_email: email,
-      date: values.date ? values.date.toISOString() : null,
+    // Prepare the task for the command
+    const task = {
+      title: values.title,
+      description: values.description,
+      user_email: user.email,
+      due_date: values.date ? format(values.date, "yyyy-MM-dd") : null,
       duration: values.duration ? parseInt(values.duration) : null,
       priority: values.priority || 1,
     };
 
-    await fetch("http://localhost:4875/tasks/create", {
-      method: "POST",
-      headers: {
-        "Content-Type": "application/json",
-        Authorization: `Bearer ${token}`,
-      },
-      body: JSON.stringify(requestBody),
-    })
-      .then(handleResponse)
+    await invoke("create_task", { task })
       .then(() => {
         // Handle success
         toast({
@@ -143,7 +118,7 @@ const TodoPage = () => {
   function formatDate(inputDate: string): string {
     // If date falls within today
     let today = new Date();
-    let taskDate = new Date(inputDate);
+    let taskDate = parseISO(inputDate);
 
     if (taskDate.getDay() == today.getDay()) {
       return "Today";
@@ -152,41 +127,15 @@ const TodoPage = () => {
     }
   }
 
-  function handleResponse(response: Response) {
-    return response.ok
-      ? response.json()
-      : response.json().then((data) => {
-          throw new Error(data);
-        });
-  }
-
   function handleError(error: any) {
     console.error(error);
-    toast({ variant: "destructive", title: "Operation failed ❌", description: error.message || "An error occurred" });
+    toast({ variant: "destructive", title: "Operation failed ❌", description: errorMessage(error) || "An error occurred" });
   }
 
   async function handleDeleteTask(id: number) {
     toast({ title: "Deleting task... 🔃" });
-    const token = getCookie("token");
 
-    if (!token) {
-      toast({ title: "You must be logged in to manage tasks ❌" });
-      return;
-    }
-
-    await fetch(`http://localhost:4875/tasks/delete/${id}`, {
-      method: "DELETE",
-      headers: {
-        Authorization: `Bearer ${token}`,
-      },
-    })
-      .then((response) => {
-        return response.ok
-          ? response
-          : response.json().then((data) => {
-              throw new Error(data);
-            });
-      })
+    await invoke("delete_task", { taskId: id })
       .then(() => {
         toast({
           title: "Task deleted successfully ✅",
@@ -231,7 +180,7 @@ const TodoPage = () => {
     };
   }, []);
 
-  const Task = ({ task_id, checked, title, description, date, duration, isLate, priority }: Task) => {
+  const Task = ({ task_id, checked, title, description, due_date, duration, overdue, priority }: Task) => {
     return (
       <div className="border-2 rounded-sm border-gray-400 flex flex-col sm:flex-row gap-3 p-3 my-1">
         <Checkbox
@@ -244,7 +193,7 @@ const TodoPage = () => {
           <h4 className={`h4 ${checked && "line-through"}`}>{title}</h4>
           <p className={`p ${checked && "line-through"}`}>{description}</p>
         </div>
-        <p className={`${isLate && "text-red-400"}`}>{date ? formatDate(date) : "No date"}</p>
+        <p className={`${overdue && "text-red-400"}`}>{due_date ? formatDate(due_date) : "No date"}</p>
         <p>{duration ? `${duration} minutes` : "No duration"}</p>
         <p>
           <FlagIcon className="inline mr-1" />
@@ -8,7 +8,8 @@ import { Form, FormControl, FormDescription, FormField, FormItem, FormLabel, For
 import { Input } from "@/components/ui/input";
 import { Tabs, TabsContent, TabsList, TabsTrigger } from "@/components/ui/tabs";
 import { useToast } from "@/components/ui/use-toast";
-import { hasCookie, setCookie } from "cookies-next";
+import { invoke } from "@tauri-apps/api/tauri";
+import { errorMessage } from "@/lib/utils";
 
 const registerFormSchema = z.object({
   username: z.string().min(2).max(50),
@@ -21,19 +22,26 @@ const loginFormSchema = z.object({
   password: z.string().min(3),
 });
 
+interface CurrentUser {
+  email: string;
+  username: string;
+}
+
+// Outcome of the `login` command
+type LoginResult =
+  | { status: "signed_in"; user: CurrentUser }
+  | { status: "two_factor_required"; challenge_token: string };
+
 export default function AuthPage() {
   const { toast } = useToast();
   const [error, setError] = useState("");
   const [loading, setLoading] = useState(false);
 
   useEffect(() => {
-    const checkAuth = () => {
-      console.log("Checking auth!");
-      if (hasCookie("token")) {
-        window.location.replace("/home");
-      }
-    };
-    checkAuth();
+    // The app may have signed back in to the last session on launch
+    invoke<boolean>("is_authorized")
+      .then((authorized) => authorized && window.location.replace("/home"))
+      .catch(console.error);
   }, []);
 
   const registerForm = useForm<z.infer<typeof registerFormSchema>>({
@@ -46,25 +54,12 @@ export default function AuthPage() {
     defaultValues: { email: "", password: "" },
   });
 
-  function handleResponse(response: Response) {
-    return response.ok
-      ? response.json()
-      : response.json().then((data) => {
-          throw new Error(data);
-        });
-  }
-
   function handleRegister(values: z.infer<typeof registerFormSchema>) {
     toast({ title: "Logging in... 🔃" });
     setLoading(true);
-    fetch("http://localhost:4875/auth/register", {
-      method: "POST",
-      headers: { "Content-Type": "application/json" },
-      body: JSON.stringify(values),
-    })
-      .then(handleResponse)
-      .then((data) => {
-        setCookiesAndRedirect(data);
+    invoke<CurrentUser>("register", { user: values })
+      .then(() => {
+        window.location.replace("/home");
         setLoading(false); // Move setLoading inside then block
       })
       .catch((error) => {
@@ -76,14 +71,10 @@ export default function AuthPage() {
   function handleLogin(values: z.infer<typeof loginFormSchema>) {
     toast({ title: "Logging in... 🔃" });
     setLoading(true);
-    fetch("http://localhost:4875/auth/login", {
-      method: "POST",
-      headers: { "Content-Type": "application/json" },
-      body: JSON.stringify(values),
-    })
-      .then(handleResponse)
-      .then((data) => {
-        setCookiesAndRedirect(data);
+    invoke<LoginResult>("login", values)
+      .then((result) => (result.status === "two_factor_required" ? verifyTwoFactor(result.challenge_token) : result.user))
+      .then(() => {
+        window.location.replace("/home");
         setLoading(false); // Move setLoading inside then block
       })
       .catch((error) => {
@@ -92,22 +83,22 @@ export default function AuthPage() {
       });
   }
 
-  function setCookiesAndRedirect(data: any) {
-    const currentDate = new Date();
-    const expirationDate = new Date(currentDate);
-    expirationDate.setDate(currentDate.getDate() + 3);
-    console.log(data);
-    console.log("setting cookies!");
-    setCookie("token", data.token, { sameSite: true, expires: expirationDate });
-    setCookie("username", data.user_username, { sameSite: true, expires: expirationDate });
-    setCookie("email", data.user_email, { sameSite: true, expires: expirationDate });
-    window.location.replace("/home");
+  // Second login step for accounts with two-factor authentication
+  function verifyTwoFactor(challengeToken: string) {
+    const code = window.prompt("Enter the code from your authenticator app, or a recovery code");
+    if (!code) throw new Error("Two-factor code required");
+    const isRecoveryCode = code.includes("-");
+    return invoke<CurrentUser>(
+      "verify_two_factor",
+      isRecoveryCode ? { challengeToken, recoveryCode: code } : { challengeToken, code }
+    );
   }
 
   function handleError(error: any) {
     console.error(error);
-    setError(error.message || "An error occurred");
-    toast({ variant: "destructive", title: "Operation failed ❌", description: error.message || "An error occurred" });
+    const message = errorMessage(error) || "An error occurred";
+    setError(message);
+    toast({ variant: "destructive", title: "Operation failed ❌", description: message });
     setLoading(false);
   }
 
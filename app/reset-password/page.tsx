@@ -0,0 +1,114 @@
+"use client";
+import { useEffect, useState } from "react";
+import { Button } from "@/components/ui/button";
+import { z } from "zod";
+import { zodResolver } from "@hookform/resolvers/zod";
+import { useForm } from "react-hook-form";
+import { Form, FormControl, FormField, FormItem, FormLabel, FormMessage } from "@/components/ui/form";
+import { Input } from "@/components/ui/input";
+import { useToast } from "@/components/ui/use-toast";
+import { invoke } from "@tauri-apps/api/tauri";
+import { errorMessage } from "@/lib/utils";
+
+const requestFormSchema = z.object({
+  email: z.string().min(2),
+});
+
+const confirmFormSchema = z.object({
+  new_password: z.string().min(8),
+});
+
+export default function ResetPasswordPage() {
+  const { toast } = useToast();
+  const [token, setToken] = useState<string | null>(null);
+  const [loading, setLoading] = useState(false);
+
+  useEffect(() => {
+    // The reset email links here with the token in the query string
+    setToken(new URLSearchParams(window.location.search).get("token"));
+  }, []);
+
+  const requestForm = useForm<z.infer<typeof requestFormSchema>>({
+    resolver: zodResolver(requestFormSchema),
+    defaultValues: { email: "" },
+  });
+
+  const confirmForm = useForm<z.infer<typeof confirmFormSchema>>({
+    resolver: zodResolver(confirmFormSchema),
+    defaultValues: { new_password: "" },
+  });
+
+  function run(command: string, args: Record<string, unknown>) {
+    setLoading(true);
+    return invoke(command, args).finally(() => setLoading(false));
+  }
+
+  function handleRequest(values: z.infer<typeof requestFormSchema>) {
+    run("request_password_reset", values)
+      .then(() => toast({ title: "If the account exists, a reset link has been sent" }))
+      .catch(handleError);
+  }
+
+  function handleConfirm(values: z.infer<typeof confirmFormSchema>) {
+    run("reset_password", { reset: { token, ...values } })
+      .then(() => {
+        toast({ title: "Password has been reset, please log in again" });
+        window.location.replace("/");
+      })
+      .catch(handleError);
+  }
+
+  function handleError(error: any) {
+    console.error(error);
+    toast({ variant: "destructive", title: "Operation failed ❌", description: errorMessage(error) || "An error occurred" });
+  }
+
+  return (
+    <div className="flex min-h-screen flex-col items-center justify-center p-24">
+      <h1 className="h2 border-b pb-2 mb-4">Reset password</h1>
+      {token ? (
+        <Form {...confirmForm}>
+          <form onSubmit={confirmForm.handleSubmit(handleConfirm)} className="space-y-4 w-[400px]">
+            <FormField
+              control={confirmForm.control}
+              name="new_password"
+              render={({ field }) => (
+                <FormItem>
+                  <FormLabel>New password</FormLabel>
+                  <FormControl>
+                    <Input type="password" {...field} />
+                  </FormControl>
+                  <FormMessage />
+                </FormItem>
+              )}
+            />
+            <Button type="submit" disabled={loading}>
+              Set new password
+            </Button>
+          </form>
+        </Form>
+      ) : (
+        <Form {...requestForm}>
+          <form onSubmit={requestForm.handleSubmit(handleRequest)} className="space-y-4 w-[400px]">
+            <FormField
+              control={requestForm.control}
+              name="email"
+              render={({ field }) => (
+                <FormItem>
+                  <FormLabel>Email</FormLabel>
+                  <FormControl>
+                    <Input placeholder="you@example.com" {...field} />
+                  </FormControl>
+                  <FormMessage />
+                </FormItem>
+              )}
+            />
+            <Button type="submit" disabled={loading}>
+              Send reset link
+            </Button>
+          </form>
+        </Form>
+      )}
+    </div>
+  );
+}
@@ -0,0 +1,32 @@
+"use client";
+import { useEffect, useState } from "react";
+import { Button } from "@/components/ui/button";
+import { invoke } from "@tauri-apps/api/tauri";
+import { errorMessage } from "@/lib/utils";
+
+export default function VerifyEmailPage() {
+  const [status, setStatus] = useState("Verifying your email... 🔃");
+  const [done, setDone] = useState(false);
+
+  useEffect(() => {
+    // The verification email links here with the token in the query string
+    const token = new URLSearchParams(window.location.search).get("token");
+    if (!token) {
+      setStatus("This verification link is missing its token.");
+      setDone(true);
+      return;
+    }
+    invoke<string>("verify_email", { token })
+      .then((message) => setStatus(message))
+      .catch((error) => setStatus(errorMessage(error) || "An error occurred"))
+      .finally(() => setDone(true));
+  }, []);
+
+  return (
+    <div className="flex min-h-screen flex-col items-center justify-center p-24">
+      <h1 className="h2 border-b pb-2 mb-4">Verify email</h1>
+      <p className="mb-4">{status}</p>
+      {done && <Button onClick={() => window.location.replace("/")}>Continue</Button>}
+    </div>
+  );
+}
@@ -1,6 +1,6 @@
 "use client";
 import React, { useEffect, useState } from "react";
-import { getCookie, deleteCookie } from "cookies-next";
+import { invoke } from "@tauri-apps/api/tauri";
 import Link from "next/link";
 import { ModeToggle } from "./ModeToggle";
 import { appWindow } from "@tauri-apps/api/window";
@@ -12,9 +12,10 @@ const Navbar = () => {
   const [activePage, setActivePage] = useState<string>("");
 
   useEffect(() => {
-    const getData = () => {
-      let username = getCookie("username");
-      const email = getCookie("email");
+    const getData = async () => {
+      const user = await invoke<{ email: string; username: string } | null>("current_user").catch(() => null);
+      let username = user?.username;
+      const email = user?.email;
 
       if (username) {
         // Format username
@@ -31,10 +32,9 @@ const Navbar = () => {
     return activePage === page ? "border-l-4 border-blue-400" : "";
   };
 
-  const handleLogout = () => {
-    deleteCookie("username");
-    deleteCookie("email");
-    deleteCookie("token");
+  const handleLogout = async () => {
+    // Signs the app out even when the session cannot be ended on the server
+    await invoke("sign_out").catch(console.error);
     setUsername("");
     setEmail("");
     setActivePage("");
@@ -4,3 +4,15 @@ import { twMerge } from "tailwind-merge"
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs))
 }
+
+// Turns an error from a command or the server into one readable line.
+// Validation failures (422) list every invalid field.
+export function errorMessage(data: any): string {
+  if (data && Array.isArray(data.errors)) {
+    return data.errors.map((error: { field: string; message: string }) => `${error.field}: ${error.message}`).join(", ")
+  }
+  if (data instanceof Error || typeof data?.message === "string") {
+    return data.message
+  }
+  return typeof data === "string" ? data : JSON.stringify(data)
+}
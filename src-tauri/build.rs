@@ -1,3 +1,6 @@
 fn main() {
-  tauri_build::build()
+  // Only the desktop app is a Tauri app; the standalone server builds without its config
+  if std::env::var_os("CARGO_FEATURE_DESKTOP").is_some() {
+    tauri_build::build()
+  }
 }
@@ -0,0 +1,14 @@
+-- Tasks kept by the terminal client when working without a server.
+-- Same fields as the server's tasks table; dates and times are stored as ISO 8601 text.
+CREATE TABLE tasks (
+    task_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_email TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    checked BOOLEAN NOT NULL DEFAULT FALSE,
+    due_date TEXT,
+    due_time TEXT,
+    duration INTEGER,
+    priority INTEGER,
+    project TEXT
+);
@@ -0,0 +1,13 @@
+-- Habits and the days they were checked off, as on the server.
+CREATE TABLE habits (
+    habit_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_email TEXT NOT NULL,
+    name TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE habit_checkins (
+    habit_id INTEGER NOT NULL REFERENCES habits(habit_id) ON DELETE CASCADE,
+    day TEXT NOT NULL,
+    PRIMARY KEY (habit_id, day)
+);
@@ -0,0 +1,17 @@
+-- Baseline schema for the tables that predate migrations.
+CREATE TABLE IF NOT EXISTS users (
+    username TEXT NOT NULL,
+    email TEXT PRIMARY KEY,
+    password TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tasks (
+    task_id SERIAL PRIMARY KEY,
+    user_email TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    checked BOOLEAN NOT NULL DEFAULT FALSE,
+    date TEXT,
+    duration INTEGER,
+    priority INTEGER
+);
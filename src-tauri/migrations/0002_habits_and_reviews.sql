@@ -0,0 +1,29 @@
+CREATE TABLE habits (
+    habit_id SERIAL PRIMARY KEY,
+    user_email TEXT NOT NULL,
+    name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One row per day a habit was checked off.
+CREATE TABLE habit_checkins (
+    habit_id INTEGER NOT NULL REFERENCES habits(habit_id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    PRIMARY KEY (habit_id, day)
+);
+
+CREATE TABLE reviews (
+    review_id SERIAL PRIMARY KEY,
+    user_email TEXT NOT NULL,
+    period TEXT NOT NULL,
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    notes TEXT NOT NULL,
+    completed_count INTEGER NOT NULL,
+    slipped_count INTEGER NOT NULL,
+    overdue_count INTEGER NOT NULL,
+    carried_over INTEGER[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX reviews_user_email_idx ON reviews (user_email, start_date DESC);
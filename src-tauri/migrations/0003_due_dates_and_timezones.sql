@@ -0,0 +1,46 @@
+-- Replace the free-text task date with a typed due date and optional due time.
+ALTER TABLE tasks ADD COLUMN due_date DATE, ADD COLUMN due_time TIME;
+
+-- The old column was never validated, so well-shaped values like 2024-13-45 can still fail to
+-- parse. These return NULL for them instead of aborting the migration.
+CREATE FUNCTION pg_temp.try_date(value TEXT) RETURNS DATE AS $$
+BEGIN
+    RETURN value::date;
+EXCEPTION WHEN data_exception THEN
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION pg_temp.try_utc_timestamp(value TEXT) RETURNS TIMESTAMP AS $$
+BEGIN
+    RETURN value::timestamptz AT TIME ZONE 'UTC';
+EXCEPTION WHEN data_exception THEN
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Plain dates are taken as they are.
+UPDATE tasks SET due_date = pg_temp.try_date(date) WHERE date ~ '^\d{4}-\d{2}-\d{2}$';
+
+-- The window stored picked days with toISOString(), i.e. the user's local midnight in UTC:
+-- 2024-03-20T23:00:00.000Z is the 21st for someone at UTC+1. Nobody's timezone is known yet
+-- (the column below starts out as UTC for everyone), so the day is the nearest midnight rather
+-- than the UTC day, which is right for offsets less than 10 hours west or 12 hours east of UTC.
+-- Times from 10:00 to 12:00 UTC can be midnight on either side of the date line; those tasks
+-- get no due date, and the stored value is added to their description so it can be set again,
+-- as are values that could not be parsed at all.
+UPDATE tasks SET due_date = CASE
+        WHEN pg_temp.try_utc_timestamp(date)::time < '10:00' THEN pg_temp.try_utc_timestamp(date)::date
+        ELSE pg_temp.try_utc_timestamp(date)::date + 1
+    END
+WHERE date ~ '^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}(:\d{2}(\.\d+)?)?Z$'
+    AND pg_temp.try_utc_timestamp(date)::time NOT BETWEEN '10:00' AND '12:00';
+
+UPDATE tasks SET description = description || E'\n\nDue date before the upgrade: ' || date
+WHERE date IS NOT NULL AND due_date IS NULL;
+
+ALTER TABLE tasks DROP COLUMN date;
+DROP FUNCTION pg_temp.try_date(TEXT), pg_temp.try_utc_timestamp(TEXT);
+
+-- IANA timezone name used to evaluate deadlines, e.g. 'Europe/Berlin'.
+ALTER TABLE users ADD COLUMN timezone TEXT NOT NULL DEFAULT 'UTC';
@@ -0,0 +1,13 @@
+-- Either remind_at (absolute) or minutes_before (relative to the task's deadline) is set.
+-- fired_at is stamped when the scheduler claims the reminder, so it fires exactly once.
+CREATE TABLE reminders (
+    reminder_id SERIAL PRIMARY KEY,
+    task_id INTEGER NOT NULL REFERENCES tasks(task_id) ON DELETE CASCADE,
+    remind_at TIMESTAMPTZ,
+    minutes_before INTEGER CHECK (minutes_before >= 0),
+    fired_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((remind_at IS NULL) <> (minutes_before IS NULL))
+);
+
+CREATE INDEX reminders_pending_idx ON reminders (task_id) WHERE fired_at IS NULL;
@@ -0,0 +1,12 @@
+-- Optional grouping used to order related tasks.
+ALTER TABLE tasks ADD COLUMN project TEXT;
+
+-- task_id cannot start until blocked_by is finished.
+CREATE TABLE task_dependencies (
+    task_id INTEGER NOT NULL REFERENCES tasks(task_id) ON DELETE CASCADE,
+    blocked_by INTEGER NOT NULL REFERENCES tasks(task_id) ON DELETE CASCADE,
+    PRIMARY KEY (task_id, blocked_by),
+    CHECK (task_id <> blocked_by)
+);
+
+CREATE INDEX task_dependencies_blocked_by_idx ON task_dependencies (blocked_by);
@@ -0,0 +1,22 @@
+-- A session is one signed-in device. Its refresh tokens form a single rotation family.
+CREATE TABLE sessions (
+    session_id SERIAL PRIMARY KEY,
+    email TEXT NOT NULL,
+    device TEXT,
+    ip TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX sessions_email_idx ON sessions (email) WHERE revoked_at IS NULL;
+
+-- Only SHA-256 hashes of refresh tokens are stored. rotated_at is set once a token
+-- has been swapped for a new one; presenting it again revokes the session.
+CREATE TABLE refresh_tokens (
+    token_hash TEXT PRIMARY KEY,
+    session_id INTEGER NOT NULL REFERENCES sessions(session_id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    rotated_at TIMESTAMPTZ
+);
@@ -0,0 +1,8 @@
+-- Bumped by "log out everywhere"; tokens carrying an older version are rejected.
+ALTER TABLE users ADD COLUMN token_version INTEGER NOT NULL DEFAULT 0;
+
+-- Access tokens revoked before they expire, by their jti claim.
+CREATE TABLE revoked_tokens (
+    jti TEXT PRIMARY KEY,
+    expires_at TIMESTAMPTZ NOT NULL
+);
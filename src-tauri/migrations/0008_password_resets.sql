@@ -0,0 +1,10 @@
+-- Single-use password reset tokens. Only SHA-256 hashes are stored.
+CREATE TABLE password_resets (
+    token_hash TEXT PRIMARY KEY,
+    email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ
+);
+
+CREATE INDEX password_resets_email_idx ON password_resets (email) WHERE used_at IS NULL;
@@ -0,0 +1,14 @@
+-- Accounts start unverified. Accounts that existed before verification are treated as verified.
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMPTZ;
+UPDATE users SET email_verified_at = NOW();
+
+-- Verification emails sent, kept to throttle resends. Only SHA-256 hashes of tokens are stored.
+CREATE TABLE email_verifications (
+    token_hash TEXT PRIMARY KEY,
+    email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ
+);
+
+CREATE INDEX email_verifications_email_idx ON email_verifications (email, created_at);
@@ -0,0 +1,21 @@
+-- TOTP two-factor authentication. The secret is set on enrolment and only
+-- enforced once totp_enabled_at is set; totp_last_step stops a code being replayed.
+ALTER TABLE users ADD COLUMN totp_secret TEXT;
+ALTER TABLE users ADD COLUMN totp_enabled_at TIMESTAMPTZ;
+ALTER TABLE users ADD COLUMN totp_last_step BIGINT;
+
+-- One-time recovery codes, stored as SHA-256 hashes.
+CREATE TABLE recovery_codes (
+    email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    used_at TIMESTAMPTZ,
+    PRIMARY KEY (email, code_hash)
+);
+
+-- Second login step: issued after a correct password, swapped for a session with a valid code.
+CREATE TABLE login_challenges (
+    token_hash TEXT PRIMARY KEY,
+    email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,28 @@
+-- An email change waits here until the new address is verified.
+ALTER TABLE users ADD COLUMN pending_email TEXT;
+ALTER TABLE email_verifications ADD COLUMN new_email TEXT;
+
+-- A user's data follows an email change and is removed with the account.
+-- NOT VALID skips checking rows written before users had to exist.
+ALTER TABLE tasks ADD CONSTRAINT tasks_user_email_fkey FOREIGN KEY (user_email)
+    REFERENCES users(email) ON UPDATE CASCADE ON DELETE CASCADE NOT VALID;
+ALTER TABLE habits ADD CONSTRAINT habits_user_email_fkey FOREIGN KEY (user_email)
+    REFERENCES users(email) ON UPDATE CASCADE ON DELETE CASCADE NOT VALID;
+ALTER TABLE reviews ADD CONSTRAINT reviews_user_email_fkey FOREIGN KEY (user_email)
+    REFERENCES users(email) ON UPDATE CASCADE ON DELETE CASCADE NOT VALID;
+
+ALTER TABLE password_resets DROP CONSTRAINT password_resets_email_fkey,
+    ADD CONSTRAINT password_resets_email_fkey FOREIGN KEY (email)
+    REFERENCES users(email) ON UPDATE CASCADE ON DELETE CASCADE;
+ALTER TABLE email_verifications DROP CONSTRAINT email_verifications_email_fkey,
+    ADD CONSTRAINT email_verifications_email_fkey FOREIGN KEY (email)
+    REFERENCES users(email) ON UPDATE CASCADE ON DELETE CASCADE;
+ALTER TABLE recovery_codes DROP CONSTRAINT recovery_codes_email_fkey,
+    ADD CONSTRAINT recovery_codes_email_fkey FOREIGN KEY (email)
+    REFERENCES users(email) ON UPDATE CASCADE ON DELETE CASCADE;
+ALTER TABLE login_challenges DROP CONSTRAINT login_challenges_email_fkey,
+    ADD CONSTRAINT login_challenges_email_fkey FOREIGN KEY (email)
+    REFERENCES users(email) ON UPDATE CASCADE ON DELETE CASCADE;
+ALTER TABLE personal_access_tokens DROP CONSTRAINT personal_access_tokens_email_fkey,
+    ADD CONSTRAINT personal_access_tokens_email_fkey FOREIGN KEY (email)
+    REFERENCES users(email) ON UPDATE CASCADE ON DELETE CASCADE;
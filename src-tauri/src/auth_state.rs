@@ -0,0 +1,199 @@
+// Who is signed in to the desktop app.
+// The webview logs in over IPC and the embedded server records the outcome here (see `Desktop`),
+// telling the window through `auth-changed` events. Logins over HTTP, e.g. from the CLI, are not
+// the window's and leave this alone.
+// The tokens never reach the window: the commands sign their calls with the access token and
+// refresh the session themselves when it runs out (see `commands::signed_in`), and the refresh
+// token is kept in the credential store so the next launch can sign back in.
+
+use std::sync::{ Arc, Mutex };
+
+use serde::Serialize;
+use tauri::{ AppHandle, Manager };
+
+use kaizen::credentials::{ CredentialStore, StoredCredentials };
+use kaizen::server::services::auth::CurrentUser;
+
+/// Name of the event emitted to the window when the user signs in or out.
+pub const AUTH_CHANGED_EVENT: &str = "auth-changed";
+
+// Define a struct to hold authentication state, signed out by default
+#[derive(Default)]
+pub struct AuthState {
+    pub(crate) token: Option<String>, // Optional token string
+    pub(crate) logged_in: bool, // Boolean indicating whether user is logged in
+    pub(crate) user: Option<CurrentUser>, // Who is logged in
+    pub(crate) session_id: Option<i32>, // Server session the token belongs to
+    refresh_token: Option<String>, // Kept in the credential store, only used from Rust
+}
+
+/// Payload of `auth-changed`. The token stays on the Rust side.
+#[derive(Serialize, Clone, Debug)]
+struct AuthChanged {
+    logged_in: bool,
+    user: Option<CurrentUser>,
+}
+
+impl AuthState {
+    fn sign_out(&mut self) {
+        *self = AuthState::default();
+    }
+
+    fn is_user(&self, email: &str) -> bool {
+        self.user.as_ref().is_some_and(|user| user.email == email)
+    }
+
+    /// What the credential store should hold, if anyone is signed in.
+    fn credentials(&self) -> Option<StoredCredentials> {
+        let user = self.user.as_ref()?;
+        Some(StoredCredentials {
+            email: user.email.clone(),
+            username: user.username.clone(),
+            refresh_token: self.refresh_token.clone()?,
+        })
+    }
+}
+
+/// The access and refresh tokens of the app's session, if signed in.
+pub fn session_tokens(app: &AppHandle) -> Option<(String, String)> {
+    let state_mutex = app.try_state::<Mutex<AuthState>>()?;
+    let state = state_mutex.lock().unwrap();
+    Some((state.token.clone()?, state.refresh_token.clone()?))
+}
+
+/// Records a new session for a user and tells the window.
+pub fn signed_in(
+    app: &AppHandle,
+    user: CurrentUser,
+    session_id: i32,
+    token: String,
+    refresh_token: String
+) {
+    update(app, |state| {
+        *state = AuthState {
+            token: Some(token),
+            logged_in: true,
+            user: Some(user),
+            session_id: Some(session_id),
+            refresh_token: Some(refresh_token),
+        };
+        true
+    });
+}
+
+/// Swaps in refreshed tokens when they belong to the current session.
+pub fn refreshed(app: &AppHandle, session_id: i32, token: String, refresh_token: String) {
+    update(app, |state| {
+        if state.session_id == Some(session_id) {
+            state.token = Some(token);
+            state.refresh_token = Some(refresh_token);
+        }
+        false // Nothing the window shows has changed
+    });
+}
+
+/// Moves to the session that replaced the current one, e.g. after a password change.
+pub fn session_replaced(
+    app: &AppHandle,
+    old_session_id: i32,
+    session_id: i32,
+    token: String,
+    refresh_token: String
+) {
+    update(app, |state| {
+        if state.session_id == Some(old_session_id) {
+            state.session_id = Some(session_id);
+            state.token = Some(token);
+            state.refresh_token = Some(refresh_token);
+        }
+        false // Same user, still signed in
+    });
+}
+
+/// Signs out when the current session was ended.
+pub fn session_ended(app: &AppHandle, session_id: i32) {
+    update(app, |state| {
+        let ended = state.session_id == Some(session_id);
+        if ended {
+            state.sign_out();
+        }
+        ended
+    });
+}
+
+/// Signs out when every session of the current user was ended, e.g. by a password change.
+pub fn user_signed_out(app: &AppHandle, email: &str) {
+    update(app, |state| {
+        let ended = state.is_user(email);
+        if ended {
+            state.sign_out();
+        }
+        ended
+    });
+}
+
+/// Signs out whoever is signed in, without asking the server.
+pub fn sign_out(app: &AppHandle) {
+    update(app, |state| {
+        let was_logged_in = state.logged_in;
+        state.sign_out();
+        was_logged_in
+    });
+}
+
+/// The credentials saved by the last run, if any.
+/// A store that cannot be read is treated as empty, which signs the user out.
+pub fn saved_credentials(app: &AppHandle) -> Option<StoredCredentials> {
+    let store = app.try_state::<Arc<dyn CredentialStore>>()?;
+    match store.load() {
+        Ok(credentials) => credentials,
+        Err(err) => {
+            tracing::error!(error = %err, "credential load error");
+            None
+        }
+    }
+}
+
+/// Wipes the saved credentials, e.g. when the server no longer accepts them.
+pub fn forget_credentials(app: &AppHandle) {
+    persist(app, None);
+}
+
+/// Applies a change to the managed `AuthState` and emits `auth-changed` when it says so.
+/// Credentials are saved or wiped whenever the refresh token changes.
+fn update(app: &AppHandle, change: impl FnOnce(&mut AuthState) -> bool) {
+    let Some(state_mutex) = app.try_state::<Mutex<AuthState>>() else {
+        return;
+    };
+    let (event, credentials) = {
+        let mut state = state_mutex.lock().unwrap();
+        let refresh_token = state.refresh_token.clone();
+        let emit = change(&mut state);
+        let credentials = (state.refresh_token != refresh_token).then(|| state.credentials());
+        let event = emit.then(|| AuthChanged { logged_in: state.logged_in, user: state.user.clone() });
+        (event, credentials)
+    };
+    // File and window updates happen outside the lock
+    if let Some(credentials) = credentials {
+        persist(app, credentials);
+    }
+    if let Some(event) = event {
+        if let Err(err) = app.emit_all(AUTH_CHANGED_EVENT, event) {
+            tracing::warn!(error = %err, "auth event error");
+        }
+    }
+}
+
+/// Saves the credentials to the managed store, or wipes it for `None`.
+fn persist(app: &AppHandle, credentials: Option<StoredCredentials>) {
+    let Some(store) = app.try_state::<Arc<dyn CredentialStore>>() else {
+        return;
+    };
+    let result = match credentials {
+        Some(credentials) => store.save(&credentials),
+        None => store.clear(),
+    };
+    if let Err(err) = result {
+        tracing::error!(error = %err, "credential store error");
+    }
+}
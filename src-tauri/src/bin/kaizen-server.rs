@@ -0,0 +1,108 @@
+// Runs the Kaizen API on its own, for a shared backend on a machine without a desktop.
+//
+// Settings are environment variables (`DATABASE_URL`, `SERVER_HOST`, `SERVER_PORT`, `CORS_ORIGINS`,
+// `MAILER`, `JWT_*`, `LOG_FORMAT`, `LOG_DIR`, ...). Any not set in the environment are read from a file
+// of `KEY=value` lines: the one given with `--config`, else `KAIZEN_CONFIG`, else
+// /etc/kaizen/server.env or ./.env when they exist.
+//
+// The API is described at /openapi.json; build with `--features docs-ui` to browse it at /docs.
+
+use std::{ env, path::PathBuf, process::ExitCode, sync::Arc, thread };
+
+use kaizen::logging;
+use kaizen::server::{ self, lifecycle::ServerState, notifier::Headless };
+
+/// Config file used when none is given and it exists.
+const DEFAULT_CONFIG_FILE: &str = "/etc/kaizen/server.env";
+
+const USAGE: &str = "usage: kaizen-server [--config <file>]";
+
+fn main() -> ExitCode {
+    let config_file = match config_file() {
+        Ok(config_file) => config_file,
+        Err(message) => {
+            eprintln!("{message}\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    // Variables already in the environment win over the file
+    let loaded = match &config_file {
+        Some(path) => dotenv::from_path(path).map_err(|err| format!("failed to read {}: {err}", path.display())),
+        None => {
+            dotenv::dotenv().ok();
+            Ok(())
+        }
+    };
+    if let Err(message) = loaded {
+        eprintln!("{message}");
+        return ExitCode::FAILURE;
+    }
+
+    logging::init(env::var_os("LOG_DIR").map(PathBuf::from).as_deref());
+    if let Some(path) = &config_file {
+        tracing::info!(path = %path.display(), "loaded config file");
+    }
+
+    let lifecycle = server::start(Arc::new(Headless));
+
+    // Finish in-flight requests and close the database connections on Ctrl+C or SIGTERM
+    let signal_lifecycle = lifecycle.clone();
+    thread::spawn(move || {
+        if let Err(err) = wait_for_signal() {
+            tracing::error!(error = %err, "cannot listen for shutdown signals");
+            return;
+        }
+        tracing::info!("shutting down");
+        if !signal_lifecycle.stop(server::SHUTDOWN_TIMEOUT) {
+            tracing::warn!("server did not stop in time");
+            std::process::exit(1);
+        }
+    });
+
+    lifecycle.wait();
+    match lifecycle.status().state {
+        ServerState::Stopped => ExitCode::SUCCESS,
+        _ => ExitCode::FAILURE,
+    }
+}
+
+/// The config file to read, from the command line, `KAIZEN_CONFIG` or the default location.
+fn config_file() -> Result<Option<PathBuf>, String> {
+    let mut args = env::args().skip(1);
+    match (args.next().as_deref(), args.next(), args.next()) {
+        (None, _, _) => {}
+        (Some("--config"), Some(path), None) => {
+            return Ok(Some(PathBuf::from(path)));
+        }
+        (Some("--help" | "-h"), None, None) => {
+            println!("{USAGE}");
+            std::process::exit(0);
+        }
+        _ => {
+            return Err("unexpected arguments".to_string());
+        }
+    }
+
+    if let Some(path) = env::var_os("KAIZEN_CONFIG") {
+        return Ok(Some(PathBuf::from(path)));
+    }
+    let default = PathBuf::from(DEFAULT_CONFIG_FILE);
+    Ok(default.exists().then_some(default))
+}
+
+/// Blocks until the process is asked to stop.
+fn wait_for_signal() -> std::io::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        #[cfg(unix)]
+        {
+            let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+            tokio::select! {
+                result = tokio::signal::ctrl_c() => result,
+                _ = terminate.recv() => Ok(()),
+            }
+        }
+        #[cfg(not(unix))]
+        tokio::signal::ctrl_c().await
+    })
+}
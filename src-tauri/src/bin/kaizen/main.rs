@@ -0,0 +1,346 @@
+// Kaizen from the terminal: log in, list and change tasks, run a timer while working on one,
+// and plan the day on a full-screen view with `kaizen plan`.
+//
+// Tasks live on a Kaizen server by default, reached with the session saved by `kaizen login`.
+// `--local`, or `"backend": "local"` in the config file, keeps them in a SQLite file instead.
+// The config file is `$KAIZEN_HOME/config.json`, else `~/.config/kaizen/config.json`.
+
+mod output;
+mod plan;
+mod timer;
+
+use std::io::{ self, BufRead, Write };
+use std::process::ExitCode;
+
+use chrono::{ NaiveDate, NaiveTime };
+use clap::{ Args, Parser, Subcommand };
+use kaizen::client::config::{ Backend, Config };
+use kaizen::client::local::{ LocalStore, LOCAL_USER };
+use kaizen::client::remote::{ self, Login, Remote };
+use kaizen::client::{ ClientError, TaskBackend };
+use kaizen::server::services::tasks::AddTask;
+use kaizen::server::services::ServiceError;
+use output::Format;
+use serde_json::json;
+
+#[derive(Parser)]
+#[command(name = "kaizen", version, about = "Kaizen tasks from the terminal")]
+struct Cli {
+    /// Use the local database instead of the server
+    #[arg(long, global = true)]
+    local: bool,
+
+    /// Server address, e.g. https://kaizen.example.com
+    #[arg(long, global = true, conflicts_with = "local")]
+    server: Option<String>,
+
+    /// How results are printed
+    #[arg(long, short, global = true, value_enum, default_value_t = Format::Table)]
+    output: Format,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Log in to the server and save the session
+    Login {
+        #[arg(long)]
+        email: Option<String>,
+        /// Read the password from the first line of stdin instead of prompting
+        #[arg(long)]
+        password_stdin: bool,
+        /// Use a recovery code instead of the authenticator app
+        #[arg(long)]
+        recovery: bool,
+    },
+    /// End the session on the server and forget it
+    Logout,
+    /// List and change tasks
+    #[command(subcommand)]
+    Tasks(TaskCommand),
+    /// Count down while working on a task, for its duration unless given
+    Timer {
+        task_id: i32,
+        #[arg(long)]
+        minutes: Option<u32>,
+        /// Check the task off when the time is up
+        #[arg(long)]
+        check: bool,
+    },
+    /// Plan the day: today's and upcoming tasks, habits and a focus timer, all from the keyboard
+    Plan,
+}
+
+#[derive(Subcommand)]
+enum TaskCommand {
+    /// List open tasks
+    List {
+        /// Include checked off tasks
+        #[arg(long)]
+        all: bool,
+        #[arg(long)]
+        project: Option<String>,
+        /// Leave out tasks waiting on an unfinished blocker
+        #[arg(long)]
+        hide_blocked: bool,
+    },
+    /// Add a task
+    Add {
+        title: String,
+        #[command(flatten)]
+        fields: TaskFields,
+    },
+    /// Check a task off
+    #[command(alias = "done")]
+    Complete {
+        task_id: i32,
+        /// Reopen the task instead
+        #[arg(long)]
+        undo: bool,
+    },
+    /// Change a task's fields
+    Edit {
+        task_id: i32,
+        #[arg(long)]
+        title: Option<String>,
+        #[command(flatten)]
+        fields: TaskFields,
+        /// Remove the due date and time
+        #[arg(long, conflicts_with_all = ["due", "at"])]
+        no_due: bool,
+        /// Remove the task from its project
+        #[arg(long, conflicts_with = "project")]
+        no_project: bool,
+    },
+    /// Delete a task
+    Delete {
+        task_id: i32,
+    },
+}
+
+/// Fields shared by `add` and `edit`.
+#[derive(Args)]
+struct TaskFields {
+    #[arg(long, short)]
+    description: Option<String>,
+    /// Due date, YYYY-MM-DD
+    #[arg(long)]
+    due: Option<NaiveDate>,
+    /// Due time, HH:MM in your timezone
+    #[arg(long, value_parser = parse_time)]
+    at: Option<NaiveTime>,
+    /// Expected minutes of work
+    #[arg(long)]
+    duration: Option<i32>,
+    /// 1 is the most important, 5 the least
+    #[arg(long, short)]
+    priority: Option<i32>,
+    #[arg(long)]
+    project: Option<String>,
+}
+
+impl TaskFields {
+    /// Overwrites the fields that were given.
+    fn apply(self, task: &mut AddTask) {
+        if let Some(description) = self.description {
+            task.description = description;
+        }
+        if self.due.is_some() {
+            task.due_date = self.due;
+        }
+        if self.at.is_some() {
+            task.due_time = self.at;
+        }
+        if self.duration.is_some() {
+            task.duration = self.duration;
+        }
+        if self.priority.is_some() {
+            task.priority = self.priority;
+        }
+        if self.project.is_some() {
+            task.project = self.project;
+        }
+    }
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| "expected HH:MM".to_string())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            if let ClientError::Service(ServiceError::Invalid(invalid)) = &err {
+                for error in &invalid.errors {
+                    eprintln!("  {}: {}", error.field, error.message);
+                }
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), ClientError> {
+    let mut config = Config::load()?;
+    let use_local = cli.local || (cli.server.is_none() && config.backend == Backend::Local);
+    if let Some(server) = cli.server {
+        config.server = Some(server);
+    }
+
+    match cli.command {
+        Command::Login { email, password_stdin, recovery } => {
+            login(config, cli.output, email, password_stdin, recovery)
+        }
+        Command::Logout => {
+            let email = Remote::new(config).and_then(|remote| {
+                let email = remote.session().email.clone();
+                remote.logout().map(|_| email)
+            })?;
+            output::message(cli.output, &format!("Logged out {email}"), json!({"email": email}));
+            Ok(())
+        }
+        Command::Tasks(command) => {
+            let mut backend = open_backend(config, use_local)?;
+            tasks(&mut *backend, cli.output, command)
+        }
+        Command::Timer { task_id, minutes, check } => {
+            let mut backend = open_backend(config, use_local)?;
+            timer::run(&mut *backend, cli.output, task_id, minutes, check)
+        }
+        Command::Plan => {
+            let source = if use_local { "local".to_string() } else { config.server_url() };
+            let mut backend = open_backend(config, use_local)?;
+            plan::run(&mut *backend, source)
+        }
+    }
+}
+
+/// The local database, or the server with the saved session.
+fn open_backend(config: Config, use_local: bool) -> Result<Box<dyn TaskBackend>, ClientError> {
+    if !use_local {
+        return Ok(Box::new(Remote::new(config)?));
+    }
+    let email = config.session.as_ref().map_or(LOCAL_USER, |session| &session.email);
+    Ok(Box::new(LocalStore::open(&config.database_path(), email, config.timezone())?))
+}
+
+fn login(
+    mut config: Config,
+    format: Format,
+    email: Option<String>,
+    password_stdin: bool,
+    recovery: bool
+) -> Result<(), ClientError> {
+    let email = match email {
+        Some(email) => email,
+        None => prompt("Email: ")?,
+    };
+    let password = if password_stdin {
+        read_line()?
+    } else {
+        rpassword::prompt_password("Password: ")?
+    };
+
+    let session = match remote::login(&mut config, &email, &password)? {
+        Login::SignedIn(session) => session,
+        Login::TwoFactorRequired { challenge_token } if recovery => {
+            let code = prompt("Recovery code: ")?;
+            remote::verify_two_factor(&mut config, &challenge_token, None, Some(&code))?
+        }
+        Login::TwoFactorRequired { challenge_token } => {
+            let code = prompt("Code from your authenticator app: ")?;
+            remote::verify_two_factor(&mut config, &challenge_token, Some(&code), None)?
+        }
+    };
+    output::message(
+        format,
+        &format!("Logged in as {} <{}>", session.username, session.email),
+        json!({"email": session.email, "username": session.username})
+    );
+    Ok(())
+}
+
+fn tasks(backend: &mut dyn TaskBackend, format: Format, command: TaskCommand) -> Result<(), ClientError> {
+    match command {
+        TaskCommand::List { all, project, hide_blocked } => {
+            let mut tasks = backend.list_tasks()?;
+            tasks.retain(|task| {
+                (all || !task.checked) &&
+                    (!hide_blocked || !task.blocked) &&
+                    (project.is_none() || task.project == project)
+            });
+            // Open before done, then the soonest deadline, then the most important
+            tasks.sort_by_key(|task| {
+                (
+                    task.checked,
+                    task.due_date.is_none(),
+                    task.due_date,
+                    task.due_time,
+                    task.priority.unwrap_or(i32::MAX),
+                )
+            });
+            output::tasks(format, &tasks);
+        }
+        TaskCommand::Add { title, fields } => {
+            let mut task = AddTask {
+                user_email: backend.user_email().to_string(),
+                title,
+                description: String::new(),
+                due_date: None,
+                due_time: None,
+                duration: None,
+                priority: None,
+                project: None,
+            };
+            fields.apply(&mut task);
+            backend.create_task(&task)?;
+            output::message(format, &format!("Added \"{}\"", task.title), json!(task));
+        }
+        TaskCommand::Complete { task_id, undo } => {
+            let checked = backend.check_task(task_id, !undo)?;
+            output::checked(format, &checked);
+        }
+        TaskCommand::Edit { task_id, title, fields, no_due, no_project } => {
+            let mut task = AddTask::from(&backend.task(task_id)?);
+            if let Some(title) = title {
+                task.title = title;
+            }
+            fields.apply(&mut task);
+            if no_due {
+                task.due_date = None;
+                task.due_time = None;
+            }
+            if no_project {
+                task.project = None;
+            }
+            backend.update_task(task_id, &task)?;
+            output::task(format, &backend.task(task_id)?);
+        }
+        TaskCommand::Delete { task_id } => {
+            backend.delete_task(task_id)?;
+            output::message(
+                format,
+                &format!("Deleted task {task_id}"),
+                json!({"task_id": task_id, "deleted": true})
+            );
+        }
+    }
+    Ok(())
+}
+
+fn prompt(label: &str) -> Result<String, ClientError> {
+    print!("{label}");
+    io::stdout().flush()?;
+    read_line()
+}
+
+fn read_line() -> Result<String, ClientError> {
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
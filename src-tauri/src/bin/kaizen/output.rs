@@ -0,0 +1,125 @@
+// Prints results as aligned tables for people or as JSON for scripts.
+
+use clap::ValueEnum;
+use kaizen::server::services::tasks::{ CheckedTask, Task };
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+}
+
+pub fn tasks(format: Format, tasks: &[Task]) {
+    match format {
+        Format::Json => print_json(&tasks),
+        Format::Table if tasks.is_empty() => println!("No tasks"),
+        Format::Table => print!("{}", task_table(tasks)),
+    }
+}
+
+pub fn task(format: Format, task: &Task) {
+    match format {
+        Format::Json => print_json(task),
+        Format::Table => print!("{}", task_table(std::slice::from_ref(task))),
+    }
+}
+
+pub fn checked(format: Format, checked: &CheckedTask) {
+    if format == Format::Json {
+        return print_json(checked);
+    }
+    let action = if checked.checked { "Checked off" } else { "Reopened" };
+    println!("{action} task {}", checked.task_id);
+    if !checked.unblocked.is_empty() {
+        let ids: Vec<String> = checked.unblocked.iter().map(i32::to_string).collect();
+        println!("Unblocked {}", ids.join(", "));
+    }
+}
+
+/// A sentence for people, or the value for scripts.
+pub fn message(format: Format, text: &str, value: Value) {
+    match format {
+        Format::Json => print_json(&value),
+        Format::Table => println!("{text}"),
+    }
+}
+
+pub fn print_json(value: &impl Serialize) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{json}"),
+        Err(err) => eprintln!("error: {err}"),
+    }
+}
+
+fn task_table(tasks: &[Task]) -> String {
+    let rows = tasks
+        .iter()
+        .map(|task| {
+            let due = match (task.due_date, task.due_time) {
+                (Some(date), Some(time)) => format!("{date} {}", time.format("%H:%M")),
+                (Some(date), None) => date.to_string(),
+                _ => String::new(),
+            };
+            let mut state = Vec::new();
+            if task.overdue {
+                state.push("overdue");
+            }
+            if task.blocked {
+                state.push("blocked");
+            }
+            vec![
+                task.task_id.to_string(),
+                if task.checked { "x" } else { "" }.to_string(),
+                task.title.clone(),
+                due,
+                task.duration.map(|minutes| minutes.to_string()).unwrap_or_default(),
+                task.priority.map(|priority| priority.to_string()).unwrap_or_default(),
+                task.project.clone().unwrap_or_default(),
+                state.join(", "),
+            ]
+        })
+        .collect();
+    table(&["ID", "DONE", "TITLE", "DUE", "MIN", "PRI", "PROJECT", "STATE"], rows)
+}
+
+/// Left-aligned columns as wide as their longest cell, two spaces apart.
+fn table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header = headers.iter().map(|header| header.to_string()).collect();
+    let mut out = String::new();
+    for row in std::iter::once(header).chain(rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        out.push_str(line.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligns_columns() {
+        let rows = vec![
+            vec!["1".to_string(), "Write the report".to_string(), "".to_string()],
+            vec!["12".to_string(), "Call".to_string(), "overdue".to_string()]
+        ];
+        assert_eq!(
+            table(&["ID", "TITLE", "STATE"], rows),
+            "ID  TITLE             STATE\n1   Write the report\n12  Call              overdue\n"
+        );
+    }
+}
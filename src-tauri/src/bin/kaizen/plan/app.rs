@@ -0,0 +1,413 @@
+// State of the planning screen and what each key does.
+
+use std::time::{ Duration, Instant };
+
+use chrono::{ Days, Local, NaiveDate };
+use kaizen::client::{ ClientError, TaskBackend };
+use kaizen::server::services::habits::Habit;
+use kaizen::server::services::tasks::{ AddTask, Task };
+use ratatui::crossterm::event::{ KeyCode, KeyEvent, KeyModifiers };
+
+use crate::timer::DEFAULT_MINUTES;
+
+/// Priority assumed for tasks without one when it is raised or lowered.
+const MIDDLE_PRIORITY: i32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pane {
+    Today,
+    Upcoming,
+    Habits,
+}
+
+impl Pane {
+    const ALL: [Pane; 3] = [Pane::Today, Pane::Upcoming, Pane::Habits];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Text being typed, and what it will become.
+pub enum Input {
+    Task(String),
+    Habit(String),
+}
+
+/// A focus timer on a task.
+pub struct Focus {
+    pub task_id: i32,
+    pub title: String,
+    started: Instant,
+    length: Duration,
+    pub finished: bool,
+}
+
+impl Focus {
+    pub fn left(&self) -> Duration {
+        self.length.saturating_sub(self.started.elapsed())
+    }
+
+    /// How much of the time has passed, from 0 to 1.
+    pub fn progress(&self) -> f64 {
+        (self.started.elapsed().as_secs_f64() / self.length.as_secs_f64().max(1.0)).min(1.0)
+    }
+}
+
+pub struct App<'a> {
+    backend: &'a mut dyn TaskBackend,
+    pub source: String, // Where the tasks are kept, for the title
+    pub today: NaiveDate,
+    pub tasks: Vec<Task>,
+    pub habits: Vec<Habit>,
+    pub pane: Pane,
+    selected: [usize; 3], // Selected row of each pane
+    pub input: Option<Input>,
+    pub focus: Option<Focus>,
+    pub status: String,
+    pub bell: bool, // Ring the terminal bell on the next draw
+    pub quit: bool,
+}
+
+/// Today's tasks and the ones after.
+/// Today has what is due today or overdue, with what was finished today last.
+/// Upcoming has open tasks due later, soonest first, then those without a date.
+pub fn plan(tasks: &[Task], today: NaiveDate) -> (Vec<&Task>, Vec<&Task>) {
+    let mut now: Vec<&Task> = tasks
+        .iter()
+        .filter(|task| match task.due_date {
+            Some(date) => date == today || (date < today && !task.checked),
+            None => false,
+        })
+        .collect();
+    now.sort_by_key(|task| (task.checked, task.due_date, task.due_time, task.priority.unwrap_or(i32::MAX)));
+
+    let mut later: Vec<&Task> = tasks
+        .iter()
+        .filter(|task| !task.checked && task.due_date.map_or(true, |date| date > today))
+        .collect();
+    later.sort_by_key(|task| {
+        (task.due_date.is_none(), task.due_date, task.due_time, task.priority.unwrap_or(i32::MAX))
+    });
+    (now, later)
+}
+
+impl<'a> App<'a> {
+    pub fn new(backend: &'a mut dyn TaskBackend, source: String) -> Result<Self, ClientError> {
+        let mut app = App {
+            backend,
+            source,
+            today: Local::now().date_naive(),
+            tasks: Vec::new(),
+            habits: Vec::new(),
+            pane: Pane::Today,
+            selected: [0; 3],
+            input: None,
+            focus: None,
+            status: String::new(),
+            bell: false,
+            quit: false,
+        };
+        app.reload()?;
+        Ok(app)
+    }
+
+    /// Selected row of a pane, `None` when it is empty.
+    pub fn selected(&self, pane: Pane) -> Option<usize> {
+        let len = self.len(pane);
+        (len > 0).then(|| self.selected[pane.index()].min(len - 1))
+    }
+
+    fn len(&self, pane: Pane) -> usize {
+        let (today, upcoming) = plan(&self.tasks, self.today);
+        match pane {
+            Pane::Today => today.len(),
+            Pane::Upcoming => upcoming.len(),
+            Pane::Habits => self.habits.len(),
+        }
+    }
+
+    fn selected_task(&self) -> Option<Task> {
+        let (today, upcoming) = plan(&self.tasks, self.today);
+        let row = self.selected(self.pane)?;
+        match self.pane {
+            Pane::Today => today.get(row).map(|task| (*task).clone()),
+            Pane::Upcoming => upcoming.get(row).map(|task| (*task).clone()),
+            Pane::Habits => None,
+        }
+    }
+
+    /// Moves the clock on: the day may have changed and the focus timer may be up.
+    pub fn tick(&mut self) {
+        self.today = Local::now().date_naive();
+        if let Some(focus) = self.focus.as_mut().filter(|focus| !focus.finished) {
+            if focus.left().is_zero() {
+                focus.finished = true;
+                self.status = format!("Time is up for \"{}\"", focus.title);
+                self.bell = true;
+            }
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if self.input.is_some() {
+            return self.handle_input(key);
+        }
+        self.status.clear();
+
+        let result = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.quit = true;
+                Ok(())
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.quit = true;
+                Ok(())
+            }
+            KeyCode::Tab => {
+                self.pane = Pane::ALL[(self.pane.index() + 1) % Pane::ALL.len()];
+                Ok(())
+            }
+            KeyCode::BackTab => {
+                self.pane = Pane::ALL[(self.pane.index() + Pane::ALL.len() - 1) % Pane::ALL.len()];
+                Ok(())
+            }
+            KeyCode::Char('1') => {
+                self.pane = Pane::Today;
+                Ok(())
+            }
+            KeyCode::Char('2') => {
+                self.pane = Pane::Upcoming;
+                Ok(())
+            }
+            KeyCode::Char('3') => {
+                self.pane = Pane::Habits;
+                Ok(())
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.move_selection(1);
+                Ok(())
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.move_selection(-1);
+                Ok(())
+            }
+            KeyCode::Char(' ' | 'x') | KeyCode::Enter => self.check(),
+            KeyCode::Char('a') => {
+                self.input = Some(match self.pane {
+                    Pane::Habits => Input::Habit(String::new()),
+                    _ => Input::Task(String::new()),
+                });
+                Ok(())
+            }
+            KeyCode::Char('+' | '=') => self.change_priority(-1),
+            KeyCode::Char('-') => self.change_priority(1),
+            KeyCode::Char('>' | '.') => {
+                let today = self.today;
+                // Overdue tasks move to tomorrow rather than to another past day
+                self.reschedule(|due| due.map_or(today, |due| due.max(today)) + Days::new(1))
+            }
+            KeyCode::Char('<' | ',') => {
+                let today = self.today;
+                // Never into the past
+                self.reschedule(|due| (due.unwrap_or(today) - Days::new(1)).max(today))
+            }
+            KeyCode::Char('t') => {
+                let today = self.today;
+                self.reschedule(|_| today)
+            }
+            KeyCode::Char('f') => {
+                self.toggle_focus();
+                Ok(())
+            }
+            KeyCode::Char('r') => self.reload(),
+            _ => Ok(()),
+        };
+        if let Err(err) = result {
+            self.status = format!("Error: {err}");
+        }
+    }
+
+    fn handle_input(&mut self, key: KeyEvent) {
+        let Some(Input::Task(text) | Input::Habit(text)) = self.input.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.input = None;
+            }
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Enter => {
+                if let Err(err) = self.submit() {
+                    self.status = format!("Error: {err}");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Adds what was typed: a task due today from the Today pane, a task without a date
+    /// from the Upcoming pane, or a habit.
+    fn submit(&mut self) -> Result<(), ClientError> {
+        let Some(input) = self.input.take() else {
+            return Ok(());
+        };
+        match input {
+            Input::Task(title) if !title.trim().is_empty() => {
+                let task = AddTask {
+                    user_email: self.backend.user_email().to_string(),
+                    title: title.trim().to_string(),
+                    description: String::new(),
+                    due_date: (self.pane == Pane::Today).then_some(self.today),
+                    due_time: None,
+                    duration: None,
+                    priority: None,
+                    project: None,
+                };
+                self.backend.create_task(&task)?;
+                self.status = format!("Added \"{}\"", task.title);
+            }
+            Input::Habit(name) if !name.trim().is_empty() => {
+                self.backend.create_habit(name.trim())?;
+                self.status = format!("Added habit \"{}\"", name.trim());
+            }
+            _ => {
+                return Ok(());
+            }
+        }
+        self.reload()
+    }
+
+    fn move_selection(&mut self, step: isize) {
+        let Some(row) = self.selected(self.pane) else {
+            return;
+        };
+        let last = self.len(self.pane) - 1;
+        self.selected[self.pane.index()] = row.saturating_add_signed(step).min(last);
+    }
+
+    /// Checks the selected task off or reopens it, or checks the selected habit off for today.
+    fn check(&mut self) -> Result<(), ClientError> {
+        if self.pane == Pane::Habits {
+            let Some(habit) = self.selected(Pane::Habits).map(|row| self.habits[row].clone()) else {
+                return Ok(());
+            };
+            self.backend.check_habit(habit.habit_id, self.today)?;
+            self.status = format!("Checked off \"{}\" for today", habit.name);
+            return self.reload();
+        }
+
+        let Some(task) = self.selected_task() else {
+            return Ok(());
+        };
+        let checked = self.backend.check_task(task.task_id, !task.checked)?;
+        self.status = if checked.checked {
+            format!("Checked off \"{}\"", task.title)
+        } else {
+            format!("Reopened \"{}\"", task.title)
+        };
+        if !checked.unblocked.is_empty() {
+            self.status.push_str(&format!(", unblocked {} task(s)", checked.unblocked.len()));
+        }
+        self.reload()
+    }
+
+    /// Raises (negative step) or lowers the selected task's priority, 1 being the highest.
+    fn change_priority(&mut self, step: i32) -> Result<(), ClientError> {
+        self.edit(|task| {
+            task.priority = Some((task.priority.unwrap_or(MIDDLE_PRIORITY) + step).clamp(1, 5));
+        })
+    }
+
+    /// Moves the selected task to another day, keeping its time.
+    fn reschedule(&mut self, day: impl FnOnce(Option<NaiveDate>) -> NaiveDate) -> Result<(), ClientError> {
+        self.edit(|task| {
+            task.due_date = Some(day(task.due_date));
+        })
+    }
+
+    fn edit(&mut self, change: impl FnOnce(&mut AddTask)) -> Result<(), ClientError> {
+        let Some(task) = self.selected_task() else {
+            return Ok(());
+        };
+        let mut edited = AddTask::from(&task);
+        change(&mut edited);
+        self.backend.update_task(task.task_id, &edited)?;
+        self.reload()
+    }
+
+    /// Starts a focus timer on the selected task, for its duration, or stops the running one.
+    fn toggle_focus(&mut self) {
+        if let Some(focus) = self.focus.take().filter(|focus| !focus.finished) {
+            self.status = format!("Stopped focusing on \"{}\"", focus.title);
+            return;
+        }
+        let Some(task) = self.selected_task() else {
+            self.status = "Select a task to focus on".to_string();
+            return;
+        };
+        let minutes = task.duration
+            .and_then(|duration| u32::try_from(duration).ok())
+            .unwrap_or(DEFAULT_MINUTES);
+        self.status = format!("Focusing on \"{}\" for {minutes} min", task.title);
+        self.focus = Some(Focus {
+            task_id: task.task_id,
+            title: task.title,
+            started: Instant::now(),
+            length: Duration::from_secs(u64::from(minutes) * 60),
+            finished: false,
+        });
+    }
+
+    pub fn reload(&mut self) -> Result<(), ClientError> {
+        self.tasks = self.backend.list_tasks()?;
+        self.habits = self.backend.list_habits()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(task_id: i32, due_date: Option<NaiveDate>, checked: bool, priority: Option<i32>) -> Task {
+        Task {
+            task_id,
+            user_email: "ada@example.com".to_string(),
+            title: format!("Task {task_id}"),
+            description: String::new(),
+            checked,
+            due_date,
+            due_time: None,
+            duration: None,
+            priority,
+            project: None,
+            overdue: false,
+            blocked_by: Vec::new(),
+            blocked: false,
+        }
+    }
+
+    #[test]
+    fn splits_today_from_upcoming() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let yesterday = today.pred_opt();
+        let tomorrow = today.succ_opt();
+        let tasks = vec![
+            task(1, Some(today), true, None),
+            task(2, Some(today), false, Some(2)),
+            task(3, yesterday, false, None),
+            task(4, yesterday, true, None),
+            task(5, None, false, Some(1)),
+            task(6, tomorrow, false, None)
+        ];
+
+        let (now, later) = plan(&tasks, today);
+        let ids = |tasks: Vec<&Task>| tasks.iter().map(|task| task.task_id).collect::<Vec<_>>();
+        assert_eq!(ids(now), [3, 2, 1]);
+        assert_eq!(ids(later), [6, 5]);
+    }
+}
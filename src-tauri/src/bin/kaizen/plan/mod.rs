@@ -0,0 +1,48 @@
+// `kaizen plan`: a full-screen, keyboard-driven view of the day.
+// Works on whichever backend the other commands use, so it runs offline on the local database.
+
+mod app;
+mod ui;
+
+use std::io::Write;
+use std::time::Duration;
+
+use kaizen::client::{ ClientError, TaskBackend };
+use ratatui::crossterm::event::{ self, Event, KeyEventKind };
+use ratatui::DefaultTerminal;
+
+use app::App;
+
+/// How often the screen is redrawn while no key is pressed, for the focus timer.
+const TICK: Duration = Duration::from_millis(250);
+
+/// Shows the planning screen until the user quits. The terminal is restored even on a panic.
+pub fn run(backend: &mut dyn TaskBackend, source: String) -> Result<(), ClientError> {
+    let mut app = App::new(backend, source)?;
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut app);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> Result<(), ClientError> {
+    while !app.quit {
+        terminal.draw(|frame| ui::draw(frame, app))?;
+        if app.bell {
+            // Through the terminal's own writer, so it cannot land in the middle of a frame
+            app.bell = false;
+            terminal.backend_mut().write_all(b"\x07")?;
+            terminal.backend_mut().flush()?;
+        }
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                // Windows also reports releases
+                if key.kind == KeyEventKind::Press {
+                    app.handle_key(key);
+                }
+            }
+        }
+        app.tick();
+    }
+    Ok(())
+}
@@ -0,0 +1,143 @@
+// Draws the planning screen: today's tasks and upcoming ones on the left,
+// habits and the focus timer on the right, and a line for messages and typing at the bottom.
+
+use ratatui::layout::{ Constraint, Layout, Rect };
+use ratatui::style::{ Color, Modifier, Style };
+use ratatui::text::{ Line, Span };
+use ratatui::widgets::{ Block, Gauge, List, ListItem, ListState, Paragraph };
+use ratatui::Frame;
+
+use kaizen::server::services::tasks::Task;
+
+use super::app::{ plan, App, Input, Pane };
+use crate::timer::clock;
+
+const HELP: &str =
+    "a add  x done  +/- priority  </> move day  t today  f focus  tab pane  r reload  q quit";
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [title, body, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ]).areas(frame.area());
+    let [left, right] = Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)]).areas(body);
+    let [today_area, upcoming_area] = Layout::vertical([
+        Constraint::Percentage(55),
+        Constraint::Percentage(45),
+    ]).areas(left);
+    let [habits_area, focus_area] = Layout::vertical([Constraint::Min(0), Constraint::Length(5)]).areas(right);
+
+    let heading = format!(" Kaizen · {} · {}", app.today.format("%A %-d %B"), app.source);
+    frame.render_widget(Paragraph::new(heading).style(Style::new().add_modifier(Modifier::BOLD)), title);
+
+    let (today, upcoming) = plan(&app.tasks, app.today);
+    let focused = app.focus.as_ref().filter(|focus| !focus.finished).map(|focus| focus.task_id);
+    let today_items = today
+        .iter()
+        .map(|task| task_item(task, task.due_time.map(|time| time.format("%H:%M").to_string()), focused))
+        .collect();
+    draw_list(frame, app, Pane::Today, format!(" Today ({}) ", today.len()), today_items, today_area);
+
+    let upcoming_items = upcoming
+        .iter()
+        .map(|task| {
+            let when = match (task.due_date, task.due_time) {
+                (Some(date), Some(time)) => format!("{} {}", date.format("%a %-d %b"), time.format("%H:%M")),
+                (Some(date), None) => date.format("%a %-d %b").to_string(),
+                _ => "someday".to_string(),
+            };
+            task_item(task, Some(when), focused)
+        })
+        .collect();
+    draw_list(frame, app, Pane::Upcoming, format!(" Upcoming ({}) ", upcoming.len()), upcoming_items, upcoming_area);
+
+    let habit_items = app.habits
+        .iter()
+        .map(|habit| {
+            let done = habit.last_checked == Some(app.today);
+            let style = if done { Style::new().fg(Color::Green) } else { Style::new() };
+            ListItem::new(Line::styled(format!("[{}] {}", if done { "x" } else { " " }, habit.name), style))
+        })
+        .collect();
+    draw_list(frame, app, Pane::Habits, " Habits ".to_string(), habit_items, habits_area);
+
+    draw_focus(frame, app, focus_area);
+    draw_footer(frame, app, footer);
+}
+
+/// One task row: checkbox, when it is due, title, priority, project and state.
+fn task_item(task: &Task, when: Option<String>, focused: Option<i32>) -> ListItem<'static> {
+    let mut style = Style::new();
+    if task.checked {
+        style = style.fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT);
+    } else if task.overdue {
+        style = style.fg(Color::Red);
+    } else if task.blocked {
+        style = style.fg(Color::DarkGray);
+    }
+
+    let marker = if focused == Some(task.task_id) { "▶" } else { " " };
+    let mut spans = vec![
+        Span::raw(format!("{marker}[{}] ", if task.checked { "x" } else { " " })),
+        Span::styled(format!("{:<16}", when.unwrap_or_default()), Style::new().fg(Color::Blue)),
+        Span::styled(task.title.clone(), style)
+    ];
+    if let Some(priority) = task.priority {
+        let color = if priority <= 2 { Color::Yellow } else { Color::Gray };
+        spans.push(Span::styled(format!("  P{priority}"), Style::new().fg(color)));
+    }
+    if let Some(project) = &task.project {
+        spans.push(Span::styled(format!("  #{project}"), Style::new().fg(Color::Magenta)));
+    }
+    if task.overdue {
+        spans.push(Span::styled("  overdue", Style::new().fg(Color::Red)));
+    }
+    if task.blocked {
+        spans.push(Span::styled("  blocked", Style::new().fg(Color::DarkGray)));
+    }
+    ListItem::new(Line::from(spans))
+}
+
+/// A pane's list, highlighted and scrolled to its selection when it is the active pane.
+fn draw_list(frame: &mut Frame, app: &App, pane: Pane, title: String, items: Vec<ListItem>, area: Rect) {
+    let active = app.pane == pane;
+    let border = if active { Style::new().fg(Color::Cyan) } else { Style::new().fg(Color::DarkGray) };
+    let list = List::new(items)
+        .block(Block::bordered().title(title).border_style(border))
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(app.selected(pane).filter(|_| active));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_focus(frame: &mut Frame, app: &App, area: Rect) {
+    let block = Block::bordered().title(" Focus ").border_style(Style::new().fg(Color::DarkGray));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let Some(focus) = &app.focus else {
+        frame.render_widget(Paragraph::new("Press f on a task to focus on it"), inner);
+        return;
+    };
+    let [text_area, gauge_area] = Layout::vertical([Constraint::Length(2), Constraint::Length(1)]).areas(inner);
+    let left = if focus.finished {
+        Span::styled("Time is up", Style::new().fg(Color::Green).add_modifier(Modifier::BOLD))
+    } else {
+        Span::styled(format!("{} left", clock(focus.left())), Style::new().add_modifier(Modifier::BOLD))
+    };
+    frame.render_widget(Paragraph::new(vec![Line::raw(focus.title.clone()), Line::from(left)]), text_area);
+    frame.render_widget(
+        Gauge::default().gauge_style(Style::new().fg(Color::Cyan)).ratio(focus.progress()).label(""),
+        gauge_area
+    );
+}
+
+fn draw_footer(frame: &mut Frame, app: &App, area: Rect) {
+    let line = match &app.input {
+        Some(Input::Task(text)) => Line::raw(format!("New task: {text}█")),
+        Some(Input::Habit(text)) => Line::raw(format!("New habit: {text}█")),
+        None if !app.status.is_empty() => Line::raw(app.status.clone()),
+        None => Line::styled(HELP, Style::new().fg(Color::DarkGray)),
+    };
+    frame.render_widget(Paragraph::new(line), area);
+}
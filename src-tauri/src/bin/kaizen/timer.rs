@@ -0,0 +1,91 @@
+// A countdown for working on one task, by default for the minutes the task is expected to take.
+
+use std::io::{ self, IsTerminal, Write };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use chrono::{ Local, Utc };
+use kaizen::client::{ ClientError, TaskBackend };
+use serde_json::json;
+
+use crate::output::{ self, Format };
+
+/// Length of a timer for a task without a duration.
+pub const DEFAULT_MINUTES: u32 = 25;
+
+/// Counts down, showing the time left on one line when printing to a terminal,
+/// and checks the task off at the end when asked. Ctrl+C stops the timer.
+pub fn run(
+    backend: &mut dyn TaskBackend,
+    format: Format,
+    task_id: i32,
+    minutes: Option<u32>,
+    check: bool
+) -> Result<(), ClientError> {
+    let task = backend.task(task_id)?;
+    let minutes = minutes
+        .or(task.duration.and_then(|duration| u32::try_from(duration).ok()))
+        .unwrap_or(DEFAULT_MINUTES);
+    let length = Duration::from_secs(u64::from(minutes) * 60);
+    let started_at = Utc::now();
+    let ends_at = started_at + chrono::Duration::try_minutes(i64::from(minutes)).unwrap();
+
+    match format {
+        Format::Json =>
+            output::print_json(
+                &json!({"event": "started", "task_id": task_id, "title": task.title, "minutes": minutes, "started_at": started_at, "ends_at": ends_at})
+            ),
+        Format::Table =>
+            println!(
+                "Working on \"{}\" for {minutes} min, until {}",
+                task.title,
+                ends_at.with_timezone(&Local).format("%H:%M")
+            ),
+    }
+
+    let live = format == Format::Table && io::stdout().is_terminal();
+    let end = Instant::now() + length;
+    loop {
+        let left = end.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        if live {
+            print!("\r{} left ", clock(left));
+            io::stdout().flush()?;
+        }
+        // Wake as the clock turns over to the next second
+        let tick = match left.subsec_millis() {
+            0 => 1000,
+            millis => millis,
+        };
+        thread::sleep(left.min(Duration::from_millis(u64::from(tick))));
+    }
+
+    match format {
+        Format::Json =>
+            output::print_json(
+                &json!({"event": "finished", "task_id": task_id, "finished_at": Utc::now()})
+            ),
+        Format::Table => {
+            if live {
+                print!("\r\x07"); // Clear the clock and ring the terminal bell
+            }
+            println!("Time is up for \"{}\"", task.title);
+        }
+    }
+    if check {
+        output::checked(format, &backend.check_task(task_id, true)?);
+    }
+    Ok(())
+}
+
+/// `mm:ss`, or `h:mm:ss` from an hour up.
+pub fn clock(left: Duration) -> String {
+    // Round up, so the clock reads 00:01 until the very end
+    let secs = left.as_secs() + u64::from(left.subsec_nanos() > 0);
+    match secs / 3600 {
+        0 => format!("{:02}:{:02}", secs / 60, secs % 60),
+        hours => format!("{hours}:{:02}:{:02}", (secs / 60) % 60, secs % 60),
+    }
+}
@@ -0,0 +1,168 @@
+// The terminal client's settings and saved session, kept in a JSON file readable only by its owner:
+// `$KAIZEN_HOME/config.json`, else `config.json` in the user's config directory under `kaizen`.
+
+use std::{ env, fs, io, path::{ Path, PathBuf } };
+
+use chrono_tz::Tz;
+use serde::{ Deserialize, Serialize };
+
+use crate::credentials::restrict_to_owner;
+use crate::server::{ deadline, PORT };
+
+/// Name of the config file in the config directory.
+pub const CONFIG_FILE: &str = "config.json";
+
+/// Name of the local database in the config directory, unless the config names another.
+pub const DATABASE_FILE: &str = "kaizen.db";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to access {path}: {source}")] Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    #[error("{path} is malformed: {source}")] Malformed {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("no config directory, set KAIZEN_HOME")] NoHome,
+}
+
+/// Where tasks are kept unless the command line picks.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    #[default]
+    Http,
+    Local,
+}
+
+/// A signed-in session on the server.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Session {
+    pub email: String,
+    pub username: String,
+    pub token: String, // Short-lived access token
+    pub refresh_token: String, // Swapped for a new pair when the access token expires
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(default)]
+    pub backend: Backend,
+    pub server: Option<String>, // API address, the local default port when unset
+    pub database: Option<PathBuf>, // SQLite file for the local backend
+    pub timezone: Option<String>, // IANA name deadlines of local tasks are checked in, UTC when unset
+    pub session: Option<Session>,
+}
+
+impl Config {
+    /// Reads the config file from its usual place.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::load_from(config_dir()?.join(CONFIG_FILE))
+    }
+
+    /// Reads a config file. A missing file is an empty config that will be created on save.
+    pub fn load_from(path: impl Into<PathBuf>) -> Result<Self, ConfigError> {
+        let path = path.into();
+        let mut config: Config = match fs::read(&path) {
+            Ok(bytes) =>
+                serde_json
+                    ::from_slice(&bytes)
+                    .map_err(|source| ConfigError::Malformed { path: path.clone(), source })?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Config::default(),
+            Err(source) => {
+                return Err(ConfigError::Io { path, source });
+            }
+        };
+        config.path = path;
+        Ok(config)
+    }
+
+    /// Writes the config back, readable only by the current user since it holds the session.
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let io_error = |source| ConfigError::Io { path: self.path.clone(), source };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        let json = serde_json::to_vec_pretty(self).map_err(|source| ConfigError::Malformed {
+            path: self.path.clone(),
+            source,
+        })?;
+        // Write next to the file and swap it in, so a crash never leaves half a file behind
+        let temp_path = self.path.with_extension("tmp");
+        fs::write(&temp_path, json).map_err(io_error)?;
+        restrict_to_owner(&temp_path).map_err(io_error)?;
+        fs::rename(&temp_path, &self.path).map_err(io_error)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Base address of the API, without a trailing slash.
+    pub fn server_url(&self) -> String {
+        match &self.server {
+            Some(server) => server.trim_end_matches('/').to_string(),
+            None => format!("http://127.0.0.1:{PORT}"),
+        }
+    }
+
+    /// Timezone deadlines of local tasks are checked in.
+    pub fn timezone(&self) -> Tz {
+        self.timezone.as_deref().and_then(deadline::parse_timezone).unwrap_or(Tz::UTC)
+    }
+
+    /// The local database, next to the config file unless the config names another.
+    pub fn database_path(&self) -> PathBuf {
+        match &self.database {
+            Some(path) => path.clone(),
+            None => self.path.with_file_name(DATABASE_FILE),
+        }
+    }
+}
+
+/// `$KAIZEN_HOME`, else `kaizen` in the platform's config directory.
+pub fn config_dir() -> Result<PathBuf, ConfigError> {
+    if let Some(home) = env::var_os("KAIZEN_HOME") {
+        return Ok(PathBuf::from(home));
+    }
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    };
+    base.map(|base| base.join("kaizen")).ok_or(ConfigError::NoHome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_defaults() {
+        let path = env::temp_dir().join(format!("kaizen-config-{}", std::process::id())).join(CONFIG_FILE);
+        let mut config = Config::load_from(&path).unwrap();
+        assert_eq!(config.backend, Backend::Http);
+        assert_eq!(config.server_url(), format!("http://127.0.0.1:{PORT}"));
+        assert_eq!(config.database_path(), path.with_file_name(DATABASE_FILE));
+
+        config.server = Some("https://kaizen.example.com/".to_string());
+        config.session = Some(Session {
+            email: "ada@example.com".to_string(),
+            username: "ada".to_string(),
+            token: "access".to_string(),
+            refresh_token: "refresh".to_string(),
+        });
+        config.save().unwrap();
+
+        let loaded = Config::load_from(&path).unwrap();
+        assert_eq!(loaded, config);
+        assert_eq!(loaded.server_url(), "https://kaizen.example.com");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,257 @@
+// Tasks and habits in a SQLite file on this machine, for working without a server.
+// Tasks have the same fields and rules as on the server; blocked-by links only exist there.
+
+use std::{ fs, path::Path };
+
+use chrono::{ NaiveDate, Utc };
+use chrono_tz::Tz;
+use sqlx::sqlite::{ SqliteConnectOptions, SqlitePool, SqlitePoolOptions };
+use tokio::runtime::Runtime;
+
+use crate::server::services::habits::Habit;
+use crate::server::services::tasks::{ AddTask, CheckedTask, Task };
+use crate::server::services::ServiceError;
+use crate::server::validation::Validate;
+use super::{ ClientError, TaskBackend };
+
+/// Owner of local tasks created while nobody is logged in.
+pub const LOCAL_USER: &str = "me@kaizen.local";
+
+/// A local database, used from synchronous code through its own runtime.
+pub struct LocalStore {
+    runtime: Runtime,
+    pool: SqlitePool,
+    user_email: String,
+    timezone: Tz, // Deadlines are checked in it
+}
+
+impl LocalStore {
+    /// Opens the database, creating the file and its tables when needed.
+    pub fn open(path: &Path, user_email: &str, timezone: Tz) -> Result<Self, ClientError> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let options = SqliteConnectOptions::new().filename(path).create_if_missing(true);
+        let pool = runtime.block_on(async {
+            // One connection, so writes never wait on each other
+            let pool = SqlitePoolOptions::new().max_connections(1).connect_with(options).await?;
+            sqlx::migrate!("./local_migrations")
+                .run(&pool).await
+                .map_err(|err| ServiceError::Internal(format!("migration error: {err}")))?;
+            Ok::<_, ClientError>(pool)
+        })?;
+        Ok(LocalStore { runtime, pool, user_email: user_email.to_string(), timezone })
+    }
+}
+
+impl TaskBackend for LocalStore {
+    fn user_email(&self) -> &str {
+        &self.user_email
+    }
+
+    fn list_tasks(&mut self) -> Result<Vec<Task>, ClientError> {
+        let mut tasks = self.runtime.block_on(
+            sqlx
+                ::query_as::<_, Task>("SELECT * FROM tasks WHERE user_email = $1 ORDER BY task_id")
+                .bind(&self.user_email)
+                .fetch_all(&self.pool)
+        )?;
+        let now = Utc::now();
+        for task in tasks.iter_mut() {
+            task.overdue = task.is_overdue(self.timezone, now);
+        }
+        Ok(tasks)
+    }
+
+    fn create_task(&mut self, task: &AddTask) -> Result<(), ClientError> {
+        task.validate().map_err(ServiceError::from)?;
+        self.runtime.block_on(
+            sqlx
+                ::query(
+                    "INSERT INTO tasks(user_email, title, description, checked, due_date, due_time, duration, priority, project)
+                    VALUES($1, $2, $3, FALSE, $4, $5, $6, $7, $8)"
+                )
+                .bind(&self.user_email)
+                .bind(&task.title)
+                .bind(&task.description)
+                .bind(task.due_date)
+                .bind(task.due_time)
+                .bind(task.duration)
+                .bind(task.priority)
+                .bind(&task.project)
+                .execute(&self.pool)
+        )?;
+        Ok(())
+    }
+
+    fn update_task(&mut self, task_id: i32, task: &AddTask) -> Result<(), ClientError> {
+        task.validate().map_err(ServiceError::from)?;
+        let done = self.runtime.block_on(
+            sqlx
+                ::query(
+                    "UPDATE tasks SET title = $1, description = $2, due_date = $3, due_time = $4,
+                    duration = $5, priority = $6, project = $7
+                    WHERE task_id = $8 AND user_email = $9"
+                )
+                .bind(&task.title)
+                .bind(&task.description)
+                .bind(task.due_date)
+                .bind(task.due_time)
+                .bind(task.duration)
+                .bind(task.priority)
+                .bind(&task.project)
+                .bind(task_id)
+                .bind(&self.user_email)
+                .execute(&self.pool)
+        )?;
+        if done.rows_affected() == 0 {
+            return Err(ClientError::TaskNotFound(task_id));
+        }
+        Ok(())
+    }
+
+    fn check_task(&mut self, task_id: i32, checked: bool) -> Result<CheckedTask, ClientError> {
+        let done = self.runtime.block_on(
+            sqlx
+                ::query("UPDATE tasks SET checked = $1 WHERE task_id = $2 AND user_email = $3")
+                .bind(checked)
+                .bind(task_id)
+                .bind(&self.user_email)
+                .execute(&self.pool)
+        )?;
+        if done.rows_affected() == 0 {
+            return Err(ClientError::TaskNotFound(task_id));
+        }
+        // Without links nothing is ever unblocked
+        Ok(CheckedTask { task_id, checked, unblocked: Vec::new() })
+    }
+
+    fn delete_task(&mut self, task_id: i32) -> Result<(), ClientError> {
+        let done = self.runtime.block_on(
+            sqlx
+                ::query("DELETE FROM tasks WHERE task_id = $1 AND user_email = $2")
+                .bind(task_id)
+                .bind(&self.user_email)
+                .execute(&self.pool)
+        )?;
+        if done.rows_affected() == 0 {
+            return Err(ClientError::TaskNotFound(task_id));
+        }
+        Ok(())
+    }
+
+    fn list_habits(&mut self) -> Result<Vec<Habit>, ClientError> {
+        let habits = self.runtime.block_on(
+            sqlx
+                ::query_as::<_, Habit>(
+                    "SELECT h.*, (SELECT MAX(c.day) FROM habit_checkins c WHERE c.habit_id = h.habit_id) AS last_checked
+                    FROM habits h WHERE h.user_email = $1 ORDER BY h.habit_id"
+                )
+                .bind(&self.user_email)
+                .fetch_all(&self.pool)
+        )?;
+        Ok(habits)
+    }
+
+    fn create_habit(&mut self, name: &str) -> Result<(), ClientError> {
+        self.runtime.block_on(
+            sqlx
+                ::query("INSERT INTO habits(user_email, name, created_at) VALUES($1, $2, $3)")
+                .bind(&self.user_email)
+                .bind(name)
+                .bind(Utc::now())
+                .execute(&self.pool)
+        )?;
+        Ok(())
+    }
+
+    fn check_habit(&mut self, habit_id: i32, day: NaiveDate) -> Result<(), ClientError> {
+        // Habits of other users are as good as missing
+        let owned: Option<i32> = self.runtime.block_on(
+            sqlx
+                ::query_scalar("SELECT habit_id FROM habits WHERE habit_id = $1 AND user_email = $2")
+                .bind(habit_id)
+                .bind(&self.user_email)
+                .fetch_optional(&self.pool)
+        )?;
+        if owned.is_none() {
+            return Err(ClientError::HabitNotFound(habit_id));
+        }
+        self.runtime.block_on(
+            sqlx
+                ::query("INSERT INTO habit_checkins(habit_id, day) VALUES($1, $2) ON CONFLICT DO NOTHING")
+                .bind(habit_id)
+                .bind(day)
+                .execute(&self.pool)
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_tasks_and_habits() {
+        let dir = std::env::temp_dir().join(format!("kaizen-local-{}", std::process::id()));
+        let mut store = LocalStore::open(&dir.join("kaizen.db"), LOCAL_USER, Tz::UTC).unwrap();
+        let mut task = AddTask {
+            user_email: LOCAL_USER.to_string(),
+            title: "Write the report".to_string(),
+            description: String::new(),
+            due_date: NaiveDate::from_ymd_opt(2000, 1, 1),
+            due_time: None,
+            duration: Some(30),
+            priority: Some(2),
+            project: None,
+        };
+        store.create_task(&task).unwrap();
+
+        let tasks = store.list_tasks().unwrap();
+        assert_eq!(tasks.len(), 1);
+        let task_id = tasks[0].task_id;
+        assert!(tasks[0].overdue);
+
+        task.title = "Send the report".to_string();
+        store.update_task(task_id, &task).unwrap();
+        store.check_task(task_id, true).unwrap();
+        let stored = store.task(task_id).unwrap();
+        assert_eq!(stored.title, "Send the report");
+        assert!(stored.checked && !stored.overdue);
+
+        task.title = " ".to_string();
+        assert!(matches!(store.update_task(task_id, &task), Err(ClientError::Service(ServiceError::Invalid(_)))));
+        store.delete_task(task_id).unwrap();
+        assert!(matches!(store.task(task_id), Err(ClientError::TaskNotFound(_))));
+        assert!(matches!(store.delete_task(task_id), Err(ClientError::TaskNotFound(_))));
+        // Tasks are kept as the store's user, whoever the payload names
+        task.title = "Send the report".to_string();
+        task.user_email = "someone@example.com".to_string();
+        store.create_task(&task).unwrap();
+        let task_id = store.list_tasks().unwrap()[0].task_id;
+        assert_eq!(store.task(task_id).unwrap().user_email, LOCAL_USER);
+
+        store.create_habit("Stretch").unwrap();
+        let habit_id = store.list_habits().unwrap()[0].habit_id;
+        let day = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        store.check_habit(habit_id, day).unwrap();
+        store.check_habit(habit_id, day).unwrap();
+        assert_eq!(store.list_habits().unwrap()[0].last_checked, Some(day));
+        assert!(matches!(store.check_habit(habit_id + 1, day), Err(ClientError::HabitNotFound(_))));
+
+        // Someone else logged in on the same machine sees none of them
+        drop(store);
+        let mut other = LocalStore::open(&dir.join("kaizen.db"), "other@example.com", Tz::UTC).unwrap();
+        assert!(other.list_tasks().unwrap().is_empty());
+        assert!(matches!(other.check_task(task_id, true), Err(ClientError::TaskNotFound(_))));
+        assert!(matches!(other.update_task(task_id, &task), Err(ClientError::TaskNotFound(_))));
+        assert!(matches!(other.delete_task(task_id), Err(ClientError::TaskNotFound(_))));
+        assert!(other.list_habits().unwrap().is_empty());
+        assert!(matches!(other.check_habit(habit_id, day), Err(ClientError::HabitNotFound(_))));
+        drop(other);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
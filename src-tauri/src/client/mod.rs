@@ -0,0 +1,66 @@
+// Kaizen from the terminal: tasks kept by a Kaizen server and reached over its HTTP API,
+// or kept in a SQLite file on this machine for working offline.
+
+pub mod config; // Server address, local database and saved session
+pub mod remote; // The HTTP API, with the login and refresh token flow
+pub mod local; // Tasks in a local SQLite file
+
+use chrono::NaiveDate;
+
+use crate::server::services::habits::Habit;
+use crate::server::services::tasks::{ AddTask, CheckedTask, Task };
+use crate::server::services::ServiceError;
+use config::ConfigError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("not logged in, run `kaizen login` first")] NotSignedIn,
+    #[error("session expired, run `kaizen login` again")] SessionExpired,
+    #[error("task {0} not found")] TaskNotFound(i32),
+    #[error("habit {0} not found")] HabitNotFound(i32),
+    #[error("{0}")] Api(String), // The server's answer to a refused request
+    #[error("request failed: {0}")] Request(#[from] reqwest::Error),
+    #[error(transparent)] Service(#[from] ServiceError),
+    #[error(transparent)] Config(#[from] ConfigError),
+    #[error(transparent)] Io(#[from] std::io::Error),
+}
+
+impl From<sqlx::Error> for ClientError {
+    fn from(err: sqlx::Error) -> Self {
+        ClientError::Service(err.into())
+    }
+}
+
+/// Somewhere tasks and habits are kept: the HTTP API or the local database.
+pub trait TaskBackend {
+    /// Owner of the tasks and habits, filled into the tasks this backend creates.
+    fn user_email(&self) -> &str;
+
+    /// Every task, flagged as overdue or blocked.
+    fn list_tasks(&mut self) -> Result<Vec<Task>, ClientError>;
+
+    fn create_task(&mut self, task: &AddTask) -> Result<(), ClientError>;
+
+    /// Replaces a task's fields, keeping whether it is checked off.
+    fn update_task(&mut self, task_id: i32, task: &AddTask) -> Result<(), ClientError>;
+
+    /// Checks off (or reopens) a task.
+    fn check_task(&mut self, task_id: i32, checked: bool) -> Result<CheckedTask, ClientError>;
+
+    fn delete_task(&mut self, task_id: i32) -> Result<(), ClientError>;
+
+    /// Every habit, oldest first.
+    fn list_habits(&mut self) -> Result<Vec<Habit>, ClientError>;
+
+    fn create_habit(&mut self, name: &str) -> Result<(), ClientError>;
+
+    /// Checks a habit off for a day. Checking it twice on the same day is a no-op.
+    fn check_habit(&mut self, habit_id: i32, day: NaiveDate) -> Result<(), ClientError>;
+
+    fn task(&mut self, task_id: i32) -> Result<Task, ClientError> {
+        self.list_tasks()?
+            .into_iter()
+            .find(|task| task.task_id == task_id)
+            .ok_or(ClientError::TaskNotFound(task_id))
+    }
+}
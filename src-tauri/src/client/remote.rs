@@ -0,0 +1,257 @@
+// Tasks on a Kaizen server, through its HTTP API.
+// Signs in with the same token pair as the desktop app: requests carry the short-lived access token,
+// and a refused one is swapped once through /auth/refresh before giving up.
+
+use chrono::NaiveDate;
+use reqwest::blocking::{ Client, RequestBuilder, Response };
+use reqwest::StatusCode;
+use serde_json::{ json, Value };
+
+use crate::server::responses::{ LoginResponse, SessionResponse, TokenPairResponse };
+use crate::server::services::habits::Habit;
+use crate::server::services::tasks::{ AddTask, CheckedTask, Task };
+use super::config::{ Config, Session };
+use super::{ ClientError, TaskBackend };
+
+/// How the first login step ended.
+pub enum Login {
+    SignedIn(Session),
+    /// The account has two-factor authentication; finish with `verify_two_factor`.
+    TwoFactorRequired {
+        challenge_token: String,
+    },
+}
+
+impl From<LoginResponse> for Login {
+    fn from(response: LoginResponse) -> Self {
+        match response {
+            LoginResponse::TwoFactorRequired(challenge) =>
+                Login::TwoFactorRequired { challenge_token: challenge.challenge_token },
+            LoginResponse::SignedIn(session) => Login::SignedIn(session.into()),
+        }
+    }
+}
+
+impl From<SessionResponse> for Session {
+    fn from(response: SessionResponse) -> Self {
+        Session {
+            email: response.user_email,
+            username: response.user_username,
+            token: response.token,
+            refresh_token: response.refresh_token,
+        }
+    }
+}
+
+/// Checks for email and password. A session is saved to the config unless a second factor is needed.
+pub fn login(config: &mut Config, email: &str, password: &str) -> Result<Login, ClientError> {
+    let request = Client::new()
+        .post(format!("{}/auth/login", config.server_url()))
+        .json(&json!({"email": email, "password": password}));
+    let login = Login::from(checked(request.send()?)?.json::<LoginResponse>()?);
+    if let Login::SignedIn(session) = &login {
+        config.session = Some(session.clone());
+        config.save()?;
+    }
+    Ok(login)
+}
+
+/// Second login step, with a code from the authenticator app or a recovery code.
+/// The new session is saved to the config.
+pub fn verify_two_factor(
+    config: &mut Config,
+    challenge_token: &str,
+    code: Option<&str>,
+    recovery_code: Option<&str>
+) -> Result<Session, ClientError> {
+    let request = Client::new()
+        .post(format!("{}/auth/2fa/verify", config.server_url()))
+        .json(
+            &json!({"challenge_token": challenge_token, "code": code, "recovery_code": recovery_code})
+        );
+    let session = Session::from(checked(request.send()?)?.json::<SessionResponse>()?);
+    config.session = Some(session.clone());
+    config.save()?;
+    Ok(session)
+}
+
+/// Passes successful responses through and turns the others into the server's message.
+fn checked(response: Response) -> Result<Response, ClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let text = response.text().unwrap_or_default();
+    Err(ClientError::Api(error_message(status, &text)))
+}
+
+/// The message of an error body: a JSON string, or an object with a message and invalid fields.
+fn error_message(status: StatusCode, body: &str) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(Value::String(message)) => message,
+        Ok(Value::Object(object)) => {
+            let mut message = object
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("Request failed")
+                .to_string();
+            for error in object.get("errors").and_then(Value::as_array).into_iter().flatten() {
+                let field = error.get("field").and_then(Value::as_str).unwrap_or("?");
+                let detail = error.get("message").and_then(Value::as_str).unwrap_or("invalid");
+                message.push_str(&format!("\n  {field}: {detail}"));
+            }
+            message
+        }
+        _ if body.trim().is_empty() => status.to_string(),
+        _ => body.trim().to_string(),
+    }
+}
+
+/// A server, reached with the session saved in the config.
+pub struct Remote {
+    http: Client,
+    config: Config,
+}
+
+impl Remote {
+    pub fn new(config: Config) -> Result<Self, ClientError> {
+        if config.session.is_none() {
+            return Err(ClientError::NotSignedIn);
+        }
+        Ok(Remote { http: Client::new(), config })
+    }
+
+    pub fn session(&self) -> &Session {
+        self.config.session.as_ref().expect("checked in Remote::new")
+    }
+
+    /// Ends the session on the server and forgets it here.
+    /// It is forgotten even when the server cannot be told.
+    pub fn logout(mut self) -> Result<(), ClientError> {
+        let url = self.url("/auth/logout");
+        let result = self.authorized(|http| http.post(&url)).map(|_| ());
+        self.config.session = None;
+        self.config.save()?;
+        result
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.config.server_url())
+    }
+
+    /// Sends a request with the access token, refreshing the session once if it was refused.
+    fn authorized(&mut self, request: impl Fn(&Client) -> RequestBuilder) -> Result<Response, ClientError> {
+        let response = request(&self.http).bearer_auth(&self.session().token).send()?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return checked(response);
+        }
+        self.refresh()?;
+        checked(request(&self.http).bearer_auth(&self.session().token).send()?)
+    }
+
+    /// Swaps the refresh token for a new pair and saves it.
+    /// A refused refresh token ends the session here too.
+    fn refresh(&mut self) -> Result<(), ClientError> {
+        let response = self.http
+            .post(self.url("/auth/refresh"))
+            .json(&json!({"refresh_token": self.session().refresh_token}))
+            .send()?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            self.config.session = None;
+            self.config.save()?;
+            return Err(ClientError::SessionExpired);
+        }
+        let refreshed: TokenPairResponse = checked(response)?.json()?;
+        if let Some(session) = self.config.session.as_mut() {
+            session.token = refreshed.token;
+            session.refresh_token = refreshed.refresh_token;
+        }
+        self.config.save()?;
+        Ok(())
+    }
+}
+
+impl TaskBackend for Remote {
+    fn user_email(&self) -> &str {
+        &self.session().email
+    }
+
+    fn list_tasks(&mut self) -> Result<Vec<Task>, ClientError> {
+        let url = self.url(&format!("/tasks/{}", self.session().email));
+        Ok(self.authorized(|http| http.get(&url))?.json()?)
+    }
+
+    fn create_task(&mut self, task: &AddTask) -> Result<(), ClientError> {
+        let url = self.url("/tasks/create");
+        self.authorized(|http| http.post(&url).json(task))?;
+        Ok(())
+    }
+
+    fn update_task(&mut self, task_id: i32, task: &AddTask) -> Result<(), ClientError> {
+        let url = self.url(&format!("/tasks/update/{task_id}"));
+        self.authorized(|http| http.post(&url).json(task))?;
+        Ok(())
+    }
+
+    fn check_task(&mut self, task_id: i32, checked: bool) -> Result<CheckedTask, ClientError> {
+        let url = self.url(&format!("/tasks/check/{task_id}"));
+        Ok(self.authorized(|http| http.post(&url).json(&json!({"checked": checked})))?.json()?)
+    }
+
+    fn delete_task(&mut self, task_id: i32) -> Result<(), ClientError> {
+        let url = self.url(&format!("/tasks/delete/{task_id}"));
+        self.authorized(|http| http.delete(&url))?;
+        Ok(())
+    }
+
+    fn list_habits(&mut self) -> Result<Vec<Habit>, ClientError> {
+        let url = self.url(&format!("/habits/{}", self.session().email));
+        Ok(self.authorized(|http| http.get(&url))?.json()?)
+    }
+
+    fn create_habit(&mut self, name: &str) -> Result<(), ClientError> {
+        let url = self.url("/habits/create");
+        let body = json!({"user_email": self.session().email, "name": name});
+        self.authorized(|http| http.post(&url).json(&body))?;
+        Ok(())
+    }
+
+    fn check_habit(&mut self, habit_id: i32, day: NaiveDate) -> Result<(), ClientError> {
+        let url = self.url(&format!("/habits/check/{habit_id}"));
+        self.authorized(|http| http.post(&url).json(&json!({"day": day})))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_error_bodies() {
+        let status = StatusCode::UNPROCESSABLE_ENTITY;
+        assert_eq!(error_message(status, "\"Task not found\""), "Task not found");
+        assert_eq!(
+            error_message(
+                status,
+                r#"{"message":"Some fields are invalid","errors":[{"field":"title","code":"blank","message":"Must not be blank"}]}"#
+            ),
+            "Some fields are invalid\n  title: Must not be blank"
+        );
+        assert_eq!(error_message(StatusCode::BAD_GATEWAY, ""), "502 Bad Gateway");
+    }
+
+    #[test]
+    fn reads_both_login_answers() {
+        let challenge = r#"{"message":"Enter the code","two_factor_required":true,"challenge_token":"abc"}"#;
+        let login = Login::from(serde_json::from_str::<LoginResponse>(challenge).unwrap());
+        assert!(matches!(login, Login::TwoFactorRequired { challenge_token } if challenge_token == "abc"));
+
+        let signed_in =
+            r#"{"message":"Hi","token":"t","refresh_token":"r","user_email":"a@b.c","user_username":"ab"}"#;
+        match Login::from(serde_json::from_str::<LoginResponse>(signed_in).unwrap()) {
+            Login::SignedIn(session) => assert_eq!((session.email.as_str(), session.token.as_str()), ("a@b.c", "t")),
+            Login::TwoFactorRequired { .. } => panic!("expected a session"),
+        }
+    }
+}
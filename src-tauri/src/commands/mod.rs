@@ -1,25 +1,267 @@
-use std::sync::Mutex;
+// Tauri commands the window can `invoke` instead of calling the HTTP API.
+// They run the same services as the actix handlers; the signed-in user comes from `AuthState`.
 
-use tauri::State;
+use std::sync::{ Arc, Mutex };
 
-use crate::AuthState;
+use chrono::NaiveDate;
+use serde::Serialize;
+use tauri::{ AppHandle, Manager, State };
+
+use kaizen::server::services::{
+    self,
+    account::ConfirmReset,
+    auth::{ ClientInfo, CurrentUser, Login, RegisterUser },
+    habits::Habit,
+    reviews::{ ReviewPeriod, ReviewSummary },
+    tasks::{ AddTask, CheckedTask, Task, TaskOrder },
+    ServiceError,
+};
+use kaizen::server::lifecycle::{ Lifecycle, ServerStatus };
+use kaizen::server::TauriAppState;
+
+use crate::auth_state::{ self, AuthState };
 
 // create the error type that represents all errors possible in our program
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("Failed to make server request: {0}")] Request(#[from] reqwest::Error),
     #[error("Server failed with non 200 status code")] Server(),
+    #[error("The server is still starting, please try again")] NotReady,
+    #[error("Please log in first")] NotSignedIn,
+    #[error(transparent)] Service(#[from] ServiceError),
 }
 
 // we must manually implement serde::Serialize
 impl serde::Serialize for Error {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::ser::Serializer {
-        serializer.serialize_str(self.to_string().as_ref())
+        match self {
+            // Keep the field errors of invalid payloads, as the HTTP API does
+            Error::Service(err) => err.to_json().serialize(serializer),
+            _ => serializer.serialize_str(self.to_string().as_ref()),
+        }
     }
 }
 
+/// Outcome of `login`, mirroring the HTTP login response without the tokens.
+#[derive(Serialize, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum LoginResult {
+    SignedIn {
+        user: CurrentUser,
+    },
+    TwoFactorRequired {
+        challenge_token: String, // Pass to `verify_two_factor` with a code
+    },
+}
+
+/// The server's shared state, once it has connected to the database.
+fn backend(app: &AppHandle) -> Result<Arc<TauriAppState>, Error> {
+    app.try_state::<Arc<TauriAppState>>()
+        .map(|state| state.inner().clone())
+        .ok_or(Error::NotReady)
+}
+
+/// Held while the app refreshes its session, so commands running at once spend the refresh token only once.
+static REFRESHING: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// The server's state and the signed-in user's email, checked against their session token.
+/// Access tokens only last an hour; once the server turns one down, the session is refreshed here.
+async fn signed_in(app: &AppHandle) -> Result<(Arc<TauriAppState>, String), Error> {
+    let backend = backend(app)?;
+    let Some((token, _)) = auth_state::session_tokens(app) else {
+        return Err(Error::NotSignedIn);
+    };
+    let email = match services::auth::authenticate(&backend, &token) {
+        Ok(claims) => claims.email,
+        Err(_) => refresh(app, &backend, &token).await?,
+    };
+    Ok((backend, email))
+}
+
+/// Swaps the session's refresh token for a new pair, signing the app out if the session is over.
+/// Returns the signed-in user's email, checked against the new access token.
+async fn refresh(app: &AppHandle, backend: &TauriAppState, rejected: &str) -> Result<String, Error> {
+    let _refreshing = REFRESHING.lock().await;
+    let Some((token, refresh_token)) = auth_state::session_tokens(app) else {
+        return Err(Error::NotSignedIn);
+    };
+    // Another command may have refreshed the session while this one waited
+    let token = if token == rejected {
+        match services::auth::refresh_session(backend, &refresh_token).await {
+            Ok(token) => token,
+            Err(err @ ServiceError::Unauthorized(_)) => {
+                auth_state::sign_out(app);
+                return Err(err.into());
+            }
+            Err(err) => {
+                return Err(err.into());
+            }
+        }
+    } else {
+        token
+    };
+    Ok(services::auth::authenticate(backend, &token)?.email)
+}
+
+/// The embedded server's latest status, as also sent in `server-status` events.
+#[tauri::command]
+pub fn get_server_status(lifecycle: State<'_, Arc<Lifecycle>>) -> ServerStatus {
+    lifecycle.status()
+}
+
 #[tauri::command]
 pub fn is_authorized(state_mutex: State<'_, Mutex<AuthState>>) -> bool {
     let state = state_mutex.lock().unwrap();
-    state.logged_in == true
+    state.logged_in
+}
+
+/// The signed-in user, if any.
+#[tauri::command]
+pub fn current_user(state_mutex: State<'_, Mutex<AuthState>>) -> Option<CurrentUser> {
+    let state = state_mutex.lock().unwrap();
+    state.user.clone()
+}
+
+/// Creates an account and signs in to it.
+#[tauri::command]
+pub async fn register(app: AppHandle, user: RegisterUser) -> Result<CurrentUser, Error> {
+    let backend = backend(&app)?;
+    let signed_in = services::auth::register(&backend, &user, &ClientInfo::desktop()).await?;
+    Ok(signed_in.user)
+}
+
+/// Signs in with an email and password.
+#[tauri::command]
+pub async fn login(app: AppHandle, email: String, password: String) -> Result<LoginResult, Error> {
+    let backend = backend(&app)?;
+    match services::auth::login(&backend, &email, password, &ClientInfo::desktop()).await? {
+        Login::SignedIn(signed_in) => Ok(LoginResult::SignedIn { user: signed_in.user }),
+        Login::TwoFactorRequired { challenge_token } =>
+            Ok(LoginResult::TwoFactorRequired { challenge_token }),
+    }
+}
+
+/// Finishes a login that needs a code from the authenticator app or a recovery code.
+#[tauri::command]
+pub async fn verify_two_factor(
+    app: AppHandle,
+    challenge_token: String,
+    code: Option<String>,
+    recovery_code: Option<String>
+) -> Result<CurrentUser, Error> {
+    let backend = backend(&app)?;
+    let signed_in = services::auth::verify_second_factor(
+        &backend,
+        &challenge_token,
+        code.as_deref(),
+        recovery_code.as_deref(),
+        &ClientInfo::desktop()
+    ).await?;
+    Ok(signed_in.user)
+}
+
+/// Signs out of the desktop app and ends the session on the server.
+/// The app is signed out even when the session cannot be ended.
+#[tauri::command]
+pub async fn sign_out(app: AppHandle) -> Result<(), Error> {
+    let token = app.state::<Mutex<AuthState>>().lock().unwrap().token.clone();
+    auth_state::sign_out(&app);
+
+    let (Some(token), Ok(backend)) = (token, backend(&app)) else {
+        return Ok(());
+    };
+    // An expired or revoked token means the session is as good as gone already
+    if let Ok(claims) = services::auth::authenticate(&backend, &token) {
+        services::auth::logout(&backend, &claims).await?;
+    }
+    Ok(())
+}
+
+/// Emails a password reset link, if an account uses the address.
+#[tauri::command]
+pub async fn request_password_reset(app: AppHandle, email: String) -> Result<(), Error> {
+    let backend = backend(&app)?;
+    services::account::request_password_reset(&backend, &email);
+    Ok(())
+}
+
+/// Sets a new password with the token from a reset link. Every session is logged out.
+#[tauri::command]
+pub async fn reset_password(app: AppHandle, reset: ConfirmReset) -> Result<(), Error> {
+    let backend = backend(&app)?;
+    Ok(services::account::reset_password(&backend, &reset).await?)
+}
+
+/// Confirms an email address with the token from a verification link, answering what happened.
+#[tauri::command]
+pub async fn verify_email(app: AppHandle, token: String) -> Result<&'static str, Error> {
+    let backend = backend(&app)?;
+    Ok(services::account::verify_email(&backend, &token).await?.message())
+}
+
+#[tauri::command]
+pub async fn get_tasks(app: AppHandle, hide_blocked: Option<bool>) -> Result<Vec<Task>, Error> {
+    let (backend, email) = signed_in(&app).await?;
+    Ok(services::tasks::list_tasks(backend.pool(), &email, hide_blocked.unwrap_or(false)).await?)
+}
+
+#[tauri::command]
+pub async fn get_task_order(app: AppHandle, project: Option<String>) -> Result<TaskOrder, Error> {
+    let (backend, email) = signed_in(&app).await?;
+    Ok(services::tasks::task_order(backend.pool(), &email, project.as_deref()).await?)
+}
+
+#[tauri::command]
+pub async fn create_task(app: AppHandle, task: AddTask) -> Result<(), Error> {
+    let (backend, email) = signed_in(&app).await?;
+    Ok(services::tasks::create_task(backend.pool(), &email, &task).await?)
+}
+
+/// Replaces a task's fields with the ones given.
+#[tauri::command]
+pub async fn update_task(app: AppHandle, task_id: i32, task: AddTask) -> Result<(), Error> {
+    let (backend, email) = signed_in(&app).await?;
+    Ok(services::tasks::update_task(backend.pool(), &email, task_id, &task).await?)
+}
+
+#[tauri::command]
+pub async fn check_task(app: AppHandle, task_id: i32, checked: bool) -> Result<CheckedTask, Error> {
+    let (backend, email) = signed_in(&app).await?;
+    Ok(services::tasks::check_task(backend.pool(), &email, task_id, checked).await?)
+}
+
+#[tauri::command]
+pub async fn delete_task(app: AppHandle, task_id: i32) -> Result<(), Error> {
+    let (backend, email) = signed_in(&app).await?;
+    Ok(services::tasks::delete_task(backend.pool(), &email, task_id).await?)
+}
+
+#[tauri::command]
+pub async fn get_habits(app: AppHandle) -> Result<Vec<Habit>, Error> {
+    let (backend, email) = signed_in(&app).await?;
+    Ok(services::habits::list_habits(backend.pool(), &email).await?)
+}
+
+#[tauri::command]
+pub async fn create_habit(app: AppHandle, name: String) -> Result<(), Error> {
+    let (backend, email) = signed_in(&app).await?;
+    Ok(services::habits::create_habit(backend.pool(), &email, &name).await?)
+}
+
+/// Checks a habit off for a day, today in the user's timezone by default.
+#[tauri::command]
+pub async fn check_habit(app: AppHandle, habit_id: i32, day: Option<NaiveDate>) -> Result<(), Error> {
+    let (backend, email) = signed_in(&app).await?;
+    Ok(services::habits::check_habit(backend.pool(), &email, habit_id, day).await?)
+}
+
+/// Completion and habit statistics for the day or week containing `date`.
+#[tauri::command]
+pub async fn get_review_summary(
+    app: AppHandle,
+    period: ReviewPeriod,
+    date: NaiveDate
+) -> Result<ReviewSummary, Error> {
+    let (backend, email) = signed_in(&app).await?;
+    Ok(services::reviews::review_summary(backend.pool(), &email, period, date).await?)
 }
@@ -0,0 +1,219 @@
+// Credentials the desktop app keeps between launches, so the user stays signed in.
+// Only the refresh token is worth keeping; access tokens are short-lived and reissued on restore.
+
+use std::{ env, fs, io, path::{ Path, PathBuf }, sync::Arc };
+
+use argon2::{ password_hash::rand_core::{ OsRng, RngCore }, Argon2 };
+use chacha20poly1305::{ aead::{ Aead, KeyInit }, ChaCha20Poly1305, Key, Nonce };
+use serde::{ Deserialize, Serialize };
+
+/// Name of the credential file in the app data directory.
+pub const CREDENTIALS_FILE: &str = "credentials.json";
+
+const FILE_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// What is kept of a signed-in session.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StoredCredentials {
+    pub email: String,
+    pub username: String,
+    pub refresh_token: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CredentialError {
+    #[error("failed to access credentials: {0}")] Io(#[from] io::Error),
+    #[error("credential file is malformed")] Malformed,
+    #[error("credentials cannot be decrypted, the key has changed")] Decrypt,
+    #[error("failed to derive the credential key: {0}")] Key(String),
+}
+
+/// Somewhere to keep the signed-in user's credentials between launches.
+/// The encrypted file is the only backend for now; a platform keyring could be another.
+pub trait CredentialStore: Send + Sync {
+    /// The saved credentials, or `None` when nobody is remembered.
+    fn load(&self) -> Result<Option<StoredCredentials>, CredentialError>;
+    fn save(&self, credentials: &StoredCredentials) -> Result<(), CredentialError>;
+    /// Forgets the saved credentials. Clearing an empty store is not an error.
+    fn clear(&self) -> Result<(), CredentialError>;
+}
+
+/// Builds the credential store for an app data directory.
+/// The key comes from `CREDENTIALS_PASSPHRASE` when set, otherwise from the machine id,
+/// so a copied file cannot be opened on another computer.
+pub fn from_env(data_dir: &Path) -> Result<Arc<dyn CredentialStore>, CredentialError> {
+    let secret = match env::var("CREDENTIALS_PASSPHRASE") {
+        Ok(passphrase) if !passphrase.is_empty() => passphrase,
+        _ => machine_uid::get().map_err(|err| CredentialError::Key(format!("no machine id: {err}")))?,
+    };
+    Ok(Arc::new(EncryptedFileStore::new(data_dir.join(CREDENTIALS_FILE), secret)))
+}
+
+/// Keeps credentials in a file sealed with ChaCha20-Poly1305.
+/// The key is derived from a secret with Argon2 and a salt stored next to the ciphertext.
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    secret: Vec<u8>,
+}
+
+/// Layout of the credential file, with the binary parts hex encoded.
+#[derive(Serialize, Deserialize)]
+struct SealedFile {
+    version: u8,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl EncryptedFileStore {
+    pub fn new(path: impl Into<PathBuf>, secret: impl Into<String>) -> Self {
+        EncryptedFileStore { path: path.into(), secret: secret.into().into_bytes() }
+    }
+
+    fn cipher(&self, salt: &[u8]) -> Result<ChaCha20Poly1305, CredentialError> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(&self.secret, salt, &mut key)
+            .map_err(|err| CredentialError::Key(err.to_string()))?;
+        Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+    }
+}
+
+impl CredentialStore for EncryptedFileStore {
+    fn load(&self) -> Result<Option<StoredCredentials>, CredentialError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(None);
+            }
+            Err(err) => {
+                return Err(err.into());
+            }
+        };
+        let sealed: SealedFile = serde_json::from_str(&contents).map_err(|_| CredentialError::Malformed)?;
+        if sealed.version != FILE_VERSION {
+            return Err(CredentialError::Malformed);
+        }
+        let salt = from_hex(&sealed.salt)?;
+        let nonce = from_hex(&sealed.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(CredentialError::Malformed);
+        }
+
+        let plaintext = self
+            .cipher(&salt)?
+            .decrypt(Nonce::from_slice(&nonce), from_hex(&sealed.ciphertext)?.as_slice())
+            .map_err(|_| CredentialError::Decrypt)?;
+        let credentials = serde_json::from_slice(&plaintext).map_err(|_| CredentialError::Malformed)?;
+        Ok(Some(credentials))
+    }
+
+    fn save(&self, credentials: &StoredCredentials) -> Result<(), CredentialError> {
+        // A fresh salt and nonce for every save, so no key and nonce pair is used twice
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let plaintext = serde_json::to_vec(credentials).map_err(|_| CredentialError::Malformed)?;
+        let ciphertext = self
+            .cipher(&salt)?
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| CredentialError::Key("encryption failed".to_string()))?;
+        let sealed = SealedFile {
+            version: FILE_VERSION,
+            salt: to_hex(&salt),
+            nonce: to_hex(&nonce),
+            ciphertext: to_hex(&ciphertext),
+        };
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Write next to the file and swap it in, so a crash never leaves half a file behind
+        let temp_path = self.path.with_extension("tmp");
+        fs::write(&temp_path, serde_json::to_vec(&sealed).map_err(|_| CredentialError::Malformed)?)?;
+        restrict_to_owner(&temp_path)?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), CredentialError> {
+        match fs::remove_file(&self.path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Keeps other local users from reading the file.
+#[cfg(unix)]
+pub(crate) fn restrict_to_owner(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn restrict_to_owner(_path: &Path) -> io::Result<()> {
+    Ok(()) // The app data directory is already private to the user
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>, CredentialError> {
+    // An odd trailing digit reads as "?", which fails to parse like any other bad pair
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2).unwrap_or("?"), 16))
+        .collect::<Result<_, _>>()
+        .map_err(|_| CredentialError::Malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credentials() -> StoredCredentials {
+        StoredCredentials {
+            email: "ada@example.com".to_string(),
+            username: "ada".to_string(),
+            refresh_token: "0123456789abcdef".to_string(),
+        }
+    }
+
+    fn temp_file(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("kaizen-credentials-{}-{name}", std::process::id())).join(CREDENTIALS_FILE)
+    }
+
+    #[test]
+    fn round_trips_and_clears() {
+        let store = EncryptedFileStore::new(temp_file("round-trip"), "machine");
+        assert_eq!(store.load().unwrap(), None);
+
+        store.save(&credentials()).unwrap();
+        let contents = fs::read_to_string(&store.path).unwrap();
+        assert!(!contents.contains("0123456789abcdef"));
+        assert_eq!(store.load().unwrap(), Some(credentials()));
+
+        store.clear().unwrap();
+        assert_eq!(store.load().unwrap(), None);
+        store.clear().unwrap();
+    }
+
+    #[test]
+    fn refuses_another_key() {
+        let path = temp_file("other-key");
+        EncryptedFileStore::new(&path, "machine").save(&credentials()).unwrap();
+
+        let other = EncryptedFileStore::new(&path, "another machine");
+        assert!(matches!(other.load(), Err(CredentialError::Decrypt)));
+        other.clear().unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+// Connects the embedded server to the desktop app: sign-ins and sessions go to `AuthState`,
+// status changes and reminders to the window, and the server's state to the Tauri commands.
+
+use std::sync::Arc;
+
+use kaizen::credentials::StoredCredentials;
+use kaizen::server::lifecycle::{ ServerStatus, SERVER_STATUS_EVENT };
+use kaizen::server::notifier::Notifier;
+use kaizen::server::reminders::{ ReminderEvent, REMINDER_EVENT };
+use kaizen::server::services::auth::CurrentUser;
+use kaizen::server::TauriAppState;
+use tauri::{ api::notification::Notification, AppHandle, Manager };
+
+use crate::auth_state;
+
+/// The desktop app, as the embedded server's notifier.
+pub struct Desktop {
+    app: AppHandle,
+}
+
+impl Desktop {
+    pub fn new(app: AppHandle) -> Self {
+        Desktop { app }
+    }
+}
+
+impl Notifier for Desktop {
+    fn signed_in(&self, user: CurrentUser, session_id: i32, token: String, refresh_token: String) {
+        auth_state::signed_in(&self.app, user, session_id, token, refresh_token);
+    }
+
+    fn refreshed(&self, session_id: i32, token: String, refresh_token: String) {
+        auth_state::refreshed(&self.app, session_id, token, refresh_token);
+    }
+
+    fn session_replaced(&self, old_session_id: i32, session_id: i32, token: String, refresh_token: String) {
+        auth_state::session_replaced(&self.app, old_session_id, session_id, token, refresh_token);
+    }
+
+    fn session_ended(&self, session_id: i32) {
+        auth_state::session_ended(&self.app, session_id);
+    }
+
+    fn user_signed_out(&self, email: &str) {
+        auth_state::user_signed_out(&self.app, email);
+    }
+
+    fn saved_credentials(&self) -> Option<StoredCredentials> {
+        auth_state::saved_credentials(&self.app)
+    }
+
+    fn forget_credentials(&self) {
+        auth_state::forget_credentials(&self.app);
+    }
+
+    fn status_changed(&self, status: &ServerStatus) {
+        if let Err(err) = self.app.emit_all(SERVER_STATUS_EVENT, status.clone()) {
+            tracing::warn!(error = %err, "server status event error");
+        }
+    }
+
+    fn delivers_reminders(&self) -> bool {
+        true
+    }
+
+    /// Emits the reminder to the window and shows a desktop notification.
+    fn reminder_due(&self, event: ReminderEvent) {
+        let body = if event.missed {
+            format!("Missed reminder from {}", event.fire_at.format("%Y-%m-%d %H:%M UTC"))
+        } else {
+            "Reminder".to_string()
+        };
+
+        let identifier = self.app.config().tauri.bundle.identifier.clone();
+        if let Err(err) = Notification::new(identifier).title(&event.title).body(body).show() {
+            tracing::warn!(error = %err, "reminder notification error");
+        }
+        if let Err(err) = self.app.emit_all(REMINDER_EVENT, event) {
+            tracing::warn!(error = %err, "reminder event error");
+        }
+    }
+
+    /// Shares the state with the Tauri commands, so the window can work without the HTTP server.
+    fn serving(&self, state: Arc<TauriAppState>) {
+        self.app.manage(state);
+    }
+}
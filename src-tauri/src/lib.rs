@@ -0,0 +1,7 @@
+// The Kaizen backend, shared by the desktop app and the standalone server.
+
+pub mod server; // HTTP API, services and background jobs
+pub mod credentials; // Encrypted local session storage
+pub mod logging; // Console and file log output
+#[cfg(feature = "cli")]
+pub mod client; // Terminal client backends: the HTTP API or a local SQLite file
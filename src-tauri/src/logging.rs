@@ -0,0 +1,71 @@
+// Log output for the app and the embedded server.
+// `LOG_FORMAT` picks `pretty` (the default) or `json` lines, and `RUST_LOG` the levels,
+// e.g. `RUST_LOG=debug,sqlx=warn`.
+
+use std::{ env, path::Path };
+
+use tracing_appender::rolling::{ RollingFileAppender, Rotation };
+use tracing_subscriber::{ fmt, prelude::*, EnvFilter };
+
+/// Log files are named `kaizen.<date>.log`.
+pub const LOG_FILE_PREFIX: &str = "kaizen";
+
+/// Days of log files kept before the oldest is deleted.
+const KEPT_LOG_FILES: usize = 7;
+
+/// Levels used when `RUST_LOG` is not set.
+const DEFAULT_FILTER: &str = "info,sqlx=warn";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+    Pretty, // Human readable lines
+    Json, // One JSON object per line, for log tooling
+}
+
+impl LogFormat {
+    /// Reads `LOG_FORMAT`, falling back to pretty output.
+    pub fn from_env() -> Self {
+        match env::var("LOG_FORMAT").as_deref() {
+            Ok("json") => LogFormat::Json,
+            _ => LogFormat::Pretty,
+        }
+    }
+}
+
+/// Sends logs to stdout and, given a directory, to a file there that rotates daily,
+/// so users can attach it to bug reports. Call once, before anything logs.
+pub fn init(log_dir: Option<&Path>) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let file = log_dir.and_then(|dir| {
+        let appender = RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(LOG_FILE_PREFIX)
+            .filename_suffix("log")
+            .max_log_files(KEPT_LOG_FILES)
+            .build(dir);
+        match appender {
+            Ok(appender) => Some(appender),
+            Err(err) => {
+                eprintln!("log file error: {err}");
+                None
+            }
+        }
+    });
+
+    let registry = tracing_subscriber::registry().with(filter);
+    let result = match LogFormat::from_env() {
+        LogFormat::Pretty =>
+            registry
+                .with(fmt::layer())
+                .with(file.map(|file| fmt::layer().with_ansi(false).with_writer(file)))
+                .try_init(),
+        LogFormat::Json =>
+            registry
+                .with(fmt::layer().json())
+                .with(file.map(|file| fmt::layer().json().with_writer(file)))
+                .try_init(),
+    };
+    if let Err(err) = result {
+        eprintln!("logging setup error: {err}");
+    }
+}
@@ -2,39 +2,28 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 // Importing modules from other files
-pub mod server;
-pub mod commands;
+mod commands;
+mod auth_state;
+mod desktop;
 
 // Importing necessary crates
+use auth_state::AuthState;
 use commands::*;
-use serde::Serialize;
-use tauri::Manager;
-use std::{ sync::Mutex, thread };
+use desktop::Desktop;
+use kaizen::{ credentials, logging, server, server::lifecycle::Lifecycle };
+use tauri::{ Manager, RunEvent };
+use std::sync::{ Arc, Mutex };
 use dotenv::dotenv;
 
-// Define a struct to hold authentication state
-#[derive(Serialize)]
-pub struct AuthState {
-    token: Option<String>, // Optional token string
-    logged_in: bool, // Boolean indicating whether user is logged in
-}
-
-// Implementing default values for AuthState
-impl Default for AuthState {
-    fn default() -> Self {
-        Self {
-            token: None, // Token is initially None
-            logged_in: false, // User is initially not logged in
-        }
-    }
-}
-
 // The main function of the program
-#[tokio::main]
-async fn main() {
+fn main() {
     // Load environment variables from .env file
     dotenv().ok();
 
+    // Log to the console and to a rotating file in the app log directory
+    let context = tauri::generate_context!();
+    logging::init(tauri::api::path::app_log_dir(context.config()).as_deref());
+
     // Building the Tauri application
     tauri::Builder
         ::default() // Use default Tauri settings
@@ -47,17 +36,56 @@ async fn main() {
             // Create a mutex to manage shared access to AuthState
             handle.manage(Mutex::new(AuthState::default()));
 
-            // Clone the handle to pass into the new thread
-            let boxed_handle = Box::new(handle);
+            // Keep the refresh token encrypted in the app data directory between launches.
+            // Without a store the app still works, the user just logs in every time.
+            match handle.path_resolver().app_data_dir().map(|dir| credentials::from_env(&dir)) {
+                Some(Ok(store)) => {
+                    handle.manage(store);
+                }
+                Some(Err(err)) => tracing::error!(error = %err, "credential store error"),
+                None => tracing::error!("credential store error: no app data directory"),
+            }
 
-            // Create a new thread and start the server
-            thread::spawn(move || {
-                server::init(*boxed_handle).unwrap(); // Initialize the server with the handle
-            });
+            // Start the server on its own thread, the window follows along through its status
+            let lifecycle = server::start(Arc::new(Desktop::new(handle.clone())));
+            handle.manage(lifecycle);
 
             Ok(()) // Return Ok to indicate setup was successful
         })
-        .invoke_handler(tauri::generate_handler![is_authorized]) // Set up the handler for authorization check
-        .run(tauri::generate_context!()) // Run the Tauri application
-        .expect("error while running tauri application"); // Handle any errors
+        .invoke_handler(
+            tauri::generate_handler![
+                get_server_status,
+                is_authorized,
+                current_user,
+                register,
+                login,
+                verify_two_factor,
+                sign_out,
+                request_password_reset,
+                reset_password,
+                verify_email,
+                get_tasks,
+                get_task_order,
+                create_task,
+                update_task,
+                check_task,
+                delete_task,
+                get_habits,
+                create_habit,
+                check_habit,
+                get_review_summary
+            ]
+        ) // Set up the handlers the window can invoke
+        .build(context) // Build the Tauri application
+        .expect("error while running tauri application") // Handle any errors
+        .run(|app, event| {
+            // Let the server finish its requests and close its connections before exiting
+            if let RunEvent::Exit = event {
+                if let Some(lifecycle) = app.try_state::<Arc<Lifecycle>>() {
+                    if !lifecycle.stop(server::SHUTDOWN_TIMEOUT) {
+                        tracing::warn!("server did not stop in time");
+                    }
+                }
+            }
+        });
 }
@@ -17,11 +17,12 @@ impl TaskRepository for PostgresTaskRepository {
     async fn add_task(&self, task: &Task) -> Result<(), SqlxError> {
         sqlx
             ::query(
-                "INSERT INTO tasks (user_email, title, description, date, duration, priority) VALUES ($1, $2, $3, $4, $5, $6)",
+                "INSERT INTO tasks (user_email, title, description, due_date, due_time, duration, priority) VALUES ($1, $2, $3, $4, $5, $6, $7)",
                 task.user_email,
                 task.title,
                 task.description,
-                task.date,
+                task.due_date,
+                task.due_time,
                 task.duration,
                 task.priority
             )
@@ -50,7 +51,8 @@ pub struct Task {
     pub user_email: String,
     pub title: String,
     pub description: String,
-    pub date: Option<chrono::NaiveDate>,
+    pub due_date: Option<chrono::NaiveDate>,
+    pub due_time: Option<chrono::NaiveTime>,
     pub duration: Option<i32>,
     pub priority: Option<i32>,
 }
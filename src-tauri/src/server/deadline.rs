@@ -0,0 +1,83 @@
+// Deadline model shared by the task handlers.
+// A task is due at its due time in the user's timezone, or at the end of its due day
+// when no time is given.
+
+use chrono::{ DateTime, Days, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc };
+use chrono_tz::Tz;
+
+/// Parses an IANA timezone name such as `Europe/Berlin`.
+pub fn parse_timezone(name: &str) -> Option<Tz> {
+    name.parse().ok()
+}
+
+/// Returns the instant a task falls due.
+pub fn due_at(date: NaiveDate, time: Option<NaiveTime>, tz: Tz) -> DateTime<Utc> {
+    let local = match time {
+        Some(time) => date.and_time(time),
+        // Without a time the whole day is available, so the deadline is the next midnight
+        None => (date + Days::new(1)).and_time(NaiveTime::MIN),
+    };
+    resolve_local(local, tz)
+}
+
+/// Whether a task due on `date` (at `time`) has passed at `now`.
+pub fn is_overdue(date: NaiveDate, time: Option<NaiveTime>, tz: Tz, now: DateTime<Utc>) -> bool {
+    due_at(date, time, tz) <= now
+}
+
+/// Today's date as seen by a user in `tz`.
+pub fn today(tz: Tz, now: DateTime<Utc>) -> NaiveDate {
+    now.with_timezone(&tz).date_naive()
+}
+
+/// Maps a wall-clock time onto a single instant across DST changes.
+/// A time repeated when clocks fall back resolves to its later occurrence,
+/// and a time skipped when clocks spring forward resolves to the first valid minute after the gap.
+fn resolve_local(local: NaiveDateTime, tz: Tz) -> DateTime<Utc> {
+    let mut candidate = local;
+    loop {
+        match tz.from_local_datetime(&candidate) {
+            LocalResult::Single(due) => {
+                return due.with_timezone(&Utc);
+            }
+            LocalResult::Ambiguous(_, latest) => {
+                return latest.with_timezone(&Utc);
+            }
+            LocalResult::None => {
+                candidate += chrono::Duration::try_minutes(1).unwrap();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn utc(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn due_date_without_time_ends_at_local_midnight() {
+        let tz = parse_timezone("America/New_York").unwrap();
+        assert_eq!(due_at(date(2024, 3, 20), None, tz), utc("2024-03-21T04:00:00Z"));
+        assert!(!is_overdue(date(2024, 3, 20), None, tz, utc("2024-03-21T03:59:00Z")));
+        assert!(is_overdue(date(2024, 3, 20), None, tz, utc("2024-03-21T04:00:00Z")));
+    }
+
+    #[test]
+    fn handles_dst_transitions() {
+        let tz = parse_timezone("America/New_York").unwrap();
+        // 02:30 does not exist on 2024-03-10, the deadline moves to 03:00 EDT
+        let skipped = NaiveTime::from_hms_opt(2, 30, 0);
+        assert_eq!(due_at(date(2024, 3, 10), skipped, tz), utc("2024-03-10T07:00:00Z"));
+        // 01:30 happens twice on 2024-11-03, the later one (EST) counts
+        let repeated = NaiveTime::from_hms_opt(1, 30, 0);
+        assert_eq!(due_at(date(2024, 11, 3), repeated, tz), utc("2024-11-03T06:30:00Z"));
+    }
+}
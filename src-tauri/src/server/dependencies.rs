@@ -0,0 +1,98 @@
+// Graph helpers for "blocked by" links between tasks.
+// An edge `(task_id, blocked_by)` means `task_id` cannot start until `blocked_by` is finished.
+
+use std::collections::{ BTreeSet, HashMap, HashSet };
+
+/// Whether linking `task_id` as blocked by `blocked_by` would close a cycle.
+pub fn creates_cycle(edges: &[(i32, i32)], task_id: i32, blocked_by: i32) -> bool {
+    if task_id == blocked_by {
+        return true;
+    }
+
+    let mut blockers: HashMap<i32, Vec<i32>> = HashMap::new();
+    for (task, blocker) in edges {
+        blockers.entry(*task).or_default().push(*blocker);
+    }
+
+    // Walk everything `blocked_by` transitively waits on, looking for `task_id`
+    let mut stack = vec![blocked_by];
+    let mut seen = HashSet::new();
+    while let Some(current) = stack.pop() {
+        if current == task_id {
+            return true;
+        }
+        if seen.insert(current) {
+            stack.extend(blockers.get(&current).into_iter().flatten());
+        }
+    }
+    false
+}
+
+/// Orders `tasks` so every task comes after its blockers, breaking ties by `key`.
+/// Edges touching tasks outside the list are ignored.
+/// Returns the order and, separately, any tasks caught in (or waiting on) a cycle.
+pub fn topological_order<K: Ord>(
+    tasks: &[i32],
+    edges: &[(i32, i32)],
+    key: impl Fn(i32) -> K
+) -> (Vec<i32>, Vec<i32>) {
+    let known: HashSet<i32> = tasks.iter().copied().collect();
+    let mut waiting_on: HashMap<i32, usize> = tasks
+        .iter()
+        .map(|task| (*task, 0))
+        .collect();
+    let mut dependents: HashMap<i32, Vec<i32>> = HashMap::new();
+    for (task, blocker) in edges {
+        if known.contains(task) && known.contains(blocker) {
+            *waiting_on.get_mut(task).unwrap() += 1;
+            dependents.entry(*blocker).or_default().push(*task);
+        }
+    }
+
+    let mut ready: BTreeSet<(K, i32)> = waiting_on
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(task, _)| (key(*task), *task))
+        .collect();
+    let mut order = Vec::with_capacity(tasks.len());
+    while let Some((_, task)) = ready.pop_first() {
+        order.push(task);
+        for dependent in dependents.get(&task).into_iter().flatten() {
+            let count = waiting_on.get_mut(dependent).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                ready.insert((key(*dependent), *dependent));
+            }
+        }
+    }
+
+    let mut cyclic: Vec<i32> = waiting_on
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(task, _)| task)
+        .collect();
+    cyclic.sort();
+    (order, cyclic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_cycles() {
+        let edges = [(2, 1), (3, 2)];
+        assert!(creates_cycle(&edges, 1, 3));
+        assert!(creates_cycle(&edges, 4, 4));
+        assert!(!creates_cycle(&edges, 3, 1));
+    }
+
+    #[test]
+    fn orders_blockers_first_then_by_key() {
+        // 1 is blocked by 3, 4 and 5 form a cycle
+        let edges = [(1, 3), (4, 5), (5, 4)];
+        let (order, cyclic) = topological_order(&[1, 2, 3, 4, 5], &edges, |task| task);
+        assert_eq!(order, vec![2, 3, 1]);
+        assert_eq!(cyclic, vec![4, 5]);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::server;
+use crate::server::validation::{ Checker, Field, FieldError, Kind, Rule, Schema, Validate, ValidationErrors };
+use actix_web::{ delete, get, post, web, HttpRequest, HttpResponse };
+use chrono::{ DateTime, Days, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::prelude::FromRow;
+use utoipa::ToSchema;
+use crate::server::responses::InvalidResponse;
+use crate::server::tokens::{ hash_token, new_access_token };
+use super::auth::{ verify_request_token, Scope };
+
+/// Represents a new personal access token requested by the client.
+#[derive(Deserialize, ToSchema, Debug)]
+struct CreateAccessToken {
+    name: String, // What the token is for, e.g. "nightly backup"
+    scopes: Vec<String>, // e.g. ["tasks:read", "tasks:write"]
+    expires_in_days: Option<i32>, // Never expires when omitted
+}
+
+/// Rules for new personal access tokens, also served as JSON Schema.
+pub(crate) const CREATE_ACCESS_TOKEN_SCHEMA: Schema = Schema {
+    name: "CreateAccessToken",
+    fields: &[
+        Field {
+            name: "name",
+            kind: Kind::String,
+            required: true,
+            rules: &[Rule::NotBlank, Rule::Length { min: 1, max: 100 }],
+        },
+        Field {
+            name: "expires_in_days",
+            kind: Kind::Integer,
+            required: false,
+            rules: &[Rule::Range { min: 1, max: 365 }],
+        },
+    ],
+};
+
+impl Validate for CreateAccessToken {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let checked = Checker::new(&CREATE_ACCESS_TOKEN_SCHEMA)
+            .text("name", Some(&self.name))
+            .integer("expires_in_days", self.expires_in_days.map(i64::from))
+            .finish();
+        let mut errors = checked.err().map_or_else(Vec::new, |errors| errors.errors);
+
+        if self.scopes.is_empty() {
+            errors.push(FieldError {
+                field: "scopes",
+                code: "empty",
+                message: "Needs at least one scope".into(),
+            });
+        }
+        for scope in self.scopes.iter().filter(|scope| Scope::parse(scope).is_none()) {
+            errors.push(FieldError {
+                field: "scopes",
+                code: "unknown_scope",
+                message: format!("Unknown scope: {scope}"),
+            });
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors { errors })
+        }
+    }
+}
+
+/// A personal access token as listed to its owner. The token itself is never shown again.
+#[derive(FromRow, Debug, Serialize, ToSchema)]
+struct AccessToken {
+    token_id: i32,
+    name: String,
+    scopes: Vec<String>,
+    created_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+    last_used_at: Option<DateTime<Utc>>,
+}
+
+/// A new personal access token, with the token itself.
+#[derive(Debug, Serialize, ToSchema)]
+struct CreatedAccessToken {
+    message: String,
+    token: String,
+    details: AccessToken,
+}
+
+/// Endpoint creating a personal access token. The response is the only time the token is shown.
+#[utoipa::path(
+    tag = "access tokens",
+    request_body = CreateAccessToken,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The new token, shown only this once", body = CreatedAccessToken),
+        (status = 401, description = "Missing or invalid access token", body = String),
+        (status = 403, description = "Personal access tokens cannot create tokens", body = String),
+        (status = 422, description = "Invalid fields", body = InvalidResponse)
+    )
+)]
+#[post("/auth/tokens")]
+pub async fn create_access_token(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    create: web::Json<CreateAccessToken>
+) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+    if let Err(errors) = create.validate() {
+        return errors.into_response();
+    }
+
+    let token = new_access_token();
+    let expires_at = create.expires_in_days.map(|days| Utc::now() + Days::new(days as u64));
+    let mut scopes = create.scopes.clone();
+    scopes.sort();
+    scopes.dedup();
+
+    let result = sqlx
+        ::query_as::<_, AccessToken>(
+            "INSERT INTO personal_access_tokens(email, name, token_hash, scopes, expires_at)
+            VALUES($1, $2, $3, $4, $5)
+            RETURNING token_id, name, scopes, created_at, expires_at, last_used_at"
+        )
+        .bind(&claims.email)
+        .bind(create.name.trim())
+        .bind(hash_token(&token))
+        .bind(&scopes)
+        .bind(expires_at)
+        .fetch_one(&data.pool).await;
+
+    match result {
+        Ok(created) =>
+            HttpResponse::Ok().json(CreatedAccessToken {
+                message: "Copy this token now, it will not be shown again".to_string(),
+                token,
+                details: created,
+            }),
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+/// Endpoint listing the caller's active personal access tokens.
+#[utoipa::path(
+    tag = "access tokens",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Active personal access tokens", body = [AccessToken]),
+        (status = 401, description = "Missing or invalid access token", body = String),
+        (status = 403, description = "Personal access tokens cannot list tokens", body = String)
+    )
+)]
+#[get("/auth/tokens")]
+pub async fn get_access_tokens(data: web::Data<server::TauriAppState>, req: HttpRequest) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let response = sqlx
+        ::query_as::<_, AccessToken>(
+            "SELECT token_id, name, scopes, created_at, expires_at, last_used_at
+            FROM personal_access_tokens
+            WHERE email = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())
+            ORDER BY created_at DESC"
+        )
+        .bind(&claims.email)
+        .fetch_all(&data.pool).await;
+
+    match response {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+/// Endpoint revoking one of the caller's personal access tokens.
+#[utoipa::path(
+    tag = "access tokens",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Token revoked"),
+        (status = 401, description = "Missing or invalid access token", body = String),
+        (status = 403, description = "Personal access tokens cannot revoke tokens", body = String),
+        (status = 404, description = "Access token not found", body = String)
+    )
+)]
+#[delete("/auth/tokens/{token_id}")]
+pub async fn revoke_access_token(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    path: web::Path<i32>
+) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let result = sqlx
+        ::query(
+            "UPDATE personal_access_tokens SET revoked_at = NOW()
+            WHERE token_id = $1 AND email = $2 AND revoked_at IS NULL"
+        )
+        .bind(path.into_inner())
+        .bind(&claims.email)
+        .execute(&data.pool).await;
+
+    match result {
+        Ok(done) if done.rows_affected() == 0 => HttpResponse::NotFound().json("Access token not found"),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
@@ -0,0 +1,479 @@
+use crate::server;
+use crate::server::validation::{ Checker, Field, Kind, Rule, Schema, Validate, ValidationErrors };
+use actix_web::{ get, post, web, HttpRequest, HttpResponse };
+use serde::{ Deserialize, Serialize };
+use sqlx::{ prelude::FromRow, PgPool };
+use utoipa::ToSchema;
+use crate::server::responses::{ InvalidResponse, TokenPairResponse };
+use super::auth::verify_request_token;
+use crate::server::services::sessions::{ revoke_all_sessions, start_session };
+use crate::server::services::auth::ClientInfo;
+use crate::server::services::users::{
+    hash_user_password,
+    verify_password,
+    EMAIL_RULES,
+    PASSWORD_RULES,
+    USERNAME_RULES,
+};
+use crate::server::services::account::send_verification;
+
+/// Represents a profile change received from the client. Omitted fields stay as they are.
+#[derive(Deserialize, ToSchema, Debug)]
+struct UpdateProfile {
+    username: Option<String>,
+    timezone: Option<String>, // IANA timezone name, e.g. "Europe/Berlin"
+}
+
+/// Rules for profile changes, also served as JSON Schema.
+pub(crate) const UPDATE_PROFILE_SCHEMA: Schema = Schema {
+    name: "UpdateProfile",
+    fields: &[
+        Field { name: "username", kind: Kind::String, required: false, rules: USERNAME_RULES },
+        Field { name: "timezone", kind: Kind::String, required: false, rules: &[Rule::Timezone] },
+    ],
+};
+
+impl Validate for UpdateProfile {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        Checker::new(&UPDATE_PROFILE_SCHEMA)
+            .text("username", self.username.as_deref())
+            .text("timezone", self.timezone.as_deref())
+            .finish()
+    }
+}
+
+/// Represents a password change received from the client.
+#[derive(Deserialize, ToSchema, Debug)]
+struct ChangePassword {
+    current_password: String,
+    new_password: String,
+}
+
+/// Rules for password changes; the new password follows the registration rules.
+pub(crate) const CHANGE_PASSWORD_SCHEMA: Schema = Schema {
+    name: "ChangePassword",
+    fields: &[
+        Field { name: "current_password", kind: Kind::String, required: true, rules: &[] },
+        Field { name: "new_password", kind: Kind::String, required: true, rules: PASSWORD_RULES },
+    ],
+};
+
+impl Validate for ChangePassword {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        Checker::new(&CHANGE_PASSWORD_SCHEMA)
+            .text("current_password", Some(&self.current_password))
+            .text("new_password", Some(&self.new_password))
+            .finish()
+    }
+}
+
+/// Represents an email change received from the client.
+#[derive(Deserialize, ToSchema, Debug)]
+struct ChangeEmail {
+    new_email: String,
+    password: String, // Current password, entered again
+}
+
+/// Rules for email changes, also served as JSON Schema.
+pub(crate) const CHANGE_EMAIL_SCHEMA: Schema = Schema {
+    name: "ChangeEmail",
+    fields: &[
+        Field { name: "new_email", kind: Kind::String, required: true, rules: EMAIL_RULES },
+        Field { name: "password", kind: Kind::String, required: true, rules: &[] },
+    ],
+};
+
+impl Validate for ChangeEmail {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        Checker::new(&CHANGE_EMAIL_SCHEMA)
+            .text("new_email", Some(&self.new_email))
+            .text("password", Some(&self.password))
+            .finish()
+    }
+}
+
+/// Represents an account deletion received from the client.
+#[derive(Deserialize, ToSchema, Debug)]
+struct DeleteAccount {
+    password: String, // Current password, entered again
+}
+
+/// The caller's own account as shown on the settings page.
+#[derive(FromRow, Debug, Serialize, ToSchema)]
+struct Profile {
+    username: String,
+    email: String,
+    timezone: String,
+    email_verified: bool,
+    pending_email: Option<String>, // New address waiting to be verified
+    two_factor_enabled: bool,
+}
+
+/// Endpoint returning the caller's profile.
+#[utoipa::path(
+    tag = "account",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The caller's profile", body = Profile),
+        (status = 401, description = "Missing or invalid access token", body = String),
+        (status = 404, description = "User not found", body = String)
+    )
+)]
+#[get("/account")]
+pub async fn get_account(data: web::Data<server::TauriAppState>, req: HttpRequest) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    match profile(&data.pool, &claims.email).await {
+        Ok(Some(profile)) => HttpResponse::Ok().json(profile),
+        Ok(None) => HttpResponse::NotFound().json("User not found"),
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+/// Endpoint changing the caller's username or timezone.
+#[utoipa::path(
+    tag = "account",
+    request_body = UpdateProfile,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The updated profile", body = Profile),
+        (status = 401, description = "Missing or invalid access token", body = String),
+        (status = 404, description = "User not found", body = String),
+        (status = 422, description = "Invalid fields", body = InvalidResponse)
+    )
+)]
+#[post("/account/profile")]
+pub async fn update_profile(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    update: web::Json<UpdateProfile>
+) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+    if let Err(errors) = update.validate() {
+        return errors.into_response();
+    }
+
+    let result = sqlx
+        ::query(
+            "UPDATE users SET username = COALESCE($1, username), timezone = COALESCE($2, timezone)
+            WHERE email = $3"
+        )
+        .bind(update.username.as_deref().map(str::trim))
+        .bind(&update.timezone)
+        .bind(&claims.email)
+        .execute(&data.pool).await;
+    if let Err(e) = result {
+        return HttpResponse::InternalServerError().json(e.to_string());
+    }
+
+    match profile(&data.pool, &claims.email).await {
+        Ok(Some(profile)) => HttpResponse::Ok().json(profile),
+        Ok(None) => HttpResponse::NotFound().json("User not found"),
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+/// Endpoint changing the caller's password.
+/// Every session is logged out and the caller gets the tokens of a fresh one.
+#[utoipa::path(
+    tag = "account",
+    request_body = ChangePassword,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Password changed; the tokens of the caller's new session", body = TokenPairResponse),
+        (status = 401, description = "Missing or invalid access token, or incorrect password", body = String),
+        (status = 422, description = "Invalid fields", body = InvalidResponse)
+    )
+)]
+#[post("/account/password")]
+pub async fn change_password(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    change: web::Json<ChangePassword>
+) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+    if let Err(errors) = change.validate() {
+        return errors.into_response();
+    }
+    if let Err(response) = check_password(&data.pool, &claims.email, &change.current_password).await {
+        return response;
+    }
+
+    let result = sqlx
+        ::query("UPDATE users SET password = $1 WHERE email = $2")
+        .bind(hash_user_password(change.new_password.clone()))
+        .bind(&claims.email)
+        .execute(&data.pool).await;
+    if let Err(e) = result {
+        return HttpResponse::InternalServerError().json(e.to_string());
+    }
+
+    match revoke_all_sessions(&data.pool, &claims.email).await {
+        Ok(version) => data.revocations.set_version(&claims.email, version),
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    }
+    match start_session(&data.pool, &claims.email, &ClientInfo::of(&req)).await {
+        Ok(tokens) => {
+            data.notifier.session_replaced(
+                claims.session_id,
+                tokens.session_id,
+                tokens.token.clone(),
+                tokens.refresh_token.clone()
+            );
+            HttpResponse::Ok().json(TokenPairResponse {
+                message: "Password changed, other devices have been logged out".to_string(),
+                token: tokens.token,
+                refresh_token: tokens.refresh_token,
+            })
+        }
+        Err(_) => HttpResponse::InternalServerError().json("Failed to generate token"),
+    }
+}
+
+/// Endpoint starting an email change. The account moves to the new address
+/// once the link sent there is opened, see `/auth/verify-email`.
+#[utoipa::path(
+    tag = "account",
+    request_body = ChangeEmail,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Verification link sent to the new address", body = String),
+        (status = 401, description = "Missing or invalid access token, or incorrect password", body = String),
+        (status = 409, description = "Email already registered", body = String),
+        (status = 422, description = "Invalid fields", body = InvalidResponse)
+    )
+)]
+#[post("/account/email")]
+pub async fn change_email(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    change: web::Json<ChangeEmail>
+) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+    if let Err(errors) = change.validate() {
+        return errors.into_response();
+    }
+    if let Err(response) = check_password(&data.pool, &claims.email, &change.password).await {
+        return response;
+    }
+
+    let new_email = change.new_email.trim().to_lowercase();
+    let taken: Result<Option<String>, sqlx::Error> = sqlx
+        ::query_scalar("SELECT email FROM users WHERE email = $1")
+        .bind(&new_email)
+        .fetch_optional(&data.pool).await;
+    match taken {
+        Ok(Some(_)) => {
+            return HttpResponse::Conflict().json("User with email already exists");
+        }
+        Ok(None) => {}
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    }
+
+    let result = sqlx
+        ::query("UPDATE users SET pending_email = $1 WHERE email = $2")
+        .bind(&new_email)
+        .bind(&claims.email)
+        .execute(&data.pool).await;
+    if let Err(e) = result {
+        return HttpResponse::InternalServerError().json(e.to_string());
+    }
+
+    match send_verification(&data.pool, data.mailer.clone(), &claims.email, Some(&new_email)).await {
+        Ok(_) => HttpResponse::Ok().json("Open the link sent to your new address to finish the change"),
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+/// Endpoint deleting the caller's account.
+/// Tasks, habits, reviews and everything hanging off them are deleted with it;
+/// past sessions are kept without anything pointing back to the user.
+#[utoipa::path(
+    tag = "account",
+    request_body = DeleteAccount,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Account deleted", body = String),
+        (status = 401, description = "Missing or invalid access token, or incorrect password", body = String)
+    )
+)]
+#[post("/account/delete")]
+pub async fn delete_account(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    delete: web::Json<DeleteAccount>
+) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+    if let Err(response) = check_password(&data.pool, &claims.email, &delete.password).await {
+        return response;
+    }
+
+    match remove_account(&data.pool, &claims.email).await {
+        Ok(revoked_sessions) => {
+            for session_id in revoked_sessions {
+                data.revocations.revoke_session(session_id);
+            }
+            data.notifier.user_signed_out(&claims.email);
+            HttpResponse::Ok().json("Account deleted")
+        }
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+async fn profile(pool: &PgPool, email: &str) -> Result<Option<Profile>, sqlx::Error> {
+    sqlx
+        ::query_as::<_, Profile>(
+            "SELECT username, email, timezone, email_verified_at IS NOT NULL AS email_verified,
+                pending_email, totp_enabled_at IS NOT NULL AS two_factor_enabled
+            FROM users WHERE email = $1"
+        )
+        .bind(email)
+        .fetch_optional(pool).await
+}
+
+/// Checks a password entered again before a sensitive change.
+async fn check_password(pool: &PgPool, email: &str, password: &str) -> Result<(), HttpResponse> {
+    let hashed_password: Result<String, sqlx::Error> = sqlx
+        ::query_scalar("SELECT password FROM users WHERE email = $1")
+        .bind(email)
+        .fetch_one(pool).await;
+    match hashed_password {
+        Ok(hashed_password) => {
+            if !verify_password(password.to_string(), hashed_password) {
+                return Err(HttpResponse::Unauthorized().json("Incorrect password"));
+            }
+            Ok(())
+        }
+        Err(e) => Err(HttpResponse::InternalServerError().json(e.to_string())),
+    }
+}
+
+/// Deletes a user, letting their data go with them through ON DELETE CASCADE.
+/// Returns the sessions that were still live.
+async fn remove_account(pool: &PgPool, email: &str) -> Result<Vec<i32>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Sessions have no foreign key, so they are revoked and stripped of personal details instead
+    let revoked_sessions: Vec<i32> = sqlx
+        ::query_scalar(
+            "UPDATE sessions SET revoked_at = COALESCE(revoked_at, NOW()), email = '', device = NULL, ip = NULL
+            WHERE email = $1
+            RETURNING session_id"
+        )
+        .bind(email)
+        .fetch_all(&mut *tx).await?;
+    sqlx::query("DELETE FROM users WHERE email = $1").bind(email).execute(&mut *tx).await?;
+
+    tx.commit().await?;
+    Ok(revoked_sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::test::{ call_service, init_service, TestRequest };
+    use actix_web::{ http::StatusCode, App };
+    use serde_json::json;
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+    use crate::server::{ mailer::LogMailer, notifier::Headless, TauriAppState };
+
+    #[test]
+    fn account_changes_follow_the_registration_rules() {
+        let codes = |result: Result<(), ValidationErrors>| {
+            result
+                .unwrap_err()
+                .errors.iter()
+                .map(|error| (error.field, error.code))
+                .collect::<Vec<_>>()
+        };
+
+        let update = UpdateProfile { username: Some(" ".into()), timezone: Some("Mars/Olympus".into()) };
+        assert_eq!(codes(update.validate()), [
+            ("username", "blank"),
+            ("username", "too_short"),
+            ("timezone", "unknown_timezone"),
+        ]);
+        assert!((UpdateProfile { username: None, timezone: None }).validate().is_ok());
+
+        let change = ChangePassword { current_password: "old".into(), new_password: "password".into() };
+        assert_eq!(codes(change.validate()), [("new_password", "weak_password")]);
+
+        let change = ChangeEmail { new_email: "new@localhost".into(), password: "secret1".into() };
+        assert_eq!(codes(change.validate()), [("new_email", "invalid_email")]);
+    }
+
+    #[actix_web::test]
+    async fn account_endpoints_need_a_session() {
+        // Never connects: every request below is turned away before the database is needed
+        let pool = PgPoolOptions::new().connect_lazy("postgres://localhost/kaizen").unwrap();
+        let state = TauriAppState {
+            notifier: Arc::new(Headless),
+            pool,
+            revocations: Arc::default(),
+            mailer: Arc::new(LogMailer),
+            throttle: Default::default(),
+            metrics: Default::default(),
+        };
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .service(update_profile)
+                .service(change_password)
+                .service(change_email)
+                .service(delete_account)
+        ).await;
+
+        let body = json!({
+            "username": "user",
+            "current_password": "secret1",
+            "new_password": "secret12",
+            "new_email": "new@email.com",
+            "password": "secret1"
+        });
+        for uri in ["/account/profile", "/account/password", "/account/email", "/account/delete"] {
+            let request = TestRequest::post().uri(uri).set_json(&body).to_request();
+            let response = call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{uri}");
+
+            let request = TestRequest::post()
+                .uri(uri)
+                .insert_header(("Authorization", "Bearer kzp_0123"))
+                .set_json(&body)
+                .to_request();
+            let response = call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{uri}");
+        }
+    }
+}
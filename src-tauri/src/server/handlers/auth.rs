@@ -1,27 +1,153 @@
-use std::env;
-
-use jwt_compact::{ alg::{ Hs256, Hs256Key }, AlgorithmExt, Token, UntrustedToken };
-use serde::{ Deserialize, Serialize };
-
-/// Function to verify a JWT token.
-pub fn verify_token(token_string: &str) -> Result<Token<CustomClaims>, anyhow::Error> {
-    // Load secret key from environment variable
-    let secret = env::var("TOKENSECRET").expect("TOKENSECRET not set");
-    let key = Hs256Key::new(secret.as_bytes());
-    // Parse the token.
-    let token = UntrustedToken::new(token_string)?;
-    // Before verifying the token, we might find the key which has signed the token
-    // using the `Header.key_id` field.
-    assert_eq!(token.header().key_id.as_deref(), Some("my-key"));
-    // Validate the token integrity.
-    let token: Token<CustomClaims> = Hs256.validator(&key).validate(&token)?;
-    // Validate additional conditions.
-    Ok(token)
+use actix_web::{ web, HttpRequest, HttpResponse };
+use chrono::{ DateTime, Duration, Utc };
+
+use crate::server;
+use crate::server::request_log::record_user;
+use crate::server::tokens::{ hash_token, is_access_token, verify_token, CustomClaims };
+
+/// Function to verify the bearer token from a request's `Authorization` header.
+/// Revoked tokens are rejected using the server's in-memory revocation list.
+/// Returns the token's claims so handlers can tell who is calling.
+/// Only session tokens are accepted; endpoints scripts may use go through `verify_request_scope`.
+pub async fn verify_request_token(req: &HttpRequest) -> Result<CustomClaims, HttpResponse> {
+    let token = bearer_token(req);
+    if token.is_some_and(is_access_token) {
+        return Err(HttpResponse::Forbidden().json("Personal access tokens cannot be used here"));
+    }
+
+    match token {
+        Some(token) => {
+            match verify_token(token) {
+                Ok(token) => {
+                    let claims = token.claims().custom.clone();
+                    let revoked = req
+                        .app_data::<web::Data<server::TauriAppState>>()
+                        .is_some_and(|data| data.revocations.is_revoked(&claims));
+                    if revoked {
+                        Err(HttpResponse::Unauthorized().json("Token has been revoked"))
+                    } else {
+                        record_user(&claims.email);
+                        Ok(claims)
+                    }
+                }
+                Err(e) => Err(HttpResponse::Unauthorized().json(e.to_string())),
+            }
+        }
+        None => Err(HttpResponse::Unauthorized().finish()),
+    }
+}
+
+/// Function to verify a request made with either a session token or a personal access token.
+/// Personal access tokens must carry `scope`; session tokens may do anything.
+/// Returns the caller's email.
+pub async fn verify_request_scope(req: &HttpRequest, scope: Scope) -> Result<String, HttpResponse> {
+    let Some(token) = bearer_token(req).filter(|token| is_access_token(token)) else {
+        return verify_request_token(req).await.map(|claims| claims.email);
+    };
+    let Some(data) = req.app_data::<web::Data<server::TauriAppState>>() else {
+        return Err(HttpResponse::InternalServerError().finish());
+    };
+
+    let stored = sqlx
+        ::query_as::<_, (i32, String, Vec<String>, Option<DateTime<Utc>>)>(
+            "SELECT token_id, email, scopes, last_used_at FROM personal_access_tokens
+            WHERE token_hash = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())"
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&data.pool).await;
+    let (token_id, email, scopes, last_used_at) = match stored {
+        Ok(Some(stored)) => stored,
+        Ok(None) => {
+            return Err(HttpResponse::Unauthorized().json("Access token is invalid, expired or revoked"));
+        }
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError().json(e.to_string()));
+        }
+    };
+    record_user(&email);
+    if !scopes.iter().any(|granted| granted == scope.as_str()) {
+        return Err(HttpResponse::Forbidden().json(format!("Access token lacks the {} scope", scope.as_str())));
+    }
+
+    // Only record use about once a minute, so busy scripts don't write on every request
+    if last_used_at.map_or(true, |last_used_at| Utc::now() - last_used_at > Duration::try_minutes(1).unwrap()) {
+        let result = sqlx
+            ::query("UPDATE personal_access_tokens SET last_used_at = NOW() WHERE token_id = $1")
+            .bind(token_id)
+            .execute(&data.pool).await;
+        if let Err(err) = result {
+            tracing::warn!(error = %err, "access token last-used update error");
+        }
+    }
+
+    Ok(email)
+}
+
+/// Reads the bearer token from a request's `Authorization` header.
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(" ").last())
 }
 
-/// Custom claims encoded in the token.
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
-pub struct CustomClaims {
-    #[serde(rename = "email")]
-    pub email: String, // User's email
+/// What a personal access token may be used for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    TasksRead,
+    TasksWrite,
+    HabitsRead,
+    HabitsWrite,
+    ReviewsRead,
+    ReviewsWrite,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 6] = [
+        Scope::TasksRead,
+        Scope::TasksWrite,
+        Scope::HabitsRead,
+        Scope::HabitsWrite,
+        Scope::ReviewsRead,
+        Scope::ReviewsWrite,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::TasksRead => "tasks:read",
+            Scope::TasksWrite => "tasks:write",
+            Scope::HabitsRead => "habits:read",
+            Scope::HabitsWrite => "habits:write",
+            Scope::ReviewsRead => "reviews:read",
+            Scope::ReviewsWrite => "reviews:write",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<Scope> {
+        Scope::ALL.into_iter().find(|known| known.as_str() == scope)
+    }
+}
+
+/// Refuses requests for another user's data.
+pub fn require_owner(caller: &str, user_email: &str) -> Result<(), HttpResponse> {
+    if caller.eq_ignore_ascii_case(user_email) {
+        Ok(())
+    } else {
+        Err(HttpResponse::Forbidden().json("You can only access your own data"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::tokens::new_access_token;
+    use super::*;
+
+    #[test]
+    fn parses_known_scopes_only() {
+        for scope in Scope::ALL {
+            assert_eq!(Scope::parse(scope.as_str()), Some(scope));
+        }
+        assert_eq!(Scope::parse("tasks:admin"), None);
+        assert!(is_access_token(&new_access_token()));
+    }
 }
@@ -0,0 +1,146 @@
+use crate::server;
+use crate::server::dependencies::creates_cycle;
+use actix_web::{ delete, post, web, HttpRequest, HttpResponse };
+use serde::Deserialize;
+use utoipa::ToSchema;
+use sqlx::PgPool;
+use crate::server::handlers::auth::{ verify_request_scope, Scope };
+
+/// Represents a blocked-by link received from the client.
+#[derive(Deserialize, ToSchema, Debug)]
+struct AddDependency {
+    task_id: i32, // The task that has to wait
+    blocked_by: i32, // The task that has to finish first
+}
+
+/// Outcome of trying to add a link.
+enum LinkResult {
+    Added,
+    Cycle,
+    TaskNotFound,
+}
+
+/// Endpoint for marking one of the caller's tasks as blocked by another of theirs.
+#[utoipa::path(
+    tag = "tasks",
+    request_body = AddDependency,
+    security(("bearer" = ["tasks:write"])),
+    responses(
+        (status = 200, description = "Dependency created", body = String),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope", body = String),
+        (status = 404, description = "Both tasks must exist and belong to the caller", body = String),
+        (status = 409, description = "The link would create a cycle", body = String)
+    )
+)]
+#[post("/tasks/dependencies/create")]
+pub async fn create_dependency(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    dependency: web::Json<AddDependency>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::TasksWrite).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    match add_link(&data.pool, &caller, dependency.task_id, dependency.blocked_by).await {
+        Ok(LinkResult::Added) => HttpResponse::Ok().json("Dependency created successfully"),
+        Ok(LinkResult::Cycle) =>
+            HttpResponse::Conflict().json("Dependency would create a cycle between tasks"),
+        Ok(LinkResult::TaskNotFound) =>
+            HttpResponse::NotFound().json("Both tasks must exist and belong to you"),
+        Err(err) =>
+            HttpResponse::InternalServerError().json(
+                format!("Failed to store dependency into database: {}", err)
+            ),
+    }
+}
+
+#[utoipa::path(
+    tag = "tasks",
+    security(("bearer" = ["tasks:write"])),
+    responses(
+        (status = 200, description = "Dependency removed"),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope", body = String),
+        (status = 404, description = "Dependency not found", body = String)
+    )
+)]
+#[delete("/tasks/dependencies/delete/{task_id}/{blocked_by}")]
+pub async fn delete_dependency(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::TasksWrite).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let (task_id, blocked_by) = path.into_inner();
+    let result = sqlx
+        ::query(
+            "DELETE FROM task_dependencies d USING tasks t
+            WHERE d.task_id = $1 AND d.blocked_by = $2 AND t.task_id = d.task_id AND t.user_email = $3"
+        )
+        .bind(task_id)
+        .bind(blocked_by)
+        .bind(&caller)
+        .execute(&data.pool).await;
+
+    match result {
+        Ok(done) if done.rows_affected() == 0 => HttpResponse::NotFound().json("Dependency not found"),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+async fn add_link(
+    pool: &PgPool,
+    caller: &str,
+    task_id: i32,
+    blocked_by: i32
+) -> Result<LinkResult, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Serialise link changes so two concurrent inserts cannot close a cycle together
+    sqlx::query("LOCK TABLE task_dependencies IN SHARE ROW EXCLUSIVE MODE").execute(&mut *tx).await?;
+
+    let owner: Option<String> = sqlx
+        ::query_scalar(
+            "SELECT t.user_email FROM tasks t JOIN tasks b ON b.user_email = t.user_email
+            WHERE t.task_id = $1 AND b.task_id = $2 AND t.user_email = $3"
+        )
+        .bind(task_id)
+        .bind(blocked_by)
+        .bind(caller)
+        .fetch_optional(&mut *tx).await?;
+    let Some(owner) = owner else {
+        return Ok(LinkResult::TaskNotFound);
+    };
+
+    let edges: Vec<(i32, i32)> = sqlx
+        ::query_as(
+            "SELECT d.task_id, d.blocked_by FROM task_dependencies d
+            JOIN tasks t ON t.task_id = d.task_id
+            WHERE t.user_email = $1"
+        )
+        .bind(owner)
+        .fetch_all(&mut *tx).await?;
+    if creates_cycle(&edges, task_id, blocked_by) {
+        return Ok(LinkResult::Cycle);
+    }
+
+    sqlx
+        ::query("INSERT INTO task_dependencies(task_id, blocked_by) VALUES($1, $2) ON CONFLICT DO NOTHING")
+        .bind(task_id)
+        .bind(blocked_by)
+        .execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(LinkResult::Added)
+}
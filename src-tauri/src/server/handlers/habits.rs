@@ -0,0 +1,118 @@
+use crate::server;
+use actix_web::{ get, post, web, HttpRequest, HttpResponse };
+use chrono::NaiveDate;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use crate::server::handlers::auth::{ require_owner, verify_request_scope, Scope };
+use crate::server::services::habits::{ self, Habit };
+
+/// Represents the habit data received from the client on creation.
+#[derive(Deserialize, ToSchema, Debug)]
+struct AddHabit {
+    user_email: String,
+    name: String,
+}
+
+/// Represents a day to check a habit off for. Defaults to today in the user's timezone.
+#[derive(Deserialize, ToSchema, Debug)]
+struct CheckHabit {
+    day: Option<NaiveDate>,
+}
+
+#[utoipa::path(
+    tag = "habits",
+    security(("bearer" = ["habits:read"])),
+    responses(
+        (status = 200, description = "The user's habits, oldest first", body = [Habit]),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope, or the data is someone else's", body = String)
+    )
+)]
+#[get("/habits/{user_email}")]
+pub async fn get_habits(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    path: web::Path<String>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::HabitsRead).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let user_email = path.into_inner().to_lowercase();
+    if let Err(response) = require_owner(&caller, &user_email) {
+        return response;
+    }
+    match habits::list_habits(&data.pool, &user_email).await {
+        Ok(habits) => HttpResponse::Ok().json(habits),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    tag = "habits",
+    request_body = AddHabit,
+    security(("bearer" = ["habits:write"])),
+    responses(
+        (status = 200, description = "Habit created", body = String),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope, the data is someone else's, or the email address is not verified yet", body = String)
+    )
+)]
+#[post("/habits/create")]
+pub async fn create_habit(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    habit: web::Json<AddHabit>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::HabitsWrite).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    if let Err(response) = require_owner(&caller, &habit.user_email) {
+        return response;
+    }
+
+    match habits::create_habit(&data.pool, &habit.user_email.to_lowercase(), &habit.name).await {
+        Ok(_) => HttpResponse::Ok().json("Habit created successfully"),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    tag = "habits",
+    request_body = Option<CheckHabit>,
+    security(("bearer" = ["habits:write"])),
+    responses(
+        (status = 200, description = "Habit checked off for the day"),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope, or the data is someone else's", body = String),
+        (status = 404, description = "Habit not found", body = String)
+    )
+)]
+#[post("/habits/check/{habit_id}")]
+pub async fn check_habit(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: Option<web::Json<CheckHabit>>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::HabitsWrite).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let day = body.and_then(|body| body.day);
+
+    match habits::check_habit(&data.pool, &caller, path.into_inner(), day).await {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,71 @@
+use crate::server;
+use actix_web::{ get, web, HttpResponse };
+use crate::server::responses::{ Health, Readiness };
+use sqlx::PgPool;
+
+/// Liveness: answers as long as the server is serving requests.
+#[utoipa::path(
+    tag = "health",
+    responses((status = 200, description = "The server is serving requests", body = Health))
+)]
+#[get("/healthz")]
+pub async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().json(Health { status: "ok".to_string() })
+}
+
+/// Readiness: the database answers and every migration this build ships has been applied.
+#[utoipa::path(
+    tag = "health",
+    responses(
+        (status = 200, description = "Ready to serve requests", body = Readiness),
+        (status = 503, description = "The database is unreachable or migrations are pending", body = Readiness)
+    )
+)]
+#[get("/readyz")]
+pub async fn readyz(data: web::Data<server::TauriAppState>) -> HttpResponse {
+    let database = sqlx::query("SELECT 1").execute(&data.pool).await;
+    let migrations = match &database {
+        Ok(_) => pending_migrations(&data.pool).await,
+        Err(_) => Ok(Vec::new()),
+    };
+
+    match (database, migrations) {
+        (Ok(_), Ok(pending)) if pending.is_empty() =>
+            HttpResponse::Ok().json(readiness("ready", "ok".to_string(), Some(pending))),
+        (Ok(_), Ok(pending)) =>
+            HttpResponse::ServiceUnavailable().json(readiness("not_ready", "ok".to_string(), Some(pending))),
+        (Err(e), _) | (Ok(_), Err(e)) =>
+            HttpResponse::ServiceUnavailable().json(readiness("not_ready", e.to_string(), None)),
+    }
+}
+
+fn readiness(status: &str, database: String, pending_migrations: Option<Vec<i64>>) -> Readiness {
+    Readiness { status: status.to_string(), database, pending_migrations }
+}
+
+/// Prometheus metrics in the text exposition format.
+#[utoipa::path(
+    tag = "health",
+    responses((status = 200, description = "Prometheus metrics", body = String, content_type = "text/plain"))
+)]
+#[get("/metrics")]
+pub async fn metrics(data: web::Data<server::TauriAppState>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(data.metrics.render(&data.pool))
+}
+
+/// Versions of the migrations in ./migrations not yet applied successfully.
+async fn pending_migrations(pool: &PgPool) -> Result<Vec<i64>, sqlx::Error> {
+    let applied: Vec<i64> = sqlx
+        ::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+        .fetch_all(pool).await?;
+    Ok(
+        sqlx
+            ::migrate!()
+            .iter()
+            .map(|migration| migration.version)
+            .filter(|version| !applied.contains(version))
+            .collect()
+    )
+}
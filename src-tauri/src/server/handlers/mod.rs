@@ -1,3 +1,16 @@
 pub mod users;
 pub mod tasks;
 pub mod auth;
+pub mod habits;
+pub mod reviews;
+pub mod schedule;
+pub mod reminders;
+pub mod dependencies;
+pub mod sessions;
+pub mod password_reset;
+pub mod verification;
+pub mod schemas;
+pub mod two_factor;
+pub mod access_tokens;
+pub mod account;
+pub mod health;
@@ -0,0 +1,50 @@
+use crate::server;
+use crate::server::services::{ self, account::ConfirmReset };
+use actix_web::{ post, web, HttpResponse };
+use serde::Deserialize;
+use utoipa::ToSchema;
+use crate::server::responses::InvalidResponse;
+
+/// Represents a reset request received from the client.
+#[derive(Deserialize, ToSchema, Debug)]
+struct RequestReset {
+    email: String,
+}
+
+/// Endpoint for requesting a password reset email.
+/// Answers the same, and just as fast, whether or not the account exists, so it cannot be used to probe for emails.
+#[utoipa::path(
+    tag = "password reset",
+    request_body = RequestReset,
+    responses((status = 200, description = "Reset link sent if the account exists", body = String))
+)]
+#[post("/auth/password-reset/request")]
+pub async fn request_reset(
+    data: web::Data<server::TauriAppState>,
+    request: web::Json<RequestReset>
+) -> HttpResponse {
+    services::account::request_password_reset(&data, &request.email);
+    HttpResponse::Ok().json("If the account exists, a reset link has been sent")
+}
+
+/// Endpoint for setting a new password with a reset token.
+/// Uses up the token and logs the user out everywhere.
+#[utoipa::path(
+    tag = "password reset",
+    request_body = ConfirmReset,
+    responses(
+        (status = 200, description = "Password reset, every session logged out", body = String),
+        (status = 400, description = "Reset token is invalid or expired", body = String),
+        (status = 422, description = "Invalid fields", body = InvalidResponse)
+    )
+)]
+#[post("/auth/password-reset/confirm")]
+pub async fn confirm_reset(
+    data: web::Data<server::TauriAppState>,
+    confirm: web::Json<ConfirmReset>
+) -> HttpResponse {
+    match services::account::reset_password(&data, &confirm).await {
+        Ok(()) => HttpResponse::Ok().json("Password has been reset, please log in again"),
+        Err(err) => err.into_response(),
+    }
+}
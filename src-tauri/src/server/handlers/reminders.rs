@@ -0,0 +1,204 @@
+use crate::server;
+use actix_web::{ delete, get, post, web, HttpRequest, HttpResponse };
+use chrono::{ DateTime, NaiveDate, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::{ prelude::FromRow, PgPool };
+use utoipa::ToSchema;
+use crate::server::handlers::auth::{ verify_request_scope, Scope };
+
+/// Represents the reminder data received from the client.
+/// Exactly one of `remind_at` and `minutes_before` must be set.
+#[derive(Deserialize, ToSchema, Debug)]
+struct AddReminder {
+    task_id: i32,
+    remind_at: Option<DateTime<Utc>>, // Absolute time, e.g. "2024-03-20T08:30:00Z"
+    minutes_before: Option<i32>, // Offset before the task's deadline
+}
+
+#[derive(FromRow, Debug, Serialize, ToSchema)]
+struct Reminder {
+    reminder_id: i32,
+    task_id: i32,
+    remind_at: Option<DateTime<Utc>>,
+    minutes_before: Option<i32>,
+    fired_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+#[utoipa::path(
+    tag = "reminders",
+    security(("bearer" = ["tasks:read"])),
+    responses(
+        (status = 200, description = "The task's reminders", body = [Reminder]),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope", body = String),
+        (status = 404, description = "Task not found", body = String)
+    )
+)]
+#[get("/reminders/{task_id}")]
+pub async fn get_reminders(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    path: web::Path<i32>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::TasksRead).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
+    let task_id = path.into_inner();
+
+    match owned_task_due_date(&data.pool, task_id, &caller).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::NotFound().json("Task not found");
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    }
+
+    let response = sqlx
+        ::query_as::<_, Reminder>(
+            "SELECT r.* FROM reminders r JOIN tasks t ON t.task_id = r.task_id
+            WHERE r.task_id = $1 AND t.user_email = $2 ORDER BY r.reminder_id"
+        )
+        .bind(task_id)
+        .bind(&caller)
+        .fetch_all(&data.pool).await;
+
+    match response {
+        Ok(reminders) => HttpResponse::Ok().json(reminders),
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+#[utoipa::path(
+    tag = "reminders",
+    request_body = AddReminder,
+    security(("bearer" = ["tasks:write"])),
+    responses(
+        (status = 200, description = "The stored reminder", body = Reminder),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope", body = String),
+        (status = 400, description = "Not exactly one of remind_at and minutes_before, or the task has no due date", body = String),
+        (status = 404, description = "Task not found", body = String)
+    )
+)]
+#[post("/reminders/create")]
+pub async fn create_reminder(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    reminder: web::Json<AddReminder>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::TasksWrite).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    match (reminder.remind_at, reminder.minutes_before) {
+        (Some(_), None) => {}
+        (None, Some(minutes)) if minutes >= 0 => {}
+        (None, Some(_)) => {
+            return HttpResponse::BadRequest().json("minutes_before cannot be negative");
+        }
+        _ => {
+            return HttpResponse::BadRequest().json(
+                "Set either remind_at or minutes_before, but not both"
+            );
+        }
+    }
+
+    let pool = &data.pool;
+
+    match owned_task_due_date(pool, reminder.task_id, &caller).await {
+        // Relative reminders need a deadline to count back from
+        Ok(Some(None)) if reminder.minutes_before.is_some() => {
+            return HttpResponse::BadRequest().json("Task has no due date to count back from");
+        }
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::NotFound().json("Task not found");
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    }
+
+    let result = sqlx
+        ::query_as::<_, Reminder>(
+            "INSERT INTO reminders(task_id, remind_at, minutes_before)
+            SELECT task_id, $2, $3 FROM tasks WHERE task_id = $1 AND user_email = $4
+            RETURNING *"
+        )
+        .bind(reminder.task_id)
+        .bind(reminder.remind_at)
+        .bind(reminder.minutes_before)
+        .bind(&caller)
+        .fetch_optional(pool).await;
+
+    match result {
+        Ok(Some(stored)) => HttpResponse::Ok().json(stored),
+        // The task was deleted in the meantime
+        Ok(None) => HttpResponse::NotFound().json("Task not found"),
+        Err(err) =>
+            HttpResponse::InternalServerError().json(
+                format!("Failed to store reminder into database: {}", err)
+            ),
+    }
+}
+
+#[utoipa::path(
+    tag = "reminders",
+    security(("bearer" = ["tasks:write"])),
+    responses(
+        (status = 200, description = "Reminder deleted"),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope", body = String),
+        (status = 404, description = "Reminder not found", body = String)
+    )
+)]
+#[delete("/reminders/delete/{reminder_id}")]
+pub async fn delete_reminder(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    path: web::Path<i32>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::TasksWrite).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let result = sqlx
+        ::query(
+            "DELETE FROM reminders r USING tasks t
+            WHERE r.reminder_id = $1 AND t.task_id = r.task_id AND t.user_email = $2"
+        )
+        .bind(path.into_inner())
+        .bind(&caller)
+        .execute(&data.pool).await;
+
+    match result {
+        Ok(done) if done.rows_affected() == 0 => HttpResponse::NotFound().json("Reminder not found"),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+/// The due date of a task, if the task is the user's. Other users' tasks are as good as missing.
+async fn owned_task_due_date(
+    pool: &PgPool,
+    task_id: i32,
+    email: &str
+) -> Result<Option<Option<NaiveDate>>, sqlx::Error> {
+    sqlx
+        ::query_scalar("SELECT due_date FROM tasks WHERE task_id = $1 AND user_email = $2")
+        .bind(task_id)
+        .bind(email)
+        .fetch_optional(pool).await
+}
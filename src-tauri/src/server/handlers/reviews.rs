@@ -0,0 +1,115 @@
+use crate::server;
+use actix_web::{ get, post, web, HttpRequest, HttpResponse };
+use chrono::NaiveDate;
+use serde::Deserialize;
+use utoipa::IntoParams;
+use crate::server::handlers::auth::{ require_owner, verify_request_scope, Scope };
+use crate::server::services::reviews::{ self, ReviewPeriod, ReviewSummary, SaveReview, StoredReview };
+
+/// Query parameters selecting the period to review.
+#[derive(Deserialize, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct ReviewQuery {
+    period: ReviewPeriod,
+    date: NaiveDate,
+}
+
+#[utoipa::path(
+    tag = "reviews",
+    params(ReviewQuery),
+    security(("bearer" = ["reviews:read"])),
+    responses(
+        (status = 200, description = "What was done, slipped and kept up during the period", body = ReviewSummary),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope, or the data is someone else's", body = String)
+    )
+)]
+#[get("/reviews/{user_email}/summary")]
+pub async fn get_review_summary(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<ReviewQuery>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::ReviewsRead).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let user_email = path.into_inner().to_lowercase();
+    if let Err(response) = require_owner(&caller, &user_email) {
+        return response;
+    }
+    match reviews::review_summary(&data.pool, &user_email, query.period, query.date).await {
+        Ok(summary) => HttpResponse::Ok().json(summary),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    tag = "reviews",
+    security(("bearer" = ["reviews:read"])),
+    responses(
+        (status = 200, description = "The user's reviews, newest first", body = [StoredReview]),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope, or the data is someone else's", body = String)
+    )
+)]
+#[get("/reviews/{user_email}")]
+pub async fn get_reviews(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    path: web::Path<String>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::ReviewsRead).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let user_email = path.into_inner().to_lowercase();
+    if let Err(response) = require_owner(&caller, &user_email) {
+        return response;
+    }
+    match reviews::list_reviews(&data.pool, &user_email).await {
+        Ok(reviews) => HttpResponse::Ok().json(reviews),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Stores a review and carries the selected unfinished tasks over in one transaction.
+#[utoipa::path(
+    tag = "reviews",
+    request_body = SaveReview,
+    security(("bearer" = ["reviews:write"])),
+    responses(
+        (status = 200, description = "The stored review", body = StoredReview),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope, or the data is someone else's", body = String)
+    )
+)]
+#[post("/reviews/create")]
+pub async fn create_review(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    review: web::Json<SaveReview>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::ReviewsWrite).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let user_email = review.user_email.to_lowercase();
+    if let Err(response) = require_owner(&caller, &user_email) {
+        return response;
+    }
+    match reviews::save_review(&data.pool, &user_email, &review).await {
+        Ok(stored) => HttpResponse::Ok().json(stored),
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,108 @@
+use crate::server;
+use crate::server::deadline;
+use crate::server::scheduler::{ self, FixedBlock, PlanOptions, PlanTask, Schedule, WorkingHours };
+use actix_web::{ post, web, HttpRequest, HttpResponse };
+use chrono::{ NaiveDate, Utc };
+use serde::Deserialize;
+use utoipa::ToSchema;
+use crate::server::handlers::auth::{ require_owner, verify_request_scope, Scope };
+use crate::server::services::tasks::{ load_dependencies, Task };
+use crate::server::services::users::user_timezone;
+
+/// Longest horizon overflow may spill into.
+const MAX_PLAN_DAYS: u32 = 31;
+
+/// Represents the planning constraints received from the client.
+#[derive(Deserialize, ToSchema, Debug)]
+struct PlanRequest {
+    user_email: String,
+    start_date: Option<NaiveDate>, // Defaults to today in the user's timezone
+    days: Option<u32>, // Defaults to a week
+    working_hours: WorkingHours,
+    #[serde(default)]
+    fixed_blocks: Vec<FixedBlock>,
+}
+
+/// Endpoint that plans the user's unfinished tasks into a time-blocked schedule.
+#[utoipa::path(
+    tag = "schedule",
+    request_body = PlanRequest,
+    security(("bearer" = ["tasks:read"])),
+    responses(
+        (status = 200, description = "The planned blocks and the tasks left out", body = Schedule),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope, or the data is someone else's", body = String),
+        (status = 400, description = "Working hours end before they start", body = String)
+    )
+)]
+#[post("/schedule/plan")]
+pub async fn plan_schedule(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    request: web::Json<PlanRequest>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::TasksRead).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let request = request.into_inner();
+    if request.working_hours.start >= request.working_hours.end {
+        return HttpResponse::BadRequest().json("Working hours must end after they start");
+    }
+
+    let user_email = request.user_email.to_lowercase();
+    if let Err(response) = require_owner(&caller, &user_email) {
+        return response;
+    }
+    let tz = match user_timezone(&data.pool, &user_email).await {
+        Ok(tz) => tz,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    };
+    let dependencies = match load_dependencies(&data.pool, &user_email).await {
+        Ok(dependencies) => dependencies,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    };
+    let response = sqlx
+        ::query_as::<_, Task>("SELECT * FROM tasks WHERE user_email = $1 AND checked = FALSE")
+        .bind(user_email)
+        .fetch_all(&data.pool).await;
+
+    let tasks = match response {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    };
+
+    let tasks = tasks
+        .into_iter()
+        .map(|task| PlanTask {
+            deadline: task.due_date,
+            task_id: task.task_id,
+            title: task.title,
+            duration: task.duration,
+            priority: task.priority,
+            // Finished blockers no longer hold anything up
+            blocked_by: dependencies
+                .iter()
+                .filter(|d| d.task_id == task.task_id && !d.blocker_checked)
+                .map(|d| d.blocked_by)
+                .collect(),
+        })
+        .collect();
+    let options = PlanOptions {
+        start_date: request.start_date.unwrap_or_else(|| deadline::today(tz, Utc::now())),
+        days: request.days.unwrap_or(7).clamp(1, MAX_PLAN_DAYS),
+        working_hours: request.working_hours,
+        fixed_blocks: request.fixed_blocks,
+    };
+
+    HttpResponse::Ok().json(scheduler::plan(tasks, &options))
+}
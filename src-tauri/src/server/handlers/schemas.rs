@@ -0,0 +1,39 @@
+use actix_web::{ get, web, HttpResponse };
+use crate::server::services::{
+    account::CONFIRM_RESET_SCHEMA,
+    auth::REGISTER_USER_SCHEMA,
+    tasks::ADD_TASK_SCHEMA,
+};
+use crate::server::validation::Schema;
+use super::{
+    access_tokens::CREATE_ACCESS_TOKEN_SCHEMA,
+    account::{ CHANGE_EMAIL_SCHEMA, CHANGE_PASSWORD_SCHEMA, UPDATE_PROFILE_SCHEMA },
+};
+
+/// Every payload with published validation rules.
+const SCHEMAS: [&Schema; 7] = [
+    &REGISTER_USER_SCHEMA,
+    &ADD_TASK_SCHEMA,
+    &CONFIRM_RESET_SCHEMA,
+    &CREATE_ACCESS_TOKEN_SCHEMA,
+    &UPDATE_PROFILE_SCHEMA,
+    &CHANGE_PASSWORD_SCHEMA,
+    &CHANGE_EMAIL_SCHEMA,
+];
+
+/// Endpoint serving a payload's validation rules as JSON Schema, e.g. `/schemas/AddTask`.
+#[utoipa::path(
+    tag = "schemas",
+    params(("name" = String, Path, description = "Payload name, e.g. AddTask")),
+    responses(
+        (status = 200, description = "The payload's validation rules as JSON Schema", body = Object),
+        (status = 404, description = "Schema not found", body = String)
+    )
+)]
+#[get("/schemas/{name}")]
+pub async fn get_schema(path: web::Path<String>) -> HttpResponse {
+    match SCHEMAS.iter().find(|schema| schema.name == path.as_str()) {
+        Some(schema) => HttpResponse::Ok().json(schema.to_json_schema()),
+        None => HttpResponse::NotFound().json("Schema not found"),
+    }
+}
@@ -0,0 +1,196 @@
+use crate::server;
+use crate::server::services::{ self, auth::ClientInfo };
+use crate::server::services::sessions::{ revoke_all_sessions, rotate_refresh_token, RefreshError };
+use actix_web::{ delete, get, post, web, HttpRequest, HttpResponse };
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::prelude::FromRow;
+use utoipa::ToSchema;
+use crate::server::responses::TokenPairResponse;
+use crate::server::handlers::auth::verify_request_token;
+
+/// Represents the refresh token received from the client.
+#[derive(Deserialize, ToSchema, Debug)]
+struct RefreshRequest {
+    refresh_token: String,
+}
+
+/// A signed-in device, as shown to the user.
+#[derive(FromRow, Debug, Serialize, ToSchema)]
+struct Session {
+    session_id: i32,
+    device: Option<String>,
+    ip: Option<String>,
+    created_at: DateTime<Utc>,
+    last_used_at: DateTime<Utc>,
+    #[sqlx(skip)]
+    current: bool, // The session making the request
+}
+
+/// Endpoint that swaps a refresh token for a new access token and a new refresh token.
+/// Presenting a refresh token that was already swapped revokes its whole session.
+#[utoipa::path(
+    tag = "sessions",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "A new token pair", body = TokenPairResponse),
+        (status = 401, description = "Refresh token invalid, expired or already used", body = String)
+    )
+)]
+#[post("/auth/refresh")]
+pub async fn refresh(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    body: web::Json<RefreshRequest>
+) -> HttpResponse {
+    let ip = ClientInfo::of(&req).ip;
+    match rotate_refresh_token(&data.pool, &body.refresh_token, ip).await {
+        Ok(tokens) =>
+            HttpResponse::Ok().json(TokenPairResponse {
+                message: "Session refreshed".to_string(),
+                token: tokens.token,
+                refresh_token: tokens.refresh_token,
+            }),
+        Err(RefreshError::Invalid) =>
+            HttpResponse::Unauthorized().json("Refresh token is invalid or expired"),
+        Err(RefreshError::Reused) =>
+            HttpResponse::Unauthorized().json("Refresh token was already used, session revoked"),
+        Err(RefreshError::Database(e)) => HttpResponse::InternalServerError().json(e.to_string()),
+        Err(RefreshError::Token) =>
+            HttpResponse::InternalServerError().json("Failed to generate token"),
+    }
+}
+
+/// Endpoint listing the caller's active sessions.
+#[utoipa::path(
+    tag = "sessions",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Active sessions, most recently used first", body = [Session]),
+        (status = 401, description = "Missing or invalid access token", body = String)
+    )
+)]
+#[get("/auth/sessions")]
+pub async fn get_sessions(data: web::Data<server::TauriAppState>, req: HttpRequest) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let response = sqlx
+        ::query_as::<_, Session>(
+            "SELECT session_id, device, ip, created_at, last_used_at FROM sessions
+            WHERE email = $1 AND revoked_at IS NULL
+            ORDER BY last_used_at DESC"
+        )
+        .bind(&claims.email)
+        .fetch_all(&data.pool).await;
+
+    match response {
+        Ok(mut sessions) => {
+            for session in sessions.iter_mut() {
+                session.current = session.session_id == claims.session_id;
+            }
+            HttpResponse::Ok().json(sessions)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+/// Endpoint revoking one of the caller's sessions, e.g. a lost device.
+#[utoipa::path(
+    tag = "sessions",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Session revoked"),
+        (status = 401, description = "Missing or invalid access token", body = String),
+        (status = 404, description = "Session not found", body = String)
+    )
+)]
+#[delete("/auth/sessions/{session_id}")]
+pub async fn revoke_session(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    path: web::Path<i32>
+) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let session_id = path.into_inner();
+    let result = sqlx
+        ::query(
+            "UPDATE sessions SET revoked_at = NOW()
+            WHERE session_id = $1 AND email = $2 AND revoked_at IS NULL"
+        )
+        .bind(session_id)
+        .bind(&claims.email)
+        .execute(&data.pool).await;
+
+    match result {
+        Ok(done) if done.rows_affected() == 0 => HttpResponse::NotFound().json("Session not found"),
+        Ok(_) => {
+            data.revocations.revoke_session(session_id);
+            data.notifier.session_ended(session_id);
+            HttpResponse::Ok().finish()
+        }
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+/// Endpoint for logging out: revokes the current session and the access token used to call it.
+#[utoipa::path(
+    tag = "sessions",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Logged out", body = String),
+        (status = 401, description = "Missing or invalid access token", body = String)
+    )
+)]
+#[post("/auth/logout")]
+pub async fn logout(data: web::Data<server::TauriAppState>, req: HttpRequest) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    match services::auth::logout(&data, &claims).await {
+        Ok(_) => HttpResponse::Ok().json("You are now logged out"),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Endpoint for logging out on every device by bumping the user's token version.
+#[utoipa::path(
+    tag = "sessions",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Logged out on every device", body = String),
+        (status = 401, description = "Missing or invalid access token", body = String)
+    )
+)]
+#[post("/auth/logout-all")]
+pub async fn logout_all(data: web::Data<server::TauriAppState>, req: HttpRequest) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    match revoke_all_sessions(&data.pool, &claims.email).await {
+        Ok(version) => {
+            data.revocations.set_version(&claims.email, version);
+            data.notifier.user_signed_out(&claims.email);
+            HttpResponse::Ok().json("You are now logged out everywhere")
+        }
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
@@ -1,75 +1,146 @@
 use crate::server;
 use actix_web::{ delete, get, post, web, HttpRequest, HttpResponse };
-use serde::{ Deserialize, Serialize };
-use sqlx::{ prelude::FromRow, Executor };
-use crate::server::handlers::auth;
-
-#[derive(Deserialize, Debug)]
-struct AddTask {
-    user_email: String,
-    title: String,
-    description: String,
-    date: Option<String>,
-    duration: Option<i32>,
-    priority: Option<i32>,
+use serde::Deserialize;
+use utoipa::{ IntoParams, ToSchema };
+use crate::server::handlers::auth::{ require_owner, verify_request_scope, Scope };
+use crate::server::responses::InvalidResponse;
+use crate::server::services::tasks::{ self, AddTask, CheckedTask, Task, TaskOrder };
+
+/// Query parameters for listing tasks.
+#[derive(Deserialize, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct TaskQuery {
+    #[serde(default)]
+    hide_blocked: bool, // Leave out tasks waiting on an unfinished blocker
 }
 
-#[derive(FromRow, Debug, Serialize)]
-struct Task {
-    task_id: i32,
-    user_email: String,
-    title: String,
-    description: String,
-    checked: bool,
-    date: Option<String>,
-    duration: Option<i32>,
-    priority: Option<i32>,
+/// Query parameters selecting the project to order.
+#[derive(Deserialize, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct ProjectQuery {
+    project: Option<String>, // Tasks without a project when omitted
 }
 
-// Function to verify token from request header
-async fn verify_request_token(req: &HttpRequest) -> Result<(), HttpResponse> {
-    let token = req
-        .headers()
-        .get("Authorization")
-        .and_then(|value| value.to_str().ok())
-        .and_then(|value| value.split(" ").last());
-
-    match token {
-        Some(token) => {
-            match auth::verify_token(token) {
-                Ok(_) => Ok(()),
-                Err(e) => Err(HttpResponse::Unauthorized().json(e.to_string())),
-            }
-        }
-        None => Err(HttpResponse::Unauthorized().finish()),
-    }
+/// Represents a task being checked off or reopened.
+#[derive(Deserialize, ToSchema, Debug)]
+struct CheckTask {
+    checked: bool,
 }
 
+#[utoipa::path(
+    tag = "tasks",
+    params(TaskQuery),
+    security(("bearer" = ["tasks:read"])),
+    responses(
+        (status = 200, description = "The user's tasks, flagged as overdue or blocked", body = [Task]),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope, or the data is someone else's", body = String)
+    )
+)]
 #[get("/tasks/{user_email}")]
 pub async fn get_tasks(
     data: web::Data<server::TauriAppState>,
     req: HttpRequest, // Add HttpRequest to parameters
-    path: web::Path<String>
+    path: web::Path<String>,
+    query: web::Query<TaskQuery>
 ) -> HttpResponse {
     // Use the new function for token verification
-    if let Err(response) = verify_request_token(&req).await {
+    let caller = match verify_request_scope(&req, Scope::TasksRead).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let user_email = path.into_inner().to_lowercase();
+    if let Err(response) = require_owner(&caller, &user_email) {
         return response;
     }
+    match tasks::list_tasks(&data.pool, &user_email, query.hide_blocked).await {
+        Ok(tasks) => HttpResponse::Ok().json(tasks),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Endpoint returning a project's tasks with every blocker before the tasks it blocks.
+#[utoipa::path(
+    tag = "tasks",
+    params(ProjectQuery),
+    security(("bearer" = ["tasks:read"])),
+    responses(
+        (status = 200, description = "The project's tasks, blockers first", body = TaskOrder),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope, or the data is someone else's", body = String)
+    )
+)]
+#[get("/tasks/{user_email}/order")]
+pub async fn get_task_order(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<ProjectQuery>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::TasksRead).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
 
     let user_email = path.into_inner().to_lowercase();
-    let pool = &data.pool;
-    let response = sqlx
-        ::query_as::<_, Task>("SELECT * FROM tasks WHERE user_email = $1")
-        .bind(user_email)
-        .fetch_all(pool).await;
-    match response {
-        Ok(tasks) => {
-            return HttpResponse::Ok().json(tasks);
+    if let Err(response) = require_owner(&caller, &user_email) {
+        return response;
+    }
+    match tasks::task_order(&data.pool, &user_email, query.project.as_deref()).await {
+        Ok(order) => HttpResponse::Ok().json(order),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Endpoint for checking off (or reopening) a task.
+/// Checking off a blocker reports the dependents it unblocked.
+#[utoipa::path(
+    tag = "tasks",
+    request_body = CheckTask,
+    security(("bearer" = ["tasks:write"])),
+    responses(
+        (status = 200, description = "The task's new state and the dependents it unblocked", body = CheckedTask),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope, or the data is someone else's", body = String),
+        (status = 404, description = "Task not found", body = String)
+    )
+)]
+#[post("/tasks/check/{task_id}")]
+pub async fn check_task(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<CheckTask>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::TasksWrite).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
         }
-        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    };
+
+    match tasks::check_task(&data.pool, &caller, path.into_inner(), body.checked).await {
+        Ok(checked) => HttpResponse::Ok().json(checked),
+        Err(e) => e.into_response(),
     }
 }
 
+#[utoipa::path(
+    tag = "tasks",
+    request_body = AddTask,
+    security(("bearer" = ["tasks:write"])),
+    responses(
+        (status = 200, description = "Task created", body = String),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope, the data is someone else's, or the email address is not verified yet", body = String),
+        (status = 422, description = "Invalid fields", body = InvalidResponse)
+    )
+)]
 #[post("/tasks/create")]
 pub async fn create_task(
     data: web::Data<server::TauriAppState>,
@@ -77,71 +148,77 @@ pub async fn create_task(
     task: web::Json<AddTask>
 ) -> HttpResponse {
     // Use the new function for token verification
-    if let Err(response) = verify_request_token(&req).await {
-        return response;
-    }
-
-    let pool = &data.pool;
-
-    // Extract data from payload
-    let (user_email, title, description, date, duration, priority) = (
-        task.user_email.clone().to_lowercase(),
-        task.title.clone(),
-        task.description.clone(),
-        task.date.clone(),
-        task.duration,
-        task.priority,
-    );
+    let caller = match verify_request_scope(&req, Scope::TasksWrite).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
 
-    // Store task in database
-    let result = sqlx
-        ::query(
-            "INSERT INTO tasks(user_email, title, description, checked, date, duration, priority)
-        VALUES($1, $2, $3, $4, $5, $6, $7)"
-        )
-        .bind(user_email)
-        .bind(title)
-        .bind(description)
-        .bind(false) // Assuming checked is always false initially
-        .bind(date)
-        .bind(duration)
-        .bind(priority)
-        .execute(pool).await;
+    match tasks::create_task(&data.pool, &caller, &task).await {
+        Ok(_) => HttpResponse::Ok().json("Task created successfully"),
+        Err(e) => e.into_response(),
+    }
+}
 
-    // Handle error case
-    if let Err(err) = result {
-        let error_message: String;
-        let err_string = format!("{}", { err });
-        error_message = format!("Failed to store task into database: {}", err_string);
+/// Endpoint replacing a task's fields. Whether it is checked off is kept.
+#[utoipa::path(
+    tag = "tasks",
+    request_body = AddTask,
+    security(("bearer" = ["tasks:write"])),
+    responses(
+        (status = 200, description = "Task updated", body = String),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope, or the data is someone else's", body = String),
+        (status = 404, description = "Task not found", body = String),
+        (status = 422, description = "Invalid fields", body = InvalidResponse)
+    )
+)]
+#[post("/tasks/update/{task_id}")]
+pub async fn update_task(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    task: web::Json<AddTask>
+) -> HttpResponse {
+    let caller = match verify_request_scope(&req, Scope::TasksWrite).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
+        }
+    };
 
-        // Return an error response
-        return HttpResponse::InternalServerError().json(error_message);
+    match tasks::update_task(&data.pool, &caller, path.into_inner(), &task).await {
+        Ok(_) => HttpResponse::Ok().json("Task updated successfully"),
+        Err(e) => e.into_response(),
     }
-    // Implement task creation logic here
-    HttpResponse::Ok().json("Task created successfully")
 }
 
+#[utoipa::path(
+    tag = "tasks",
+    security(("bearer" = ["tasks:write"])),
+    responses(
+        (status = 200, description = "Task deleted"),
+        (status = 401, description = "Missing or invalid token", body = String),
+        (status = 403, description = "Token lacks the scope, or the data is someone else's", body = String),
+        (status = 404, description = "Task not found", body = String)
+    )
+)]
 #[delete("/tasks/delete/{task_id}")]
 pub async fn delete_task(
     data: web::Data<server::TauriAppState>,
     req: HttpRequest,
-    path: web::Path<String>
+    path: web::Path<i32>
 ) -> HttpResponse {
-    if let Err(response) = verify_request_token(&req).await {
-        return response;
-    }
-
-    let task_id: u32 = path.into_inner().parse().expect("task_id should be a number");
-    let pool = &data.pool;
-
-    let result = pool.execute(
-        sqlx::query("DELETE FROM tasks WHERE task_id = $1").bind(task_id as i32)
-    ).await;
-
-    match result {
-        Ok(_) => {
-            return HttpResponse::Ok().finish();
+    let caller = match verify_request_scope(&req, Scope::TasksWrite).await {
+        Ok(caller) => caller,
+        Err(response) => {
+            return response;
         }
-        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    };
+
+    match tasks::delete_task(&data.pool, &caller, path.into_inner()).await {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(e) => e.into_response(),
     }
 }
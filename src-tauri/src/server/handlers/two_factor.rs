@@ -0,0 +1,302 @@
+use crate::server;
+use crate::server::services::{ self, auth::ClientInfo };
+use crate::server::totp;
+use actix_web::{ post, web, HttpRequest, HttpResponse };
+use argon2::password_hash::rand_core::{ OsRng, RngCore };
+use chrono::{ DateTime, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::{ prelude::FromRow, PgPool };
+use utoipa::ToSchema;
+use crate::server::responses::SessionResponse;
+use crate::server::services::two_factor::hash_recovery_code;
+use super::auth::verify_request_token;
+use crate::server::services::users::verify_password;
+use super::users::session_response;
+
+/// Recovery codes handed out when 2FA is turned on.
+const RECOVERY_CODES: usize = 10;
+
+/// Issuer shown in authenticator apps.
+const ISSUER: &str = "Kaizen";
+
+/// Represents the first code from a newly enrolled authenticator.
+#[derive(Deserialize, ToSchema, Debug)]
+struct ActivateTwoFactor {
+    code: String,
+}
+
+/// Represents the second login step received from the client.
+#[derive(Deserialize, ToSchema, Debug)]
+struct VerifyTwoFactor {
+    challenge_token: String, // Returned by login after a correct password
+    code: Option<String>, // From the authenticator app
+    recovery_code: Option<String>, // Used instead of a code when the authenticator is lost
+}
+
+/// Represents the password re-entered to turn 2FA off.
+#[derive(Deserialize, ToSchema, Debug)]
+struct DisableTwoFactor {
+    password: String,
+}
+
+/// A new secret waiting to be confirmed with a code.
+#[derive(Serialize, ToSchema, Debug)]
+struct Enrollment {
+    secret: String, // Base32, for typing into the authenticator by hand
+    provisioning_uri: String, // otpauth:// URI, usually shown as a QR code
+}
+
+/// The recovery codes handed out when 2FA is turned on.
+#[derive(Serialize, ToSchema, Debug)]
+struct RecoveryCodes {
+    message: String,
+    recovery_codes: Vec<String>,
+}
+
+#[derive(FromRow, Debug)]
+struct TotpState {
+    totp_secret: Option<String>,
+    totp_enabled_at: Option<DateTime<Utc>>,
+}
+
+/// Endpoint starting 2FA enrolment: returns a new secret and its provisioning URI.
+/// Nothing changes for logins until the secret is confirmed at `/auth/2fa/activate`.
+#[utoipa::path(
+    tag = "two-factor",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "A new secret to add to an authenticator app", body = Enrollment),
+        (status = 401, description = "Missing or invalid access token", body = String),
+        (status = 409, description = "Two-factor authentication is already enabled", body = String)
+    )
+)]
+#[post("/auth/2fa/enroll")]
+pub async fn enroll(data: web::Data<server::TauriAppState>, req: HttpRequest) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    match totp_state(&data.pool, &claims.email).await {
+        Ok(state) if state.totp_enabled_at.is_some() => {
+            return HttpResponse::Conflict().json("Two-factor authentication is already enabled");
+        }
+        Ok(_) => {}
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    }
+
+    let secret = totp::generate_secret();
+    let result = sqlx
+        ::query("UPDATE users SET totp_secret = $1, totp_last_step = NULL WHERE email = $2")
+        .bind(&secret)
+        .bind(&claims.email)
+        .execute(&data.pool).await;
+
+    match result {
+        Ok(_) =>
+            HttpResponse::Ok().json(Enrollment {
+                provisioning_uri: totp::provisioning_uri(&secret, &claims.email, ISSUER),
+                secret,
+            }),
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+/// Endpoint turning 2FA on once the authenticator shows a matching code.
+/// Returns the recovery codes, which are not shown again.
+#[utoipa::path(
+    tag = "two-factor",
+    request_body = ActivateTwoFactor,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Enabled; the recovery codes, shown only this once", body = RecoveryCodes),
+        (status = 400, description = "Incorrect code, or enrolment not started", body = String),
+        (status = 401, description = "Missing or invalid access token", body = String),
+        (status = 409, description = "Two-factor authentication is already enabled", body = String)
+    )
+)]
+#[post("/auth/2fa/activate")]
+pub async fn activate(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    activate: web::Json<ActivateTwoFactor>
+) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let secret = match totp_state(&data.pool, &claims.email).await {
+        Ok(state) if state.totp_enabled_at.is_some() => {
+            return HttpResponse::Conflict().json("Two-factor authentication is already enabled");
+        }
+        Ok(TotpState { totp_secret: Some(secret), .. }) => secret,
+        Ok(_) => {
+            return HttpResponse::BadRequest().json("Start enrolment at /auth/2fa/enroll first");
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    };
+
+    let Some(step) = totp::verify(&secret, &activate.code, Utc::now().timestamp(), None) else {
+        return HttpResponse::BadRequest().json("Incorrect code");
+    };
+
+    match enable(&data.pool, &claims.email, step).await {
+        Ok(recovery_codes) =>
+            HttpResponse::Ok().json(RecoveryCodes {
+                message: "Two-factor authentication enabled".to_string(),
+                recovery_codes,
+            }),
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+/// Endpoint for the second login step: swaps a challenge token and a code for a session.
+#[utoipa::path(
+    tag = "two-factor",
+    request_body = VerifyTwoFactor,
+    responses(
+        (status = 200, description = "Signed in", body = SessionResponse),
+        (status = 401, description = "Incorrect code, or the challenge is invalid or expired", body = String),
+        (status = 429, description = "Too many failed attempts", body = String)
+    )
+)]
+#[post("/auth/2fa/verify")]
+pub async fn verify(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    verify: web::Json<VerifyTwoFactor>
+) -> HttpResponse {
+    let result = services::auth::verify_second_factor(
+        &data,
+        &verify.challenge_token,
+        verify.code.as_deref(),
+        verify.recovery_code.as_deref(),
+        &ClientInfo::of(&req)
+    ).await;
+
+    match result {
+        Ok(signed_in) => session_response(signed_in, "You are now logged in"),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Endpoint turning 2FA off. The password has to be entered again.
+#[utoipa::path(
+    tag = "two-factor",
+    request_body = DisableTwoFactor,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Two-factor authentication disabled", body = String),
+        (status = 401, description = "Missing or invalid access token, or incorrect password", body = String)
+    )
+)]
+#[post("/auth/2fa/disable")]
+pub async fn disable(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest,
+    disable: web::Json<DisableTwoFactor>
+) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let hashed_password: Result<String, sqlx::Error> = sqlx
+        ::query_scalar("SELECT password FROM users WHERE email = $1")
+        .bind(&claims.email)
+        .fetch_one(&data.pool).await;
+    match hashed_password {
+        Ok(hashed_password) => {
+            if !verify_password(disable.password.clone(), hashed_password) {
+                return HttpResponse::Unauthorized().json("Incorrect password");
+            }
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    }
+
+    match turn_off(&data.pool, &claims.email).await {
+        Ok(_) => HttpResponse::Ok().json("Two-factor authentication disabled"),
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+async fn totp_state(pool: &PgPool, email: &str) -> Result<TotpState, sqlx::Error> {
+    sqlx
+        ::query_as::<_, TotpState>("SELECT totp_secret, totp_enabled_at FROM users WHERE email = $1")
+        .bind(email)
+        .fetch_one(pool).await
+}
+
+/// Turns 2FA on and replaces any recovery codes. Returns the new codes in plain text.
+async fn enable(pool: &PgPool, email: &str, step: i64) -> Result<Vec<String>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx
+        ::query("UPDATE users SET totp_enabled_at = NOW(), totp_last_step = $1 WHERE email = $2")
+        .bind(step)
+        .bind(email)
+        .execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM recovery_codes WHERE email = $1").bind(email).execute(&mut *tx).await?;
+
+    let codes: Vec<String> = (0..RECOVERY_CODES).map(|_| new_recovery_code()).collect();
+    for code in &codes {
+        sqlx
+            ::query("INSERT INTO recovery_codes(email, code_hash) VALUES($1, $2)")
+            .bind(email)
+            .bind(hash_recovery_code(code))
+            .execute(&mut *tx).await?;
+    }
+
+    tx.commit().await?;
+    Ok(codes)
+}
+
+async fn turn_off(pool: &PgPool, email: &str) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx
+        ::query(
+            "UPDATE users SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_step = NULL
+            WHERE email = $1"
+        )
+        .bind(email)
+        .execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM recovery_codes WHERE email = $1").bind(email).execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM login_challenges WHERE email = $1").bind(email).execute(&mut *tx).await?;
+    tx.commit().await
+}
+
+/// A recovery code like `3f9a-0c1d-77e2-b804`.
+fn new_recovery_code() -> String {
+    let mut bytes = [0u8; 8];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+        .chunks(2)
+        .map(|pair| format!("{:02x}{:02x}", pair[0], pair[1]))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovery_codes_match_however_they_are_typed() {
+        let code = new_recovery_code();
+        assert_eq!(code.len(), 19);
+        assert_eq!(hash_recovery_code(&code), hash_recovery_code(&code.to_uppercase().replace('-', " ")));
+        assert_ne!(hash_recovery_code(&code), hash_recovery_code(&new_recovery_code()));
+    }
+}
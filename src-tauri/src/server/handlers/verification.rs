@@ -0,0 +1,128 @@
+use crate::server;
+use crate::server::services::{ self, account::{ is_verified, send_verification } };
+use actix_web::{ post, web, HttpRequest, HttpResponse };
+use chrono::{ DateTime, Duration, Utc };
+use serde::Deserialize;
+use utoipa::ToSchema;
+use super::auth::verify_request_token;
+
+/// Minimum time between two verification emails to the same address.
+const RESEND_COOLDOWN_SECONDS: i64 = 60;
+
+/// Most verification emails sent to one address per day.
+const MAX_SENDS_PER_DAY: i64 = 5;
+
+/// Represents a verification confirmation received from the client.
+#[derive(Deserialize, ToSchema, Debug)]
+struct ConfirmVerification {
+    token: String, // Token from the verification email
+}
+
+/// Endpoint for confirming an email address with the token from the verification email.
+#[utoipa::path(
+    tag = "users",
+    request_body = ConfirmVerification,
+    responses(
+        (status = 200, description = "Email verified, or changed to the new address", body = String),
+        (status = 400, description = "Verification token is invalid or expired", body = String),
+        (status = 409, description = "Another account took the new address", body = String)
+    )
+)]
+#[post("/auth/verify-email")]
+pub async fn confirm_verification(
+    data: web::Data<server::TauriAppState>,
+    confirm: web::Json<ConfirmVerification>
+) -> HttpResponse {
+    match services::account::verify_email(&data, &confirm.token).await {
+        Ok(verified) => HttpResponse::Ok().json(verified.message()),
+        Err(err) => err.into_response(),
+    }
+}
+
+/// Endpoint for sending the verification email again, throttled per address.
+#[utoipa::path(
+    tag = "users",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Verification email sent", body = String),
+        (status = 400, description = "Email is already verified", body = String),
+        (status = 401, description = "Missing or invalid access token", body = String),
+        (status = 429, description = "Sent too recently or too often today", body = String)
+    )
+)]
+#[post("/auth/verify-email/resend")]
+pub async fn resend_verification(
+    data: web::Data<server::TauriAppState>,
+    req: HttpRequest
+) -> HttpResponse {
+    let claims = match verify_request_token(&req).await {
+        Ok(claims) => claims,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    match is_verified(&data.pool, &claims.email).await {
+        Ok(true) => {
+            return HttpResponse::BadRequest().json("Email is already verified");
+        }
+        Ok(false) => {}
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    }
+
+    let sent: Result<(i64, Option<DateTime<Utc>>), sqlx::Error> = sqlx
+        ::query_as(
+            "SELECT COUNT(*), MAX(created_at) FROM email_verifications
+            WHERE email = $1 AND created_at > NOW() - INTERVAL '1 day'"
+        )
+        .bind(&claims.email)
+        .fetch_one(&data.pool).await;
+    let (sent_today, last_sent) = match sent {
+        Ok(sent) => sent,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(e.to_string());
+        }
+    };
+    if let Some(wait) = resend_wait(sent_today, last_sent, Utc::now()) {
+        return HttpResponse::TooManyRequests()
+            .insert_header(("Retry-After", wait.num_seconds().max(1).to_string()))
+            .json("Too many verification emails, please try again later");
+    }
+
+    match send_verification(&data.pool, data.mailer.clone(), &claims.email, None).await {
+        Ok(_) => HttpResponse::Ok().json("Verification email sent"),
+        Err(e) => HttpResponse::InternalServerError().json(e.to_string()),
+    }
+}
+
+/// How long to wait before another verification email may be sent, if at all.
+fn resend_wait(
+    sent_today: i64,
+    last_sent: Option<DateTime<Utc>>,
+    now: DateTime<Utc>
+) -> Option<Duration> {
+    if sent_today >= MAX_SENDS_PER_DAY {
+        // The oldest of today's emails has to fall out of the window, an hour is a fair guess
+        return Some(Duration::try_hours(1).unwrap());
+    }
+    let next_allowed = last_sent? + Duration::try_seconds(RESEND_COOLDOWN_SECONDS).unwrap();
+    (next_allowed > now).then(|| next_allowed - now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttles_resends() {
+        let now = Utc::now();
+        let seconds_ago = |seconds| Some(now - Duration::try_seconds(seconds).unwrap());
+
+        assert_eq!(resend_wait(0, None, now), None);
+        assert_eq!(resend_wait(1, seconds_ago(90), now), None);
+        assert_eq!(resend_wait(1, seconds_ago(20), now), Some(Duration::try_seconds(40).unwrap()));
+        assert!(resend_wait(MAX_SENDS_PER_DAY, seconds_ago(3600), now).is_some());
+    }
+}
@@ -0,0 +1,304 @@
+// Signing keys for access tokens, selected by the `kid` token header.
+// One key signs new tokens; older keys keep verifying until they retire.
+//
+// The keyring is read from the JSON file named by `TOKEN_KEYRING`, for example
+//   { "active": "2024-06", "keys": [
+//       { "kid": "2024-06", "alg": "HS256", "secret": "..." },
+//       { "kid": "2024-01", "alg": "HS256", "secret": "...", "retires_at": "2024-08-01T00:00:00Z" } ] }
+// Without it, `TOKENSECRET` is used as a single HS256 key with the id `my-key`.
+// EdDSA and RS256 keys are available with the `eddsa` and `rs256` features.
+
+use std::{ collections::HashMap, env, fs, sync::OnceLock };
+
+use chrono::{ DateTime, Utc };
+use jwt_compact::{ prelude::*, alg::{ Hs256, Hs256Key }, Token, UntrustedToken };
+use serde::{ de::DeserializeOwned, Deserialize, Serialize };
+
+/// Key id used for the single key built from `TOKENSECRET`.
+const LEGACY_KEY_ID: &str = "my-key";
+
+static KEYRING: OnceLock<Result<Keyring, String>> = OnceLock::new();
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeyringError {
+    #[error("Token header has no key id")] MissingKeyId,
+    #[error("Unknown signing key: {0}")] UnknownKey(String),
+    #[error("Signing key {0} has retired")] RetiredKey(String),
+    #[error("Signing key {0} can only verify tokens")] VerifyOnly(String),
+    #[error("Invalid keyring: {0}")] Config(String),
+}
+
+/// The keyring as written in the configuration file.
+#[derive(Deserialize, Debug)]
+pub struct KeyringConfig {
+    pub active: String, // Key id used to sign new tokens
+    pub keys: Vec<KeyConfig>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct KeyConfig {
+    pub kid: String,
+    #[serde(flatten)]
+    pub source: KeySource,
+    pub retires_at: Option<DateTime<Utc>>, // Tokens signed with this key are rejected afterwards
+}
+
+/// Key material per algorithm. Asymmetric keys may omit the private half to only verify.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "alg")]
+pub enum KeySource {
+    #[serde(rename = "HS256")] Hs256 {
+        secret: String,
+    },
+    #[serde(rename = "EdDSA")] EdDsa {
+        secret_key: Option<String>, // 64 bytes, hex encoded
+        public_key: Option<String>, // 32 bytes, hex encoded
+    },
+    #[serde(rename = "RS256")] Rs256 {
+        private_key_pem: Option<String>, // PKCS#8
+        public_key_pem: Option<String>, // SPKI
+    },
+}
+
+enum KeyMaterial {
+    Hs256(Hs256Key),
+    #[cfg(feature = "eddsa")] EdDsa {
+        signing: Option<ed25519_compact::SecretKey>,
+        verifying: ed25519_compact::PublicKey,
+    },
+    #[cfg(feature = "rs256")] Rs256 {
+        signing: Option<Box<jwt_compact::alg::RsaPrivateKey>>,
+        verifying: jwt_compact::alg::RsaPublicKey,
+    },
+}
+
+struct Key {
+    material: KeyMaterial,
+    retires_at: Option<DateTime<Utc>>,
+}
+
+impl Key {
+    fn is_retired(&self, now: DateTime<Utc>) -> bool {
+        self.retires_at.is_some_and(|retires_at| retires_at <= now)
+    }
+}
+
+/// The set of keys tokens can be signed and verified with.
+pub struct Keyring {
+    active: String,
+    keys: HashMap<String, Key>,
+}
+
+/// Returns the process-wide keyring, loading it from the environment on first use.
+pub fn keyring() -> Result<&'static Keyring, KeyringError> {
+    KEYRING.get_or_init(|| Keyring::from_env().map_err(|err| err.to_string()))
+        .as_ref()
+        .map_err(|err| KeyringError::Config(err.clone()))
+}
+
+impl Keyring {
+    /// Loads the keyring from `TOKEN_KEYRING`, falling back to `TOKENSECRET`.
+    pub fn from_env() -> Result<Self, KeyringError> {
+        if let Ok(path) = env::var("TOKEN_KEYRING") {
+            let contents = fs::read_to_string(&path).map_err(|err| {
+                KeyringError::Config(format!("cannot read {path}: {err}"))
+            })?;
+            let config: KeyringConfig = serde_json
+                ::from_str(&contents)
+                .map_err(|err| KeyringError::Config(format!("cannot parse {path}: {err}")))?;
+            return Self::from_config(config);
+        }
+
+        let secret = env
+            ::var("TOKENSECRET")
+            .map_err(|_| KeyringError::Config("neither TOKEN_KEYRING nor TOKENSECRET is set".into()))?;
+        Self::from_config(KeyringConfig {
+            active: LEGACY_KEY_ID.to_string(),
+            keys: vec![KeyConfig {
+                kid: LEGACY_KEY_ID.to_string(),
+                source: KeySource::Hs256 { secret },
+                retires_at: None,
+            }],
+        })
+    }
+
+    pub fn from_config(config: KeyringConfig) -> Result<Self, KeyringError> {
+        let mut keys = HashMap::new();
+        for key in config.keys {
+            let material = key_material(&key.kid, key.source)?;
+            if keys.insert(key.kid.clone(), Key { material, retires_at: key.retires_at }).is_some() {
+                return Err(KeyringError::Config(format!("duplicate key id {}", key.kid)));
+            }
+        }
+
+        match keys.get(&config.active) {
+            None => Err(KeyringError::Config(format!("active key {} is not in the keyring", config.active))),
+            Some(key) if key.is_retired(Utc::now()) =>
+                Err(KeyringError::RetiredKey(config.active)),
+            Some(_) => Ok(Keyring { active: config.active, keys }),
+        }
+    }
+
+    /// Signs claims with the active key, naming it in the `kid` header.
+    pub fn sign<T: Serialize>(&self, claims: &Claims<T>) -> Result<String, anyhow::Error> {
+        let header = Header::empty().with_key_id(&self.active);
+        let key = &self.keys[&self.active];
+        let token = match &key.material {
+            KeyMaterial::Hs256(secret) => Hs256.token(&header, claims, secret)?,
+            #[cfg(feature = "eddsa")]
+            KeyMaterial::EdDsa { signing: Some(signing), .. } =>
+                jwt_compact::alg::Ed25519.token(&header, claims, signing)?,
+            #[cfg(feature = "rs256")]
+            KeyMaterial::Rs256 { signing: Some(signing), .. } =>
+                jwt_compact::alg::Rsa::rs256().token(&header, claims, signing)?,
+            #[allow(unreachable_patterns)]
+            _ => {
+                return Err(KeyringError::VerifyOnly(self.active.clone()).into());
+            }
+        };
+        Ok(token)
+    }
+
+    /// Verifies a token's signature with the key its `kid` header names.
+    /// Unknown, missing and retired key ids are errors rather than panics.
+    pub fn verify<T: DeserializeOwned + Clone>(
+        &self,
+        token_string: &str
+    ) -> Result<Token<T>, anyhow::Error> {
+        let token = UntrustedToken::new(token_string)?;
+        let kid = token.header().key_id.as_deref().ok_or(KeyringError::MissingKeyId)?;
+        let key = self.keys.get(kid).ok_or_else(|| KeyringError::UnknownKey(kid.to_string()))?;
+        if key.is_retired(Utc::now()) {
+            return Err(KeyringError::RetiredKey(kid.to_string()).into());
+        }
+
+        // The validator also checks the header's `alg` matches the key's algorithm
+        let token = match &key.material {
+            KeyMaterial::Hs256(secret) => Hs256.validator(secret).validate(&token)?,
+            #[cfg(feature = "eddsa")]
+            KeyMaterial::EdDsa { verifying, .. } =>
+                jwt_compact::alg::Ed25519.validator(verifying).validate(&token)?,
+            #[cfg(feature = "rs256")]
+            KeyMaterial::Rs256 { verifying, .. } =>
+                jwt_compact::alg::Rsa::rs256().validator(verifying).validate(&token)?,
+        };
+        Ok(token)
+    }
+}
+
+fn key_material(kid: &str, source: KeySource) -> Result<KeyMaterial, KeyringError> {
+    let invalid = |reason: String| KeyringError::Config(format!("key {kid}: {reason}"));
+    match source {
+        KeySource::Hs256 { secret } => Ok(KeyMaterial::Hs256(Hs256Key::new(secret.as_bytes()))),
+        #[cfg(feature = "eddsa")]
+        KeySource::EdDsa { secret_key, public_key } => {
+            let signing = secret_key
+                .map(|hex| {
+                    let bytes = decode_hex(&hex).ok_or_else(|| invalid("secret_key is not hex".into()))?;
+                    ed25519_compact::SecretKey::from_slice(&bytes).map_err(|err| invalid(err.to_string()))
+                })
+                .transpose()?;
+            let verifying = match (public_key, &signing) {
+                (Some(hex), _) => {
+                    let bytes = decode_hex(&hex).ok_or_else(|| invalid("public_key is not hex".into()))?;
+                    ed25519_compact::PublicKey::from_slice(&bytes).map_err(|err| invalid(err.to_string()))?
+                }
+                (None, Some(signing)) => signing.public_key(),
+                (None, None) => {
+                    return Err(invalid("needs secret_key or public_key".into()));
+                }
+            };
+            Ok(KeyMaterial::EdDsa { signing, verifying })
+        }
+        #[cfg(feature = "rs256")]
+        KeySource::Rs256 { private_key_pem, public_key_pem } => {
+            use rsa::pkcs8::{ DecodePrivateKey, DecodePublicKey };
+
+            let signing = private_key_pem
+                .map(|pem| {
+                    jwt_compact::alg::RsaPrivateKey
+                        ::from_pkcs8_pem(&pem)
+                        .map(Box::new)
+                        .map_err(|err| invalid(err.to_string()))
+                })
+                .transpose()?;
+            let verifying = match (public_key_pem, &signing) {
+                (Some(pem), _) =>
+                    jwt_compact::alg::RsaPublicKey
+                        ::from_public_key_pem(&pem)
+                        .map_err(|err| invalid(err.to_string()))?,
+                (None, Some(signing)) => signing.to_public_key(),
+                (None, None) => {
+                    return Err(invalid("needs private_key_pem or public_key_pem".into()));
+                }
+            };
+            Ok(KeyMaterial::Rs256 { signing, verifying })
+        }
+        #[allow(unreachable_patterns)]
+        _ => Err(invalid("algorithm support is not compiled in, enable the eddsa or rs256 feature".into())),
+    }
+}
+
+#[cfg(feature = "eddsa")]
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct TestClaims {
+        email: String,
+    }
+
+    fn hs256(kid: &str, secret: &str, retires_at: Option<DateTime<Utc>>) -> KeyConfig {
+        KeyConfig { kid: kid.into(), source: KeySource::Hs256 { secret: secret.into() }, retires_at }
+    }
+
+    fn sign_with(kid: &str, secret: &str) -> String {
+        let keyring = Keyring::from_config(KeyringConfig {
+            active: kid.into(),
+            keys: vec![hs256(kid, secret, None)],
+        }).unwrap();
+        keyring.sign(&Claims::new(TestClaims { email: "user@email.com".into() })).unwrap()
+    }
+
+    #[test]
+    fn verifies_tokens_from_older_keys_until_they_retire() {
+        let yesterday = Utc::now() - Duration::try_days(1).unwrap();
+        let tomorrow = Utc::now() + Duration::try_days(1).unwrap();
+        let keyring = Keyring::from_config(KeyringConfig {
+            active: "new".into(),
+            keys: vec![
+                hs256("new", "new-secret", None),
+                hs256("old", "old-secret", Some(tomorrow)),
+                hs256("retired", "retired-secret", Some(yesterday))
+            ],
+        }).unwrap();
+
+        let fresh = keyring.sign(&Claims::new(TestClaims { email: "user@email.com".into() })).unwrap();
+        assert!(keyring.verify::<TestClaims>(&fresh).is_ok());
+        assert!(keyring.verify::<TestClaims>(&sign_with("old", "old-secret")).is_ok());
+        assert!(keyring.verify::<TestClaims>(&sign_with("retired", "retired-secret")).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_key_ids_without_panicking() {
+        let keyring = Keyring::from_config(KeyringConfig {
+            active: "new".into(),
+            keys: vec![hs256("new", "new-secret", None)],
+        }).unwrap();
+
+        let err = keyring.verify::<TestClaims>(&sign_with("other", "new-secret")).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(KeyringError::UnknownKey(kid)) if kid == "other"));
+    }
+}
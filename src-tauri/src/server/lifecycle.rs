@@ -0,0 +1,135 @@
+// Status of the embedded server as shown in the window, and the switch that stops it.
+
+use std::sync::{ Condvar, Mutex };
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::server::notifier::Notifier;
+
+/// Name of the event the desktop app emits to its window whenever the server's status changes.
+pub const SERVER_STATUS_EVENT: &str = "server-status";
+
+/// First wait between database connection attempts, doubled after every failure.
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Longest wait between database connection attempts.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerState {
+    Starting, // Loading configuration and connecting
+    Ready, // Serving requests
+    Degraded, // Running but the database is unreachable, retrying
+    Failed, // Stopped by an error it cannot recover from
+    Stopped, // Shut down when asked to
+}
+
+/// Payload of `server-status`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ServerStatus {
+    pub state: ServerState,
+    pub port: Option<u16>, // Port the HTTP API listens on, once it does
+    pub message: Option<String>, // What went wrong, for degraded and failed
+}
+
+/// Tracks the server's status and lets the app ask it to shut down.
+pub struct Lifecycle {
+    status: Mutex<ServerStatus>,
+    shutdown: watch::Sender<bool>,
+    stopped: (Mutex<bool>, Condvar),
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Lifecycle {
+            status: Mutex::new(ServerStatus { state: ServerState::Starting, port: None, message: None }),
+            shutdown: watch::channel(false).0,
+            stopped: (Mutex::new(false), Condvar::new()),
+        }
+    }
+}
+
+impl Lifecycle {
+    /// The latest status, for windows that missed the events.
+    pub fn status(&self) -> ServerStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Records a new status and passes it on.
+    pub(crate) fn report(&self, notifier: &dyn Notifier, state: ServerState, message: Option<String>) {
+        let status = {
+            let mut status = self.status.lock().unwrap();
+            status.state = state;
+            status.message = message;
+            status.clone()
+        };
+        notifier.status_changed(&status);
+    }
+
+    /// Records the port the HTTP API ended up on.
+    pub(crate) fn listening_on(&self, port: u16) {
+        self.status.lock().unwrap().port = Some(port);
+    }
+
+    /// Asks the server to shut down and waits up to `timeout` for it to finish.
+    /// Returns whether it did.
+    pub fn stop(&self, timeout: Duration) -> bool {
+        self.shutdown.send_replace(true);
+        let (stopped, finished) = &self.stopped;
+        let stopped = finished
+            .wait_timeout_while(stopped.lock().unwrap(), timeout, |stopped| !*stopped)
+            .unwrap()
+            .0;
+        *stopped
+    }
+
+    /// Resolves once `stop` has been called.
+    pub(crate) async fn shutdown_requested(&self) {
+        let mut shutdown = self.shutdown.subscribe();
+        // The sender lives as long as self, so waiting cannot fail
+        let _ = shutdown.wait_for(|requested| *requested).await;
+    }
+
+    /// Blocks until the server has finished, after `stop` or an error.
+    pub fn wait(&self) {
+        let (stopped, finished) = &self.stopped;
+        let _stopped = finished.wait_while(stopped.lock().unwrap(), |stopped| !*stopped).unwrap();
+    }
+
+    /// Marks the server as finished, releasing `stop` and `wait`.
+    pub(crate) fn finished(&self) {
+        let (stopped, finished) = &self.stopped;
+        *stopped.lock().unwrap() = true;
+        finished.notify_all();
+    }
+}
+
+/// How long to wait before the given connection attempt, counting from zero.
+pub(crate) fn retry_delay(attempt: u32) -> Duration {
+    FIRST_RETRY_DELAY.saturating_mul(2u32.saturating_pow(attempt)).min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially_up_to_a_cap() {
+        assert_eq!(retry_delay(0), Duration::from_secs(1));
+        assert_eq!(retry_delay(1), Duration::from_secs(2));
+        assert_eq!(retry_delay(5), Duration::from_secs(32));
+        assert_eq!(retry_delay(6), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(40), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn stop_waits_for_the_server_to_finish() {
+        let lifecycle = Lifecycle::default();
+        assert!(!lifecycle.stop(Duration::from_millis(10)));
+
+        lifecycle.finished();
+        assert!(lifecycle.stop(Duration::from_millis(10)));
+    }
+}
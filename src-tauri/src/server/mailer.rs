@@ -0,0 +1,176 @@
+// Outgoing email, behind a trait so deployments can pick how mail is delivered.
+// `MAILER` selects the implementation:
+//   smtp  sends through `SMTP_HOST`/`SMTP_PORT`, e.g. a local sink such as MailHog on port 1025
+//   file  writes each message to `MAIL_DIR` as an .eml file
+//   log   prints each message (the default)
+
+use std::{ env, fs, path::PathBuf, sync::Arc };
+
+use chrono::Utc;
+use lettre::{
+    message::{ header::ContentType, Mailbox },
+    transport::smtp::authentication::Credentials,
+    Message,
+    SmtpTransport,
+    Transport,
+};
+
+/// Sender address used when `MAIL_FROM` is not set.
+const DEFAULT_FROM: &str = "Kaizen <no-reply@kaizen.local>";
+
+/// A plain text message to a single recipient.
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MailError {
+    #[error("Invalid address: {0}")] Address(String),
+    #[error("Failed to build message: {0}")] Build(#[from] lettre::error::Error),
+    #[error("SMTP error: {0}")] Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("Failed to write message: {0}")] Io(#[from] std::io::Error),
+    #[error("Invalid mailer configuration: {0}")] Config(String),
+}
+
+/// Delivers emails. Sending blocks, so async callers should go through `send_email`.
+pub trait Mailer: Send + Sync {
+    fn send(&self, email: &Email) -> Result<(), MailError>;
+}
+
+/// Sends an email on the blocking thread pool.
+pub async fn send_email(mailer: Arc<dyn Mailer>, email: Email) -> Result<(), MailError> {
+    match tokio::task::spawn_blocking(move || mailer.send(&email)).await {
+        Ok(result) => result,
+        Err(err) => Err(MailError::Config(format!("mail task failed: {err}"))),
+    }
+}
+
+/// Builds the mailer selected by the `MAILER` environment variable.
+pub fn from_env() -> Result<Arc<dyn Mailer>, MailError> {
+    let from = env::var("MAIL_FROM").unwrap_or_else(|_| DEFAULT_FROM.to_string());
+    match env::var("MAILER").as_deref() {
+        Ok("smtp") => Ok(Arc::new(SmtpMailer::from_env(&from)?)),
+        Ok("file") => {
+            let dir = env::var("MAIL_DIR").unwrap_or_else(|_| "mail".to_string());
+            Ok(Arc::new(FileMailer::new(dir, &from)?))
+        }
+        Ok("log") | Err(_) => Ok(Arc::new(LogMailer)),
+        Ok(other) => Err(MailError::Config(format!("unknown MAILER {other}"))),
+    }
+}
+
+fn build_message(from: &Mailbox, email: &Email) -> Result<Message, MailError> {
+    let to: Mailbox = email.to.parse().map_err(|_| MailError::Address(email.to.clone()))?;
+    Ok(
+        Message::builder()
+            .from(from.clone())
+            .to(to)
+            .subject(&email.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body.clone())?
+    )
+}
+
+fn parse_from(from: &str) -> Result<Mailbox, MailError> {
+    from.parse().map_err(|_| MailError::Address(from.to_string()))
+}
+
+/// Sends mail through an SMTP server.
+pub struct SmtpMailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// Reads `SMTP_HOST`, `SMTP_PORT`, `SMTP_USERNAME`, `SMTP_PASSWORD` and `SMTP_TLS`.
+    /// TLS is on unless `SMTP_TLS=false`, which local sinks need.
+    pub fn from_env(from: &str) -> Result<Self, MailError> {
+        let host = env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string());
+        let tls = env::var("SMTP_TLS").map_or(true, |value| value != "false");
+
+        let mut builder = if tls {
+            SmtpTransport::starttls_relay(&host)?
+        } else {
+            SmtpTransport::builder_dangerous(&host)
+        };
+        if let Ok(port) = env::var("SMTP_PORT") {
+            let port = port.parse().map_err(|_| MailError::Config(format!("invalid SMTP_PORT {port}")))?;
+            builder = builder.port(port);
+        }
+        if let (Ok(username), Ok(password)) = (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD")) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(SmtpMailer { transport: builder.build(), from: parse_from(from)? })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, email: &Email) -> Result<(), MailError> {
+        self.transport.send(&build_message(&self.from, email)?)?;
+        Ok(())
+    }
+}
+
+/// Writes every message to a directory, one .eml file each.
+pub struct FileMailer {
+    dir: PathBuf,
+    from: Mailbox,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>, from: &str) -> Result<Self, MailError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(FileMailer { dir, from: parse_from(from)? })
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, email: &Email) -> Result<(), MailError> {
+        let message = build_message(&self.from, email)?;
+        let name = format!("{}-{}.eml", Utc::now().format("%Y%m%dT%H%M%S%.6f"), email.to);
+        fs::write(self.dir.join(name), message.formatted())?;
+        Ok(())
+    }
+}
+
+/// Prints every message instead of sending it.
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    fn send(&self, email: &Email) -> Result<(), MailError> {
+        tracing::info!(to = %email.to, subject = %email.subject, "mail not sent, logged instead:\n{}", email.body);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_mailer_writes_one_file_per_message() {
+        let dir = env::temp_dir().join(format!("kaizen-mail-{}", std::process::id()));
+        let mailer = FileMailer::new(&dir, DEFAULT_FROM).unwrap();
+
+        mailer
+            .send(
+                &(Email {
+                    to: "user@email.com".into(),
+                    subject: "Reset your password".into(),
+                    body: "token".into(),
+                })
+            )
+            .unwrap();
+
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        let contents = fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+        assert!(contents.contains("Subject: Reset your password"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
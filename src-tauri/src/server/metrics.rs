@@ -0,0 +1,117 @@
+// Counters served at `/metrics` in the Prometheus text format.
+
+use prometheus::{
+    Encoder,
+    HistogramOpts,
+    HistogramVec,
+    IntCounterVec,
+    IntGauge,
+    Opts,
+    Registry,
+    TextEncoder,
+};
+use sqlx::PgPool;
+
+/// Route label for requests that matched no endpoint, so probing random paths
+/// cannot create a time series per path.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// The server's metrics, kept in `TauriAppState`.
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    auth_failures: IntCounterVec,
+    pool_connections: IntGauge,
+    pool_idle: IntGauge,
+    pool_max: IntGauge,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let requests = IntCounterVec::new(
+            Opts::new("kaizen_http_requests_total", "HTTP requests by route and status"),
+            &["method", "route", "status"]
+        ).unwrap();
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new("kaizen_http_request_duration_seconds", "HTTP request latency by route"),
+            &["method", "route"]
+        ).unwrap();
+        let auth_failures = IntCounterVec::new(
+            Opts::new(
+                "kaizen_auth_failures_total",
+                "Requests refused for missing, bad or insufficient credentials, or throttling"
+            ),
+            &["route", "reason"]
+        ).unwrap();
+        let pool_connections = IntGauge::new("kaizen_db_pool_connections", "Open database connections").unwrap();
+        let pool_idle = IntGauge::new("kaizen_db_pool_idle_connections", "Idle database connections").unwrap();
+        let pool_max = IntGauge::new("kaizen_db_pool_max_connections", "Database connection limit").unwrap();
+
+        let registry = Registry::new();
+        registry.register(Box::new(requests.clone())).unwrap();
+        registry.register(Box::new(request_duration.clone())).unwrap();
+        registry.register(Box::new(auth_failures.clone())).unwrap();
+        registry.register(Box::new(pool_connections.clone())).unwrap();
+        registry.register(Box::new(pool_idle.clone())).unwrap();
+        registry.register(Box::new(pool_max.clone())).unwrap();
+
+        Metrics { registry, requests, request_duration, auth_failures, pool_connections, pool_idle, pool_max }
+    }
+}
+
+impl Metrics {
+    /// Counts a finished request. `route` is the matched route pattern, not the path.
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        self.requests.with_label_values(&[method, route, &status.to_string()]).inc();
+        self.request_duration.with_label_values(&[method, route]).observe(seconds);
+
+        let reason = match status {
+            401 => "unauthorized",
+            403 => "forbidden",
+            429 => "throttled",
+            _ => {
+                return;
+            }
+        };
+        self.auth_failures.with_label_values(&[route, reason]).inc();
+    }
+
+    /// Renders every metric, sampling the pool's usage first.
+    pub fn render(&self, pool: &PgPool) -> String {
+        self.pool_connections.set(pool.size().into());
+        self.pool_idle.set(pool.num_idle() as i64);
+        self.pool_max.set(pool.options().get_max_connections().into());
+
+        let mut buffer = Vec::new();
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!(error = %err, "metrics encoding error");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_requests_and_auth_failures() {
+        let metrics = Metrics::default();
+        metrics.observe_request("GET", "/tasks/{user_email}", 200, 0.02);
+        metrics.observe_request("POST", "/users/login", 401, 0.3);
+        metrics.observe_request("POST", "/users/login", 429, 0.001);
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&metrics.registry.gather(), &mut buffer).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(
+            text.contains(
+                r#"kaizen_http_requests_total{method="GET",route="/tasks/{user_email}",status="200"} 1"#
+            )
+        );
+        assert!(text.contains(r#"kaizen_auth_failures_total{reason="unauthorized",route="/users/login"} 1"#));
+        assert!(text.contains(r#"kaizen_auth_failures_total{reason="throttled",route="/users/login"} 1"#));
+        assert!(text.contains(r#"kaizen_http_request_duration_seconds_count{method="POST",route="/users/login"} 2"#));
+    }
+}
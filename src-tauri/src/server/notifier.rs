@@ -0,0 +1,63 @@
+// What the server tells whoever embeds it.
+// The desktop app mirrors sign-ins in its window, keeps the session between launches
+// and shows reminders; a standalone server has nobody to tell, so every hook defaults to nothing.
+
+use std::sync::Arc;
+
+use crate::credentials::StoredCredentials;
+use crate::server::lifecycle::ServerStatus;
+use crate::server::reminders::ReminderEvent;
+use crate::server::services::auth::CurrentUser;
+use crate::server::TauriAppState;
+
+/// Hooks the server calls as sessions, its status and reminders change.
+pub trait Notifier: Send + Sync + 'static {
+    /// A user signed in and got a new session.
+    fn signed_in(&self, _user: CurrentUser, _session_id: i32, _token: String, _refresh_token: String) {}
+
+    /// The app's session was refreshed, see `services::auth::refresh_session`.
+    fn refreshed(&self, _session_id: i32, _token: String, _refresh_token: String) {}
+
+    /// A session was swapped for a new one, e.g. after a password change.
+    fn session_replaced(
+        &self,
+        _old_session_id: i32,
+        _session_id: i32,
+        _token: String,
+        _refresh_token: String
+    ) {}
+
+    /// A session was ended.
+    fn session_ended(&self, _session_id: i32) {}
+
+    /// Every session of a user was ended.
+    fn user_signed_out(&self, _email: &str) {}
+
+    /// Credentials kept from an earlier run, to sign back in with.
+    fn saved_credentials(&self) -> Option<StoredCredentials> {
+        None
+    }
+
+    /// The saved credentials are no longer accepted.
+    fn forget_credentials(&self) {}
+
+    /// The server's status changed.
+    fn status_changed(&self, _status: &ServerStatus) {}
+
+    /// Whether reminders should be fired here. Firing claims them in the database,
+    /// so a server that cannot show them must leave them to one that can.
+    fn delivers_reminders(&self) -> bool {
+        false
+    }
+
+    /// A reminder came due.
+    fn reminder_due(&self, _reminder: ReminderEvent) {}
+
+    /// The server connected to the database and its state can be shared.
+    fn serving(&self, _state: Arc<TauriAppState>) {}
+}
+
+/// Notifier for a server running on its own, without a window.
+pub struct Headless;
+
+impl Notifier for Headless {}
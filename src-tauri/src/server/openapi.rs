@@ -0,0 +1,142 @@
+// OpenAPI document of the HTTP API, generated from the handlers and the types they read and answer with.
+// Served at /openapi.json; builds with the `docs-ui` feature add a Swagger UI at /docs.
+
+use actix_web::web;
+use utoipa::openapi::security::{ HttpAuthScheme, HttpBuilder, SecurityScheme };
+use utoipa::{ Modify, OpenApi };
+
+use super::handlers;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Kaizen API",
+        description = "Tasks, habits, reviews and the accounts they belong to. \
+            Errors are a JSON string with the reason, except invalid payloads, which list every broken rule."
+    ),
+    paths(
+        handlers::health::healthz,
+        handlers::health::readyz,
+        handlers::health::metrics,
+        handlers::users::register,
+        handlers::users::login,
+        handlers::account::get_account,
+        handlers::account::update_profile,
+        handlers::account::change_password,
+        handlers::account::change_email,
+        handlers::account::delete_account,
+        handlers::sessions::refresh,
+        handlers::sessions::get_sessions,
+        handlers::sessions::revoke_session,
+        handlers::sessions::logout,
+        handlers::sessions::logout_all,
+        handlers::access_tokens::create_access_token,
+        handlers::access_tokens::get_access_tokens,
+        handlers::access_tokens::revoke_access_token,
+        handlers::two_factor::enroll,
+        handlers::two_factor::activate,
+        handlers::two_factor::verify,
+        handlers::two_factor::disable,
+        handlers::password_reset::request_reset,
+        handlers::password_reset::confirm_reset,
+        handlers::verification::confirm_verification,
+        handlers::verification::resend_verification,
+        handlers::schemas::get_schema,
+        handlers::tasks::create_task,
+        handlers::tasks::update_task,
+        handlers::tasks::get_tasks,
+        handlers::tasks::delete_task,
+        handlers::tasks::check_task,
+        handlers::tasks::get_task_order,
+        handlers::dependencies::create_dependency,
+        handlers::dependencies::delete_dependency,
+        handlers::habits::create_habit,
+        handlers::habits::get_habits,
+        handlers::habits::check_habit,
+        handlers::reviews::create_review,
+        handlers::reviews::get_review_summary,
+        handlers::reviews::get_reviews,
+        handlers::schedule::plan_schedule,
+        handlers::reminders::create_reminder,
+        handlers::reminders::get_reminders,
+        handlers::reminders::delete_reminder
+    ),
+    modifiers(&BearerAuth)
+)]
+pub struct ApiDoc;
+
+/// Declares the `bearer` scheme the endpoints' `security` refers to.
+/// The scopes listed on an endpoint are the ones a personal access token needs for it;
+/// endpoints without scopes only take a session's access token.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT or personal access token")
+                    .build()
+            )
+        );
+    }
+}
+
+/// The document served at /openapi.json.
+pub fn document() -> utoipa::openapi::OpenApi {
+    ApiDoc::openapi()
+}
+
+/// Serves the document, and the docs UI when it is built in.
+pub fn routes(doc: utoipa::openapi::OpenApi) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        #[cfg(feature = "docs-ui")]
+        cfg.service(utoipa_swagger_ui::SwaggerUi::new("/docs/{_:.*}").url("/openapi.json", doc));
+        #[cfg(not(feature = "docs-ui"))]
+        cfg.route(
+            "/openapi.json",
+            web::get().to(move || {
+                let doc = doc.clone();
+                async move { actix_web::HttpResponse::Ok().json(doc) }
+            })
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{ call_and_read_body_json, init_service, TestRequest };
+    use actix_web::App;
+    use super::*;
+
+    #[test]
+    fn documents_every_route_with_its_bodies() {
+        let json = serde_json::to_value(document()).unwrap();
+        let paths = &json["paths"];
+        assert!(paths["/tasks/{user_email}"]["get"].is_object());
+        assert!(paths["/tasks/update/{task_id}"]["post"]["requestBody"].is_object());
+        assert_eq!(
+            paths["/auth/login"]["post"]["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/LoginResponse"
+        );
+
+        let schemas = &json["components"]["schemas"];
+        for name in ["AddTask", "Task", "CheckedTask", "SessionResponse", "InvalidResponse", "Schedule"] {
+            assert!(schemas[name].is_object(), "{name} is missing");
+        }
+        assert!(json["components"]["securitySchemes"]["bearer"].is_object());
+    }
+
+    #[actix_web::test]
+    async fn serves_the_document() {
+        let app = init_service(App::new().configure(routes(document()))).await;
+        let json: serde_json::Value = call_and_read_body_json(
+            &app,
+            TestRequest::get().uri("/openapi.json").to_request()
+        ).await;
+        assert_eq!(json["info"]["title"], "Kaizen API");
+    }
+}
@@ -0,0 +1,133 @@
+// Background scheduler that fires task reminders.
+// A reminder is claimed in the database before it is delivered, so it fires exactly once
+// even across restarts, and anything that came due while the app was closed is caught up
+// on the first tick after launch.
+
+use std::{ sync::Arc, time::Duration as StdDuration };
+
+use chrono::{ DateTime, Duration, NaiveDate, NaiveTime, Utc };
+use serde::Serialize;
+use sqlx::{ prelude::FromRow, PgPool };
+
+use crate::server::deadline;
+use crate::server::notifier::Notifier;
+
+/// How often pending reminders are checked.
+const TICK: StdDuration = StdDuration::from_secs(30);
+
+/// Reminders that fire this long after their time are reported as missed.
+const GRACE_MINUTES: i64 = 2;
+
+/// Name of the event emitted to the window when a reminder fires.
+pub const REMINDER_EVENT: &str = "reminder";
+
+/// A reminder that has not fired yet, joined with its task's deadline.
+#[derive(FromRow, Debug)]
+struct PendingReminder {
+    reminder_id: i32,
+    task_id: i32,
+    title: String,
+    remind_at: Option<DateTime<Utc>>,
+    minutes_before: Option<i32>,
+    due_date: Option<NaiveDate>,
+    due_time: Option<NaiveTime>,
+    timezone: String,
+}
+
+/// Payload delivered to the window when a reminder fires.
+#[derive(Serialize, Debug, Clone)]
+pub struct ReminderEvent {
+    pub reminder_id: i32,
+    pub task_id: i32,
+    pub title: String,
+    pub fire_at: DateTime<Utc>,
+    pub missed: bool, // Came due while the app was not running
+}
+
+/// Returns when a reminder should fire: its absolute time, or an offset before the task's deadline.
+/// Relative reminders on tasks without a due date never fire.
+pub fn fire_at(
+    remind_at: Option<DateTime<Utc>>,
+    minutes_before: Option<i32>,
+    due_date: Option<NaiveDate>,
+    due_time: Option<NaiveTime>,
+    timezone: &str
+) -> Option<DateTime<Utc>> {
+    if remind_at.is_some() {
+        return remind_at;
+    }
+    let tz = deadline::parse_timezone(timezone)?;
+    let due = deadline::due_at(due_date?, due_time, tz);
+    Some(due - Duration::try_minutes(minutes_before? as i64)?)
+}
+
+/// Runs forever, firing due reminders every `TICK`. The first tick happens immediately.
+pub async fn run(pool: PgPool, notifier: Arc<dyn Notifier>) {
+    let mut interval = tokio::time::interval(TICK);
+    loop {
+        interval.tick().await;
+        if let Err(err) = fire_due(&pool, &*notifier).await {
+            tracing::error!(error = %err, "reminder scheduler error");
+        }
+    }
+}
+
+async fn fire_due(pool: &PgPool, notifier: &dyn Notifier) -> Result<(), sqlx::Error> {
+    let pending = sqlx
+        ::query_as::<_, PendingReminder>(
+            "SELECT r.reminder_id, r.task_id, t.title, r.remind_at, r.minutes_before,
+                t.due_date, t.due_time, u.timezone
+            FROM reminders r
+            JOIN tasks t ON t.task_id = r.task_id
+            JOIN users u ON u.email = t.user_email
+            WHERE r.fired_at IS NULL AND t.checked = FALSE"
+        )
+        .fetch_all(pool).await?;
+
+    let now = Utc::now();
+    for reminder in pending {
+        let fire_at = fire_at(
+            reminder.remind_at,
+            reminder.minutes_before,
+            reminder.due_date,
+            reminder.due_time,
+            &reminder.timezone
+        );
+        let Some(fire_at) = fire_at.filter(|fire_at| *fire_at <= now) else {
+            continue;
+        };
+
+        // Claim the reminder first so a second scheduler or a crash cannot deliver it twice
+        let claimed = sqlx
+            ::query("UPDATE reminders SET fired_at = NOW() WHERE reminder_id = $1 AND fired_at IS NULL")
+            .bind(reminder.reminder_id)
+            .execute(pool).await?;
+        if claimed.rows_affected() == 0 {
+            continue;
+        }
+
+        notifier.reminder_due(ReminderEvent {
+            reminder_id: reminder.reminder_id,
+            task_id: reminder.task_id,
+            title: reminder.title,
+            missed: now - fire_at > Duration::try_minutes(GRACE_MINUTES).unwrap(),
+            fire_at,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_reminder_counts_back_from_deadline() {
+        let due_date = NaiveDate::from_ymd_opt(2024, 3, 20);
+        let due_time = NaiveTime::from_hms_opt(9, 0, 0);
+        let expected = DateTime::parse_from_rfc3339("2024-03-20T12:30:00Z").unwrap();
+
+        assert_eq!(fire_at(None, Some(30), due_date, due_time, "America/New_York"), Some(expected.into()));
+        assert_eq!(fire_at(None, Some(30), None, None, "America/New_York"), None);
+    }
+}
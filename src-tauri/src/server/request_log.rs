@@ -0,0 +1,116 @@
+// Per-request tracing: every request runs in a span carrying its request id,
+// and ends with one log line giving its status, latency and the user who made it.
+// The same numbers feed the request metrics.
+
+use std::{ future::Future, time::Instant };
+
+use actix_web::{
+    dev::{ Service, ServiceRequest, ServiceResponse },
+    http::header::{ HeaderName, HeaderValue },
+    web,
+    Error,
+};
+use argon2::password_hash::rand_core::{ OsRng, RngCore };
+use tracing::{ debug, field, info, info_span, warn, Instrument, Span };
+
+use crate::server::metrics::UNMATCHED_ROUTE;
+use crate::server::TauriAppState;
+
+/// Header carrying the request id, taken from the client when it sends a usable one.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest client-supplied request id that is kept.
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+/// Paths polled by monitoring, logged at debug level to keep them out of the way.
+const PROBE_PATHS: [&str; 3] = ["/healthz", "/readyz", "/metrics"];
+
+/// Middleware tracing and counting each request, for `App::wrap_fn`.
+/// Echoes the request id in the response.
+pub fn trace_request<S, B>(
+    req: ServiceRequest,
+    srv: &S
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>> + 'static
+    where S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>, S::Future: 'static
+{
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(String::from)
+        .unwrap_or_else(new_request_id);
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        method = %req.method(),
+        path = %req.path(),
+        user = field::Empty
+    );
+    let state = req.app_data::<web::Data<TauriAppState>>().cloned();
+    let method = req.method().to_string();
+    let probe = PROBE_PATHS.contains(&req.path());
+    let started = Instant::now();
+    let response = span.in_scope(|| srv.call(req));
+
+    async move {
+        let result = response.await;
+        let elapsed = started.elapsed();
+        let latency_ms = elapsed.as_millis() as u64;
+        let (status, route) = match &result {
+            Ok(response) => (response.status(), response.request().match_pattern()),
+            Err(err) => (err.as_response_error().status_code(), None),
+        };
+        if status.is_server_error() {
+            warn!(status = status.as_u16(), latency_ms, "request failed");
+        } else if probe {
+            debug!(status = status.as_u16(), latency_ms, "request finished");
+        } else {
+            info!(status = status.as_u16(), latency_ms, "request finished");
+        }
+        if let Some(state) = state {
+            let route = route.as_deref().unwrap_or(UNMATCHED_ROUTE);
+            state.metrics.observe_request(&method, route, status.as_u16(), elapsed.as_secs_f64());
+        }
+
+        let mut response = result?;
+        if let Ok(value) = HeaderValue::from_str(&request_id) {
+            response.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+        }
+        Ok(response)
+    }.instrument(span)
+}
+
+/// Adds the authenticated caller to the current request's span.
+pub fn record_user(email: &str) {
+    Span::current().record("user", email);
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty() &&
+        id.len() <= MAX_REQUEST_ID_LEN &&
+        id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn new_request_id() -> String {
+    let mut bytes = [0u8; 8];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_safe_client_request_ids() {
+        assert!(is_valid_request_id("3f2a-41c9_b"));
+        assert!(is_valid_request_id(&new_request_id()));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id("line\nbreak"));
+        assert!(!is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_LEN + 1)));
+    }
+}
@@ -0,0 +1,75 @@
+// Response bodies shared by several endpoints, typed so the OpenAPI document describes them
+// and clients can read them back. Bodies used by a single endpoint live next to its handler.
+
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+
+use crate::server::validation::FieldError;
+
+/// A new session, answered by registration, login and the second login step.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct SessionResponse {
+    pub message: String,
+    pub token: String, // Short-lived access token
+    pub refresh_token: String, // Long-lived, single-use refresh token
+    pub user_email: String,
+    pub user_username: String,
+}
+
+/// A new account and its first session.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct RegisteredResponse {
+    #[serde(flatten)]
+    pub session: SessionResponse,
+    pub email_verified: bool, // Always false, a verification email is on its way
+}
+
+/// The first login step of an account with two-factor authentication.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct TwoFactorChallenge {
+    pub message: String,
+    pub two_factor_required: bool, // Always true
+    pub challenge_token: String, // Sent with the code to /auth/2fa/verify
+}
+
+/// Either a session, or the challenge to finish logging in with.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    TwoFactorRequired(TwoFactorChallenge),
+    SignedIn(SessionResponse),
+}
+
+/// A new token pair for the current session, after a refresh or a password change.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct TokenPairResponse {
+    pub message: String,
+    pub token: String,
+    pub refresh_token: String,
+}
+
+/// Answer of the liveness probe.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct Health {
+    #[schema(example = "ok")]
+    pub status: String,
+}
+
+/// Answer of the readiness probe.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct Readiness {
+    #[schema(example = "ready")]
+    pub status: String, // "ready" or "not_ready"
+    #[schema(example = "ok")]
+    pub database: String, // "ok", or why the database could not be checked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_migrations: Option<Vec<i64>>, // Left out when the database could not be checked
+}
+
+/// A payload that broke its schema's rules, sent back as a 422.
+#[derive(Serialize, ToSchema, Debug, PartialEq)]
+pub struct InvalidResponse {
+    #[schema(example = "Some fields are invalid")]
+    pub message: String,
+    pub errors: Vec<FieldError>,
+}
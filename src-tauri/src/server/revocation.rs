@@ -0,0 +1,118 @@
+// In-memory revocation list for access tokens.
+// Checking a token only reads this cache; the cache is loaded from the database on startup,
+// updated directly on logout, and reloaded periodically so other server instances' changes show up.
+
+use std::collections::{ HashMap, HashSet };
+use std::sync::{ Arc, RwLock };
+use std::time::Duration as StdDuration;
+
+use chrono::{ DateTime, Utc };
+use sqlx::PgPool;
+
+use crate::server::tokens::{ CustomClaims, ACCESS_TOKEN_HOURS };
+
+/// How often the cache is reloaded from the database.
+const RELOAD_INTERVAL: StdDuration = StdDuration::from_secs(30);
+
+#[derive(Default)]
+struct RevokedState {
+    tokens: HashMap<String, DateTime<Utc>>, // Revoked token ids and when they would expire anyway
+    sessions: HashSet<i32>, // Sessions revoked recently enough to still have live access tokens
+    versions: HashMap<String, i32>, // Current token version per user, when above zero
+}
+
+/// Shared revocation cache.
+#[derive(Default)]
+pub struct Revocations {
+    state: RwLock<RevokedState>,
+}
+
+impl Revocations {
+    /// Whether an otherwise valid token has been revoked.
+    pub fn is_revoked(&self, claims: &CustomClaims) -> bool {
+        let state = self.state.read().unwrap();
+        state.tokens.contains_key(&claims.jti) ||
+            state.sessions.contains(&claims.session_id) ||
+            state.versions.get(&claims.email).is_some_and(|version| claims.token_version < *version)
+    }
+
+    /// Records a revoked token until it would have expired.
+    pub fn revoke_token(&self, jti: &str, expires_at: DateTime<Utc>) {
+        self.state.write().unwrap().tokens.insert(jti.to_string(), expires_at);
+    }
+
+    /// Records a revoked session, invalidating every access token issued for it.
+    pub fn revoke_session(&self, session_id: i32) {
+        self.state.write().unwrap().sessions.insert(session_id);
+    }
+
+    /// Records a user's new token version, invalidating every older token.
+    pub fn set_version(&self, email: &str, version: i32) {
+        self.state.write().unwrap().versions.insert(email.to_string(), version);
+    }
+
+    /// Replaces the cache with the database's view and prunes expired entries.
+    pub async fn reload(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM revoked_tokens WHERE expires_at <= NOW()").execute(pool).await?;
+
+        let tokens: Vec<(String, DateTime<Utc>)> = sqlx
+            ::query_as("SELECT jti, expires_at FROM revoked_tokens")
+            .fetch_all(pool).await?;
+        let sessions: Vec<i32> = sqlx
+            ::query_scalar(
+                "SELECT session_id FROM sessions WHERE revoked_at > NOW() - make_interval(hours => $1)"
+            )
+            .bind(ACCESS_TOKEN_HOURS as i32)
+            .fetch_all(pool).await?;
+        let versions: Vec<(String, i32)> = sqlx
+            ::query_as("SELECT email, token_version FROM users WHERE token_version > 0")
+            .fetch_all(pool).await?;
+
+        *self.state.write().unwrap() = RevokedState {
+            tokens: tokens.into_iter().collect(),
+            sessions: sessions.into_iter().collect(),
+            versions: versions.into_iter().collect(),
+        };
+        Ok(())
+    }
+}
+
+/// Reloads the cache every `RELOAD_INTERVAL`, forever.
+pub async fn run(pool: PgPool, revocations: Arc<Revocations>) {
+    let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(err) = revocations.reload(&pool).await {
+            tracing::error!(error = %err, "revocation reload error");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(jti: &str, session_id: i32, token_version: i32) -> CustomClaims {
+        CustomClaims {
+            email: "user@email.com".to_string(),
+            session_id,
+            jti: jti.to_string(),
+            token_version,
+        }
+    }
+
+    #[test]
+    fn revokes_by_token_session_and_version() {
+        let revocations = Revocations::default();
+        assert!(!revocations.is_revoked(&claims("a", 1, 0)));
+
+        revocations.revoke_token("a", Utc::now());
+        revocations.revoke_session(2);
+        revocations.set_version("user@email.com", 1);
+
+        assert!(revocations.is_revoked(&claims("a", 3, 1)));
+        assert!(revocations.is_revoked(&claims("b", 2, 1)));
+        assert!(revocations.is_revoked(&claims("b", 3, 0)));
+        assert!(!revocations.is_revoked(&claims("b", 3, 1)));
+    }
+}
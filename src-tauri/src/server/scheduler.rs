@@ -0,0 +1,385 @@
+// Day planner that turns a list of tasks into a time-blocked schedule.
+// This module is pure: it knows nothing about HTTP or the database.
+
+use chrono::{ Days, Duration, NaiveDate, NaiveTime };
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+use std::collections::{ HashMap, HashSet };
+use std::fmt;
+
+use crate::server::dependencies;
+
+/// A task as seen by the planner.
+#[derive(Debug, Clone)]
+pub struct PlanTask {
+    pub task_id: i32,
+    pub title: String,
+    pub duration: Option<i32>, // Minutes
+    pub priority: Option<i32>, // Lower is more important
+    pub deadline: Option<NaiveDate>,
+    pub blocked_by: Vec<i32>, // Unfinished tasks that must be done first
+}
+
+/// The daily window in which tasks may be placed.
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone, Copy)]
+pub struct WorkingHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// Time that is already taken, e.g. a meeting or lunch.
+/// A block without a date repeats every day.
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+pub struct FixedBlock {
+    pub date: Option<NaiveDate>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    #[serde(default)]
+    pub label: String,
+}
+
+/// Everything the planner needs besides the tasks themselves.
+#[derive(Debug, Clone)]
+pub struct PlanOptions {
+    pub start_date: NaiveDate,
+    pub days: u32, // Number of days, starting at `start_date`, that overflow may spill into
+    pub working_hours: WorkingHours,
+    pub fixed_blocks: Vec<FixedBlock>,
+}
+
+/// A task placed on the calendar.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct ScheduledBlock {
+    pub task_id: i32,
+    pub title: String,
+    pub date: NaiveDate,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub past_deadline: bool, // No earlier slot was free before the task's deadline
+}
+
+/// Why a task could not be placed.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum SkipReason {
+    NoDuration,
+    LongerThanAnySlot {
+        longest_slot: i64,
+    },
+    NoRoom {
+        days: u32,
+    },
+    Blocked {
+        blocked_by: i32,
+    },
+    DependencyCycle,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::NoDuration => write!(f, "Task has no duration to plan around"),
+            SkipReason::LongerThanAnySlot { longest_slot } =>
+                write!(f, "Task is longer than the longest free slot ({longest_slot} minutes)"),
+            SkipReason::NoRoom { days } =>
+                write!(f, "No free time left in the next {days} days"),
+            SkipReason::Blocked { blocked_by } =>
+                write!(f, "Blocked by task {blocked_by}, which could not be scheduled"),
+            SkipReason::DependencyCycle =>
+                write!(f, "Task is part of, or waits on, a dependency cycle"),
+        }
+    }
+}
+
+/// A task left out of the schedule, with an explanation.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct UnscheduledTask {
+    pub task_id: i32,
+    pub title: String,
+    #[serde(flatten)]
+    pub reason: SkipReason,
+    pub message: String,
+}
+
+/// The planner's output, ordered by date and start time.
+#[derive(Serialize, ToSchema, Debug, Clone, Default)]
+pub struct Schedule {
+    pub blocks: Vec<ScheduledBlock>,
+    pub unscheduled: Vec<UnscheduledTask>,
+}
+
+type Slot = (NaiveTime, NaiveTime);
+
+/// Plans tasks into free time by priority, then deadline.
+/// Each task goes into the earliest slot it fits in, so whatever does not fit
+/// on the first day spills over into the following days.
+/// A task is only placed after all of its blockers that are part of the plan.
+pub fn plan(tasks: Vec<PlanTask>, options: &PlanOptions) -> Schedule {
+    let key = |task: &PlanTask| (
+        task.priority.unwrap_or(i32::MAX),
+        task.deadline.unwrap_or(NaiveDate::MAX),
+        task.task_id,
+    );
+    let ids: Vec<i32> = tasks.iter().map(|task| task.task_id).collect();
+    let edges: Vec<(i32, i32)> = tasks
+        .iter()
+        .flat_map(|task| task.blocked_by.iter().map(|blocker| (task.task_id, *blocker)))
+        .collect();
+    let mut by_id: HashMap<i32, PlanTask> = tasks
+        .into_iter()
+        .map(|task| (task.task_id, task))
+        .collect();
+    let (order, cyclic) = dependencies::topological_order(&ids, &edges, |id| key(&by_id[&id]));
+    let planned: HashSet<i32> = ids.into_iter().collect();
+
+    let days: Vec<NaiveDate> = (0..options.days)
+        .filter_map(|offset| options.start_date.checked_add_days(Days::new(offset as u64)))
+        .collect();
+    let mut free: Vec<Vec<Slot>> = days
+        .iter()
+        .map(|day| free_slots(*day, options))
+        .collect();
+    let longest_slot = free
+        .iter()
+        .flatten()
+        .map(|(start, end)| (*end - *start).num_minutes())
+        .max()
+        .unwrap_or(0);
+
+    let mut schedule = Schedule::default();
+    let mut finished_at: HashMap<i32, (NaiveDate, NaiveTime)> = HashMap::new();
+    for task in order.into_iter().filter_map(|id| by_id.remove(&id)) {
+        let minutes = match task.duration {
+            Some(minutes) if minutes > 0 => minutes as i64,
+            _ => {
+                schedule.unscheduled.push(unscheduled(task, SkipReason::NoDuration));
+                continue;
+            }
+        };
+        if minutes > longest_slot {
+            schedule.unscheduled.push(
+                unscheduled(task, SkipReason::LongerThanAnySlot { longest_slot })
+            );
+            continue;
+        }
+
+        // Start no earlier than the last blocker ends
+        let mut earliest = None;
+        let mut skipped_blocker = None;
+        for blocker in task.blocked_by.iter().filter(|blocker| planned.contains(blocker)) {
+            match finished_at.get(blocker) {
+                Some(end) => {
+                    earliest = earliest.max(Some(*end));
+                }
+                None => {
+                    skipped_blocker = Some(*blocker);
+                    break;
+                }
+            }
+        }
+        if let Some(blocked_by) = skipped_blocker {
+            schedule.unscheduled.push(unscheduled(task, SkipReason::Blocked { blocked_by }));
+            continue;
+        }
+
+        match take_slot(&days, &mut free, minutes, earliest) {
+            Some((date, start, end)) => {
+                finished_at.insert(task.task_id, (date, end));
+                schedule.blocks.push(ScheduledBlock {
+                    past_deadline: task.deadline.is_some_and(|deadline| date > deadline),
+                    task_id: task.task_id,
+                    title: task.title,
+                    date,
+                    start,
+                    end,
+                });
+            }
+            None => {
+                schedule.unscheduled.push(
+                    unscheduled(task, SkipReason::NoRoom { days: options.days })
+                );
+            }
+        }
+    }
+    for task in cyclic.into_iter().filter_map(|id| by_id.remove(&id)) {
+        schedule.unscheduled.push(unscheduled(task, SkipReason::DependencyCycle));
+    }
+
+    schedule.blocks.sort_by_key(|block| (block.date, block.start));
+    schedule
+}
+
+/// Returns the working hours of `day` minus any fixed blocks that fall on it.
+fn free_slots(day: NaiveDate, options: &PlanOptions) -> Vec<Slot> {
+    let WorkingHours { start, end } = options.working_hours;
+    let mut slots = if start < end { vec![(start, end)] } else { Vec::new() };
+
+    for block in &options.fixed_blocks {
+        if block.date.is_some_and(|date| date != day) {
+            continue;
+        }
+        slots = slots
+            .into_iter()
+            .flat_map(|(start, end)| {
+                if block.end <= start || block.start >= end {
+                    return vec![(start, end)];
+                }
+                let mut remaining = Vec::new();
+                if block.start > start {
+                    remaining.push((start, block.start));
+                }
+                if block.end < end {
+                    remaining.push((block.end, end));
+                }
+                remaining
+            })
+            .collect();
+    }
+
+    slots.sort();
+    slots
+}
+
+/// Takes `minutes` from the earliest slot that can hold them, starting no sooner than `earliest`.
+fn take_slot(
+    days: &[NaiveDate],
+    free: &mut [Vec<Slot>],
+    minutes: i64,
+    earliest: Option<(NaiveDate, NaiveTime)>
+) -> Option<(NaiveDate, NaiveTime, NaiveTime)> {
+    let length = Duration::try_minutes(minutes)?;
+    for (day, slots) in days.iter().zip(free.iter_mut()) {
+        let not_before = match earliest {
+            Some((date, _)) if *day < date => {
+                continue;
+            }
+            Some((date, time)) if *day == date => time,
+            _ => NaiveTime::MIN,
+        };
+        for index in 0..slots.len() {
+            let (slot_start, slot_end) = slots[index];
+            let start = slot_start.max(not_before);
+            if slot_end <= start || slot_end - start < length {
+                continue;
+            }
+            let end = start + length;
+            // Keep any gap left in front of the task free for later tasks
+            if start > slot_start {
+                slots[index] = (slot_start, start);
+                slots.insert(index + 1, (end, slot_end));
+            } else {
+                slots[index] = (end, slot_end);
+            }
+            return Some((*day, start, end));
+        }
+    }
+    None
+}
+
+fn unscheduled(task: PlanTask, reason: SkipReason) -> UnscheduledTask {
+    UnscheduledTask {
+        task_id: task.task_id,
+        title: task.title,
+        message: reason.to_string(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    fn task(task_id: i32, duration: Option<i32>, priority: Option<i32>) -> PlanTask {
+        PlanTask {
+            task_id,
+            title: format!("Task {task_id}"),
+            duration,
+            priority,
+            deadline: None,
+            blocked_by: Vec::new(),
+        }
+    }
+
+    fn options(days: u32, fixed_blocks: Vec<FixedBlock>) -> PlanOptions {
+        PlanOptions {
+            start_date: day(18),
+            days,
+            working_hours: WorkingHours { start: time(9, 0), end: time(12, 0) },
+            fixed_blocks,
+        }
+    }
+
+    #[test]
+    fn fills_by_priority_then_deadline() {
+        let mut urgent = task(3, Some(60), Some(2));
+        urgent.deadline = Some(day(18));
+        let tasks = vec![task(1, Some(60), Some(3)), task(2, Some(60), Some(2)), urgent];
+
+        let schedule = plan(tasks, &options(1, Vec::new()));
+        let order: Vec<(i32, NaiveTime)> = schedule.blocks
+            .iter()
+            .map(|block| (block.task_id, block.start))
+            .collect();
+        assert_eq!(order, vec![(3, time(9, 0)), (2, time(10, 0)), (1, time(11, 0))]);
+    }
+
+    #[test]
+    fn works_around_fixed_blocks() {
+        let lunch = FixedBlock { date: None, start: time(10, 0), end: time(11, 0), label: "Lunch".into() };
+        let schedule = plan(
+            vec![task(1, Some(90), Some(1)), task(2, Some(60), Some(2))],
+            &options(2, vec![lunch])
+        );
+
+        assert_eq!(schedule.blocks.len(), 1);
+        assert_eq!((schedule.blocks[0].task_id, schedule.blocks[0].start), (2, time(9, 0)));
+        assert_eq!(
+            schedule.unscheduled[0].reason,
+            SkipReason::LongerThanAnySlot { longest_slot: 60 }
+        );
+    }
+
+    #[test]
+    fn spills_overflow_and_explains_leftovers() {
+        let mut due_today = task(1, Some(120), Some(1));
+        due_today.deadline = Some(day(18));
+        let mut late = task(2, Some(120), Some(2));
+        late.deadline = Some(day(18));
+        let tasks = vec![due_today, late, task(3, Some(120), Some(3)), task(4, None, Some(1))];
+
+        let schedule = plan(tasks, &options(2, Vec::new()));
+        let placed: Vec<(i32, NaiveDate, bool)> = schedule.blocks
+            .iter()
+            .map(|block| (block.task_id, block.date, block.past_deadline))
+            .collect();
+        assert_eq!(placed, vec![(1, day(18), false), (2, day(19), true)]);
+
+        let reasons: Vec<(i32, SkipReason)> = schedule.unscheduled
+            .into_iter()
+            .map(|task| (task.task_id, task.reason))
+            .collect();
+        assert_eq!(reasons, vec![(4, SkipReason::NoDuration), (3, SkipReason::NoRoom { days: 2 })]);
+    }
+
+    #[test]
+    fn places_tasks_after_their_blockers() {
+        let mut blocked = task(1, Some(60), Some(1));
+        blocked.blocked_by = vec![2];
+        let mut stuck = task(3, Some(60), Some(1));
+        stuck.blocked_by = vec![4];
+        let tasks = vec![blocked, task(2, Some(60), Some(3)), stuck, task(4, None, Some(1))];
+
+        let schedule = plan(tasks, &options(1, Vec::new()));
+        let order: Vec<i32> = schedule.blocks.iter().map(|block| block.task_id).collect();
+        assert_eq!(order, vec![2, 1]);
+        assert!(schedule.unscheduled.iter().any(|task| task.reason == SkipReason::Blocked { blocked_by: 4 }));
+    }
+}
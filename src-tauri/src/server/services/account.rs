@@ -0,0 +1,325 @@
+use std::{ env, sync::Arc };
+
+use chrono::{ Days, Duration, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use crate::server::TauriAppState;
+use crate::server::mailer::{ send_email, Email, Mailer };
+use crate::server::tokens::{ hash_token, new_secret_token };
+use crate::server::validation::{ Checker, Field, Kind, Rule, Schema, Validate, ValidationErrors };
+use super::sessions::revoke_all_sessions;
+use super::users::{ hash_user_password, PASSWORD_RULES };
+use super::ServiceError;
+
+/// How long a reset link stays valid.
+const RESET_TOKEN_MINUTES: i64 = 30;
+
+/// How long a verification link stays valid.
+const VERIFICATION_TOKEN_DAYS: u64 = 2;
+
+/// Represents a reset confirmation received from the client.
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct ConfirmReset {
+    pub token: String, // Token from the reset email
+    pub new_password: String,
+}
+
+/// Rules for a reset confirmation; the new password follows the registration rules.
+pub(crate) const CONFIRM_RESET_SCHEMA: Schema = Schema {
+    name: "ConfirmReset",
+    fields: &[
+        Field { name: "token", kind: Kind::String, required: true, rules: &[Rule::NotBlank] },
+        Field { name: "new_password", kind: Kind::String, required: true, rules: PASSWORD_RULES },
+    ],
+};
+
+impl Validate for ConfirmReset {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        Checker::new(&CONFIRM_RESET_SCHEMA)
+            .text("token", Some(&self.token))
+            .text("new_password", Some(&self.new_password))
+            .finish()
+    }
+}
+
+/// What opening a verification link did.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailVerified {
+    Account,
+    EmailChanged, // Every session was logged out, since tokens name the old address
+}
+
+impl EmailVerified {
+    pub fn message(self) -> &'static str {
+        match self {
+            EmailVerified::Account => "Email verified successfully",
+            EmailVerified::EmailChanged => "Email changed successfully, please log in again",
+        }
+    }
+}
+
+/// Outcome of using a verification token.
+enum Verified {
+    Account,
+    EmailChanged {
+        old_email: String,
+        version: i32, // The account's new token version
+        revoked_sessions: Vec<i32>,
+    },
+    EmailTaken, // Another account took the new address in the meantime
+    InvalidToken,
+}
+
+/// Emails a reset link if an account uses the address.
+/// The account is looked up and mailed in the background: waiting for either would make
+/// answers for known emails measurably slower than for unknown ones.
+pub fn request_password_reset(state: &TauriAppState, email: &str) {
+    let pool = state.pool.clone();
+    let mailer = state.mailer.clone();
+    let email = email.to_lowercase();
+    tokio::spawn(async move {
+        match create_reset_token(&pool, &email).await {
+            Ok(Some(token)) => {
+                if let Err(err) = send_email(mailer, reset_email(&email, &token)).await {
+                    tracing::error!(error = %err, "password reset mail error");
+                }
+            }
+            Ok(None) => {}
+            Err(err) => tracing::error!(error = %err, "password reset token error"),
+        }
+    });
+}
+
+/// Sets a new password with a reset token, using up the token and logging the user out everywhere.
+pub async fn reset_password(state: &TauriAppState, reset: &ConfirmReset) -> Result<(), ServiceError> {
+    reset.validate()?;
+
+    let Some(email) = use_reset_token(&state.pool, &reset.token, &reset.new_password).await? else {
+        return Err(ServiceError::BadRequest("Reset token is invalid or expired"));
+    };
+    let version = revoke_all_sessions(&state.pool, &email).await?;
+    state.revocations.set_version(&email, version);
+    state.notifier.user_signed_out(&email);
+    Ok(())
+}
+
+/// Confirms an email address, or moves the account to a new one, with a verification token.
+pub async fn verify_email(state: &TauriAppState, token: &str) -> Result<EmailVerified, ServiceError> {
+    match use_verification_token(&state.pool, token).await? {
+        Verified::Account => Ok(EmailVerified::Account),
+        Verified::EmailChanged { old_email, version, revoked_sessions } => {
+            state.revocations.set_version(&old_email, version);
+            for session_id in revoked_sessions {
+                state.revocations.revoke_session(session_id);
+            }
+            state.notifier.user_signed_out(&old_email);
+            Ok(EmailVerified::EmailChanged)
+        }
+        Verified::EmailTaken => Err(ServiceError::Conflict("User with email already exists")),
+        Verified::InvalidToken => Err(ServiceError::BadRequest("Verification token is invalid or expired")),
+    }
+}
+
+/// Stores a new reset token for an existing user and returns it in plain text.
+async fn create_reset_token(pool: &PgPool, email: &str) -> Result<Option<String>, sqlx::Error> {
+    let exists: Option<String> = sqlx
+        ::query_scalar("SELECT email FROM users WHERE email = $1")
+        .bind(email)
+        .fetch_optional(pool).await?;
+    if exists.is_none() {
+        return Ok(None);
+    }
+
+    let token = new_secret_token();
+    let expires_at = Utc::now() + Duration::try_minutes(RESET_TOKEN_MINUTES).unwrap();
+    sqlx
+        ::query("INSERT INTO password_resets(token_hash, email, expires_at) VALUES($1, $2, $3)")
+        .bind(hash_token(&token))
+        .bind(email)
+        .bind(expires_at)
+        .execute(pool).await?;
+    Ok(Some(token))
+}
+
+/// Sets the new password if the token is valid and returns whose it was.
+/// Every outstanding reset token of that user is used up along with it.
+async fn use_reset_token(
+    pool: &PgPool,
+    token: &str,
+    new_password: &str
+) -> Result<Option<String>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Lock the token so it can only be used once, even by concurrent requests
+    let email: Option<String> = sqlx
+        ::query_scalar(
+            "SELECT email FROM password_resets
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+            FOR UPDATE"
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&mut *tx).await?;
+    let Some(email) = email else {
+        return Ok(None);
+    };
+
+    sqlx
+        ::query("UPDATE users SET password = $1 WHERE email = $2")
+        .bind(hash_user_password(new_password.to_string()))
+        .bind(&email)
+        .execute(&mut *tx).await?;
+    sqlx
+        ::query("UPDATE password_resets SET used_at = NOW() WHERE email = $1 AND used_at IS NULL")
+        .bind(&email)
+        .execute(&mut *tx).await?;
+
+    tx.commit().await?;
+    Ok(Some(email))
+}
+
+fn reset_email(email: &str, token: &str) -> Email {
+    let app_url = env::var("APP_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+    Email {
+        to: email.to_string(),
+        subject: "Reset your Kaizen password".to_string(),
+        body: format!(
+            "Someone asked to reset the password for this account.\n\n\
+            Open {app_url}/reset-password?token={token} to choose a new one.\n\
+            The link expires in {RESET_TOKEN_MINUTES} minutes. If this wasn't you, ignore this email."
+        ),
+    }
+}
+
+/// Creates a verification token for an account and emails it.
+/// With `new_email`, the token confirms a change to that address and is sent there instead.
+/// A failed delivery is only logged, since the user can ask for another email.
+pub(crate) async fn send_verification(
+    pool: &PgPool,
+    mailer: Arc<dyn Mailer>,
+    email: &str,
+    new_email: Option<&str>
+) -> Result<(), sqlx::Error> {
+    let token = new_secret_token();
+    let expires_at = Utc::now() + Days::new(VERIFICATION_TOKEN_DAYS);
+    sqlx
+        ::query(
+            "INSERT INTO email_verifications(token_hash, email, new_email, expires_at) VALUES($1, $2, $3, $4)"
+        )
+        .bind(hash_token(&token))
+        .bind(email)
+        .bind(new_email)
+        .bind(expires_at)
+        .execute(pool).await?;
+
+    let recipient = new_email.unwrap_or(email);
+    if let Err(err) = send_email(mailer, verification_email(recipient, &token)).await {
+        tracing::error!(error = %err, "verification mail error");
+    }
+    Ok(())
+}
+
+/// Whether a user has confirmed their email address.
+pub(crate) async fn is_verified(pool: &PgPool, email: &str) -> Result<bool, sqlx::Error> {
+    let verified: Option<bool> = sqlx
+        ::query_scalar("SELECT email_verified_at IS NOT NULL FROM users WHERE email = $1")
+        .bind(email)
+        .fetch_optional(pool).await?;
+    Ok(verified.unwrap_or(false))
+}
+
+/// Guard for actions only verified accounts may take, such as adding tasks and habits.
+pub(crate) async fn require_verified(pool: &PgPool, email: &str) -> Result<(), ServiceError> {
+    if is_verified(pool, email).await? {
+        Ok(())
+    } else {
+        Err(ServiceError::Forbidden("Please verify your email address first"))
+    }
+}
+
+/// Marks the token's address as verified, moving the account to it for an email change.
+async fn use_verification_token(pool: &PgPool, token: &str) -> Result<Verified, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let used: Option<(String, Option<String>)> = sqlx
+        ::query_as(
+            "UPDATE email_verifications SET used_at = NOW()
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+            RETURNING email, new_email"
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&mut *tx).await?;
+    let (email, new_email) = match used {
+        Some(used) => used,
+        None => {
+            return Ok(Verified::InvalidToken);
+        }
+    };
+
+    let Some(new_email) = new_email else {
+        sqlx
+            ::query(
+                "UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW()) WHERE email = $1"
+            )
+            .bind(&email)
+            .execute(&mut *tx).await?;
+        tx.commit().await?;
+        return Ok(Verified::Account);
+    };
+
+    let taken: Option<String> = sqlx
+        ::query_scalar("SELECT email FROM users WHERE email = $1")
+        .bind(&new_email)
+        .fetch_optional(&mut *tx).await?;
+    if taken.is_some() {
+        // Use the token up and drop the change, so the link cannot claim the address once it frees up
+        sqlx
+            ::query("UPDATE users SET pending_email = NULL WHERE email = $1 AND pending_email = $2")
+            .bind(&email)
+            .bind(&new_email)
+            .execute(&mut *tx).await?;
+        tx.commit().await?;
+        return Ok(Verified::EmailTaken);
+    }
+
+    // Only the latest requested change counts; the user's data follows through ON UPDATE CASCADE
+    let version: Option<i32> = sqlx
+        ::query_scalar(
+            "UPDATE users SET email = $2, pending_email = NULL, email_verified_at = NOW(),
+                token_version = token_version + 1
+            WHERE email = $1 AND pending_email = $2
+            RETURNING token_version"
+        )
+        .bind(&email)
+        .bind(&new_email)
+        .fetch_optional(&mut *tx).await?;
+    let Some(version) = version else {
+        // A newer change replaced this one; the token is used up all the same
+        tx.commit().await?;
+        return Ok(Verified::InvalidToken);
+    };
+    let revoked_sessions: Vec<i32> = sqlx
+        ::query_scalar(
+            "UPDATE sessions SET revoked_at = NOW() WHERE email = $1 AND revoked_at IS NULL
+            RETURNING session_id"
+        )
+        .bind(&email)
+        .fetch_all(&mut *tx).await?;
+
+    tx.commit().await?;
+    Ok(Verified::EmailChanged { old_email: email, version, revoked_sessions })
+}
+
+fn verification_email(email: &str, token: &str) -> Email {
+    let app_url = env::var("APP_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+    Email {
+        to: email.to_string(),
+        subject: "Verify your Kaizen email address".to_string(),
+        body: format!(
+            "Welcome to Kaizen!\n\n\
+            Open {app_url}/verify-email?token={token} to confirm this is your email address.\n\
+            The link expires in {VERIFICATION_TOKEN_DAYS} days."
+        ),
+    }
+}
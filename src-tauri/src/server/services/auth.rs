@@ -0,0 +1,295 @@
+use std::time::Instant;
+
+use actix_web::HttpRequest;
+use chrono::{ Duration, Utc };
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+use crate::server::TauriAppState;
+use crate::server::throttle::Throttled;
+use crate::server::tokens::{ verify_token, CustomClaims, ACCESS_TOKEN_HOURS };
+use super::sessions::{
+    revoke_current,
+    rotate_refresh_token,
+    start_session,
+    IssuedTokens,
+    RefreshError,
+};
+use super::two_factor::{ check_second_factor, start_challenge, SecondFactor };
+use super::users::{
+    dummy_password_hash,
+    hash_user_password,
+    verify_password,
+    EMAIL_RULES,
+    PASSWORD_RULES,
+    USERNAME_RULES,
+};
+use super::account::send_verification;
+use crate::server::validation::{ Checker, Field, Kind, Rule, Schema, Validate, ValidationErrors };
+use super::ServiceError;
+
+/// Represents the user data received from the client during registration.
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct RegisterUser {
+    pub username: String, // User's username
+    pub email: String, // User's email
+    pub password: String, // User's password
+    pub timezone: Option<String>, // IANA timezone name, defaults to UTC
+}
+
+/// Rules for registration, also served as JSON Schema.
+pub(crate) const REGISTER_USER_SCHEMA: Schema = Schema {
+    name: "RegisterUser",
+    fields: &[
+        Field { name: "username", kind: Kind::String, required: true, rules: USERNAME_RULES },
+        Field { name: "email", kind: Kind::String, required: true, rules: EMAIL_RULES },
+        Field { name: "password", kind: Kind::String, required: true, rules: PASSWORD_RULES },
+        Field { name: "timezone", kind: Kind::String, required: false, rules: &[Rule::Timezone] },
+    ],
+};
+
+impl Validate for RegisterUser {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        Checker::new(&REGISTER_USER_SCHEMA)
+            .text("username", Some(&self.username))
+            .text("email", Some(&self.email))
+            .text("password", Some(&self.password))
+            .text("timezone", self.timezone.as_deref())
+            .finish()
+    }
+}
+
+/// The device and IP address a session is started from, as shown in the session list.
+pub struct ClientInfo {
+    pub device: Option<String>,
+    pub ip: Option<String>,
+    pub(crate) from_window: bool, // Signs the desktop app in; HTTP clients never do
+}
+
+impl ClientInfo {
+    /// Reads the device (User-Agent) and IP address a request came from.
+    pub fn of(req: &HttpRequest) -> Self {
+        let device = req
+            .headers()
+            .get("User-Agent")
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+        let ip = req.connection_info().realip_remote_addr().map(String::from);
+        ClientInfo { device, ip, from_window: false }
+    }
+
+    /// The desktop app calling in over IPC rather than HTTP.
+    pub fn desktop() -> Self {
+        ClientInfo { device: Some("Kaizen desktop app".to_string()), ip: None, from_window: true }
+    }
+}
+
+/// The signed-in user, as shown in the window.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CurrentUser {
+    pub email: String,
+    pub username: String,
+}
+
+/// A new session and who it belongs to.
+pub struct SignedIn {
+    pub(crate) tokens: IssuedTokens,
+    pub user: CurrentUser,
+}
+
+/// Outcome of checking a password.
+pub enum Login {
+    SignedIn(SignedIn),
+    TwoFactorRequired {
+        challenge_token: String, // Finishes the login together with a code
+    },
+}
+
+/// Checks a session token and returns its claims, for callers outside an HTTP request.
+pub fn authenticate(state: &TauriAppState, token: &str) -> Result<CustomClaims, ServiceError> {
+    let Ok(token) = verify_token(token) else {
+        return Err(ServiceError::Unauthorized("Session expired, please log in again"));
+    };
+    let claims = token.claims().custom.clone();
+    if state.revocations.is_revoked(&claims) {
+        return Err(ServiceError::Unauthorized("Token has been revoked"));
+    }
+    Ok(claims)
+}
+
+/// Creates an account, emails its verification link and starts its first session.
+pub async fn register(
+    state: &TauriAppState,
+    user: &RegisterUser,
+    client: &ClientInfo
+) -> Result<SignedIn, ServiceError> {
+    // Reject the payload with every invalid field listed
+    user.validate()?;
+
+    let email = user.email.to_lowercase();
+    let hashed_password = hash_user_password(user.password.clone());
+    let result = sqlx
+        ::query("INSERT INTO users(username, email, password, timezone)
+            VALUES($1, $2, $3, $4)")
+        .bind(&user.username)
+        .bind(&email)
+        .bind(hashed_password)
+        .bind(user.timezone.as_deref().unwrap_or("UTC"))
+        .execute(&state.pool).await;
+
+    if let Err(err) = result {
+        let err_string = err.to_string();
+        return Err(
+            ServiceError::Internal(if err_string.contains("duplicate key value") {
+                "User with email already exists".to_string()
+            } else {
+                format!("Failed to store user into database: {}", err_string)
+            })
+        );
+    }
+
+    // The account starts unverified until the emailed link is opened
+    send_verification(&state.pool, state.mailer.clone(), &email, None).await?;
+
+    sign_in(state, &email, &user.username, client).await
+}
+
+/// Checks an email and password.
+/// Unknown emails and wrong passwords get the same answer after the same amount of work,
+/// so this cannot be used to find out which emails have accounts.
+pub async fn login(
+    state: &TauriAppState,
+    email: &str,
+    password: String,
+    client: &ClientInfo
+) -> Result<Login, ServiceError> {
+    let email = email.to_lowercase();
+
+    // Refuse accounts locked by earlier failures before doing any work
+    if let Some(retry_after) = state.throttle.accounts.locked_for(&email, Instant::now()) {
+        return Err(ServiceError::Throttled(Throttled { retry_after }));
+    }
+
+    let found_user: Option<(String, String)> = sqlx
+        ::query_as("SELECT username, password FROM users WHERE email = $1")
+        .bind(&email)
+        .fetch_optional(&state.pool).await?;
+
+    // Verify the password against the user's, or a dummy one for unknown users
+    let password_matches = match &found_user {
+        Some((_, hashed_password)) => verify_password(password, hashed_password.clone()),
+        None => {
+            verify_password(password, dummy_password_hash().to_string());
+            false
+        }
+    };
+    let username = match found_user {
+        Some((username, _)) if password_matches => username,
+        _ => {
+            state.throttle.accounts.record_failure(&email, Instant::now());
+            return Err(ServiceError::Unauthorized("Incorrect email or password"));
+        }
+    };
+
+    // Accounts with two-factor authentication finish logging in with `verify_second_factor`
+    if let Some(challenge_token) = start_challenge(&state.pool, &email).await? {
+        return Ok(Login::TwoFactorRequired { challenge_token });
+    }
+    state.throttle.accounts.record_success(&email);
+
+    Ok(Login::SignedIn(sign_in(state, &email, &username, client).await?))
+}
+
+/// The second login step: swaps a challenge token and a code for a session.
+pub async fn verify_second_factor(
+    state: &TauriAppState,
+    challenge_token: &str,
+    code: Option<&str>,
+    recovery_code: Option<&str>,
+    client: &ClientInfo
+) -> Result<SignedIn, ServiceError> {
+    match check_second_factor(&state.pool, challenge_token, code, recovery_code).await? {
+        SecondFactor::Passed { email, username } => {
+            state.throttle.accounts.record_success(&email);
+            sign_in(state, &email, &username, client).await
+        }
+        SecondFactor::Failed { email } => {
+            state.throttle.accounts.record_failure(&email, Instant::now());
+            Err(ServiceError::Unauthorized("Incorrect code"))
+        }
+        SecondFactor::UnknownChallenge =>
+            Err(ServiceError::Unauthorized("Login challenge is invalid or expired")),
+    }
+}
+
+/// Ends the session a token belongs to, along with the token itself.
+pub async fn logout(state: &TauriAppState, claims: &CustomClaims) -> Result<(), ServiceError> {
+    // The token cannot outlive its own expiry, so the entry only needs to last that long
+    let expires_at = Utc::now() + Duration::try_hours(ACCESS_TOKEN_HOURS).unwrap();
+    revoke_current(&state.pool, claims, expires_at).await?;
+
+    state.revocations.revoke_token(&claims.jti, expires_at);
+    state.revocations.revoke_session(claims.session_id);
+    state.notifier.session_ended(claims.session_id);
+    Ok(())
+}
+
+/// Signs the desktop app back in with the refresh token saved by its last run.
+/// Credentials the server no longer accepts are wiped; ones that could not be checked are kept.
+pub async fn restore_session(state: &TauriAppState) -> Result<(), ServiceError> {
+    let Some(credentials) = state.notifier.saved_credentials() else {
+        return Ok(());
+    };
+    match rotate_refresh_token(&state.pool, &credentials.refresh_token, None).await {
+        Ok(tokens) => {
+            let user = CurrentUser { email: credentials.email, username: credentials.username };
+            state.notifier.signed_in(user, tokens.session_id, tokens.token, tokens.refresh_token);
+            Ok(())
+        }
+        Err(RefreshError::Invalid | RefreshError::Reused) => {
+            state.notifier.forget_credentials();
+            Err(ServiceError::Unauthorized("Saved session has ended, please log in again"))
+        }
+        Err(RefreshError::Database(e)) => Err(e.into()),
+        Err(RefreshError::Token) => Err(ServiceError::Internal("Failed to generate token".to_string())),
+    }
+}
+
+/// Swaps the desktop app's refresh token for a new pair once its access token has run out,
+/// returning the new access token. The app is the only holder of its refresh token, so
+/// nobody else rotates it; the new pair reaches it through the notifier.
+pub async fn refresh_session(state: &TauriAppState, refresh_token: &str) -> Result<String, ServiceError> {
+    match rotate_refresh_token(&state.pool, refresh_token, None).await {
+        Ok(tokens) => {
+            state.notifier.refreshed(tokens.session_id, tokens.token.clone(), tokens.refresh_token);
+            Ok(tokens.token)
+        }
+        Err(RefreshError::Invalid | RefreshError::Reused) =>
+            Err(ServiceError::Unauthorized("Session has ended, please log in again")),
+        Err(RefreshError::Database(e)) => Err(e.into()),
+        Err(RefreshError::Token) => Err(ServiceError::Internal("Failed to generate token".to_string())),
+    }
+}
+
+/// Starts a session for a user who just proved who they are,
+/// recording it as the app's signed-in user when the window asked for it.
+pub(crate) async fn sign_in(
+    state: &TauriAppState,
+    email: &str,
+    username: &str,
+    client: &ClientInfo
+) -> Result<SignedIn, ServiceError> {
+    let Ok(tokens) = start_session(&state.pool, email, client).await else {
+        return Err(ServiceError::Internal("Failed to generate token".to_string()));
+    };
+    let user = CurrentUser { email: email.to_string(), username: username.to_string() };
+    // Other clients, such as the CLI, sign in next to the window rather than as it
+    if client.from_window {
+        state.notifier.signed_in(
+            user.clone(),
+            tokens.session_id,
+            tokens.token.clone(),
+            tokens.refresh_token.clone()
+        );
+    }
+    Ok(SignedIn { tokens, user })
+}
@@ -0,0 +1,74 @@
+use chrono::{ DateTime, NaiveDate, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::{ prelude::FromRow, PgPool };
+use utoipa::ToSchema;
+use crate::server::deadline;
+use super::account::require_verified;
+use super::users::user_timezone;
+use super::ServiceError;
+
+#[derive(FromRow, Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Habit {
+    pub habit_id: i32,
+    pub user_email: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_checked: Option<NaiveDate>, // Latest day the habit was checked off
+}
+
+/// A user's habits, oldest first.
+pub async fn list_habits(pool: &PgPool, user_email: &str) -> Result<Vec<Habit>, ServiceError> {
+    let habits = sqlx
+        ::query_as::<_, Habit>(
+            "SELECT h.*, (SELECT MAX(c.day) FROM habit_checkins c WHERE c.habit_id = h.habit_id) AS last_checked
+            FROM habits h WHERE h.user_email = $1 ORDER BY h.habit_id"
+        )
+        .bind(user_email)
+        .fetch_all(pool).await?;
+    Ok(habits)
+}
+
+/// Stores a new habit for the user, once they have verified their email address.
+pub async fn create_habit(pool: &PgPool, user_email: &str, name: &str) -> Result<(), ServiceError> {
+    require_verified(pool, user_email).await?;
+
+    let result = sqlx
+        ::query("INSERT INTO habits(user_email, name) VALUES($1, $2)")
+        .bind(user_email)
+        .bind(name)
+        .execute(pool).await;
+
+    if let Err(err) = result {
+        return Err(ServiceError::Internal(format!("Failed to store habit into database: {}", err)));
+    }
+    Ok(())
+}
+
+/// Checks one of the user's habits off for a day, by default today in the user's timezone.
+/// Checking a habit twice on the same day is a no-op.
+pub async fn check_habit(
+    pool: &PgPool,
+    user_email: &str,
+    habit_id: i32,
+    day: Option<NaiveDate>
+) -> Result<(), ServiceError> {
+    let owned: bool = sqlx
+        ::query_scalar("SELECT EXISTS(SELECT 1 FROM habits WHERE habit_id = $1 AND user_email = $2)")
+        .bind(habit_id)
+        .bind(user_email)
+        .fetch_one(pool).await?;
+    if !owned {
+        return Err(ServiceError::NotFound("Habit not found"));
+    }
+
+    let day = match day {
+        Some(day) => day,
+        None => deadline::today(user_timezone(pool, user_email).await?, Utc::now()),
+    };
+    sqlx
+        ::query("INSERT INTO habit_checkins(habit_id, day) VALUES($1, $2) ON CONFLICT DO NOTHING")
+        .bind(habit_id)
+        .bind(day)
+        .execute(pool).await?;
+    Ok(())
+}
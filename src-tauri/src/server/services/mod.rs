@@ -0,0 +1,67 @@
+// Operations shared by the HTTP handlers and the Tauri commands.
+// Services take an already authenticated caller and report failures as `ServiceError`,
+// which each front end turns into its own kind of response.
+// Both front ends call down into these; nothing here depends on the handlers.
+
+pub mod auth; // Registration, login and logout
+pub mod users; // Passwords, timezones and account field rules
+pub mod account; // Password resets and email verification
+pub mod sessions; // Sessions and their refresh tokens
+pub mod two_factor; // The second login step
+pub mod tasks; // Tasks and their blocked-by links
+pub mod habits; // Habits and daily check-ins
+pub mod reviews; // Daily and weekly reviews with their statistics
+
+use actix_web::{ HttpResponse, ResponseError };
+use serde_json::{ json, Value };
+
+use crate::server::throttle::Throttled;
+use crate::server::validation::ValidationErrors;
+
+/// Why a service call failed.
+#[derive(Debug, thiserror::Error)]
+pub enum ServiceError {
+    #[error("{0}")] BadRequest(&'static str),
+    #[error("{0}")] Unauthorized(&'static str),
+    #[error("{0}")] Forbidden(&'static str),
+    #[error("{0}")] NotFound(&'static str),
+    #[error("{0}")] Conflict(&'static str),
+    #[error("Some fields are invalid")] Invalid(ValidationErrors),
+    #[error(transparent)] Throttled(#[from] Throttled),
+    #[error(transparent)] Database(#[from] sqlx::Error),
+    #[error("{0}")] Internal(String),
+}
+
+impl From<ValidationErrors> for ServiceError {
+    fn from(errors: ValidationErrors) -> Self {
+        ServiceError::Invalid(errors)
+    }
+}
+
+impl ServiceError {
+    /// The response the HTTP API answers with, matching what handlers sent before services existed.
+    pub fn into_response(self) -> HttpResponse {
+        match self {
+            ServiceError::BadRequest(message) => HttpResponse::BadRequest().json(message),
+            ServiceError::Unauthorized(message) => HttpResponse::Unauthorized().json(message),
+            ServiceError::Forbidden(message) => HttpResponse::Forbidden().json(message),
+            ServiceError::NotFound(message) => HttpResponse::NotFound().json(message),
+            ServiceError::Conflict(message) => HttpResponse::Conflict().json(message),
+            ServiceError::Invalid(errors) => errors.into_response(),
+            ServiceError::Throttled(throttled) => throttled.error_response(),
+            ServiceError::Database(e) => HttpResponse::InternalServerError().json(e.to_string()),
+            ServiceError::Internal(message) => HttpResponse::InternalServerError().json(message),
+        }
+    }
+
+    /// The error as sent to the window through IPC.
+    pub fn to_json(&self) -> Value {
+        match self {
+            ServiceError::Invalid(errors) =>
+                json!({"message": self.to_string(), "errors": errors.errors}),
+            ServiceError::Throttled(throttled) =>
+                json!({"message": self.to_string(), "retry_after": throttled.retry_after.as_secs()}),
+            _ => json!({"message": self.to_string()}),
+        }
+    }
+}
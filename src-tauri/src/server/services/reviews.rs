@@ -0,0 +1,306 @@
+use chrono::{ DateTime, Datelike, Days, NaiveDate, Utc };
+use serde::{ Deserialize, Serialize };
+use sqlx::{ prelude::FromRow, PgPool };
+use utoipa::ToSchema;
+use super::tasks::Task;
+use super::ServiceError;
+
+/// The span of time a review covers.
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewPeriod {
+    Day,
+    Week,
+}
+
+impl ReviewPeriod {
+    /// Returns the first and last day (inclusive) of the period containing `date`.
+    /// Weeks run Monday to Sunday.
+    fn range(self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            ReviewPeriod::Day => (date, date),
+            ReviewPeriod::Week => {
+                let start = date - Days::new(date.weekday().num_days_from_monday() as u64);
+                (start, start + Days::new(6))
+            }
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            ReviewPeriod::Day => "day",
+            ReviewPeriod::Week => "week",
+        }
+    }
+}
+
+/// Represents the review data received from the client when a review is saved.
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct SaveReview {
+    pub user_email: String,
+    pub period: ReviewPeriod,
+    pub date: NaiveDate,
+    pub notes: String,
+    #[serde(default)]
+    pub carry_over: Vec<i32>, // Unfinished tasks to move forward
+    pub reschedule_to: Option<NaiveDate>, // Defaults to the day after the period ends
+}
+
+/// How consistently a habit was checked off during the reviewed period.
+#[derive(Serialize, ToSchema, Debug, PartialEq)]
+pub struct HabitAdherence {
+    pub habit_id: i32,
+    pub name: String,
+    pub days_checked: i64,
+    pub days_in_period: i64,
+    pub adherence: f64,
+}
+
+#[derive(FromRow)]
+struct HabitCheckins {
+    habit_id: i32,
+    name: String,
+    created_at: DateTime<Utc>,
+    days_checked: i64,
+}
+
+/// Everything gathered for a guided review of one period.
+#[derive(Serialize, ToSchema, Debug)]
+pub struct ReviewSummary {
+    pub period: ReviewPeriod,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub completed: Vec<Task>, // Checked tasks dated within the period
+    pub slipped: Vec<Task>, // Unchecked tasks dated within the period
+    pub overdue: Vec<Task>, // Unchecked tasks dated before the period
+    pub habits: Vec<HabitAdherence>,
+}
+
+/// A review as stored in the database.
+#[derive(FromRow, Debug, Serialize, ToSchema)]
+pub struct StoredReview {
+    pub review_id: i32,
+    pub user_email: String,
+    pub period: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub notes: String,
+    pub completed_count: i32,
+    pub slipped_count: i32,
+    pub overdue_count: i32,
+    pub carried_over: Vec<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A user's stored reviews, newest first.
+pub async fn list_reviews(pool: &PgPool, user_email: &str) -> Result<Vec<StoredReview>, ServiceError> {
+    let reviews = sqlx
+        ::query_as::<_, StoredReview>(
+            "SELECT * FROM reviews WHERE user_email = $1 ORDER BY start_date DESC, review_id DESC"
+        )
+        .bind(user_email)
+        .fetch_all(pool).await?;
+    Ok(reviews)
+}
+
+/// Everything gathered for a guided review of the period containing `date`.
+pub async fn review_summary(
+    pool: &PgPool,
+    user_email: &str,
+    period: ReviewPeriod,
+    date: NaiveDate
+) -> Result<ReviewSummary, ServiceError> {
+    Ok(build_summary(pool, user_email, period, date).await?)
+}
+
+/// Stores a review and carries the selected unfinished tasks over in one transaction.
+pub async fn save_review(
+    pool: &PgPool,
+    user_email: &str,
+    review: &SaveReview
+) -> Result<StoredReview, ServiceError> {
+    match store_review(pool, user_email, review).await {
+        Ok(stored) => Ok(stored),
+        Err(err) => Err(ServiceError::Internal(format!("Failed to store review into database: {}", err))),
+    }
+}
+
+async fn store_review(
+    pool: &PgPool,
+    user_email: &str,
+    review: &SaveReview
+) -> Result<StoredReview, sqlx::Error> {
+    let summary = build_summary(pool, user_email, review.period, review.date).await?;
+    let reschedule_to = review.reschedule_to.unwrap_or(summary.end_date + Days::new(1));
+
+    let mut tx = pool.begin().await?;
+
+    // Only unfinished tasks belonging to the user are moved
+    let carried_over: Vec<i32> = sqlx
+        ::query_scalar(
+            "UPDATE tasks SET due_date = $1
+            WHERE task_id = ANY($2) AND user_email = $3 AND checked = FALSE
+            RETURNING task_id"
+        )
+        .bind(reschedule_to)
+        .bind(&review.carry_over)
+        .bind(user_email)
+        .fetch_all(&mut *tx).await?;
+
+    let stored = sqlx
+        ::query_as::<_, StoredReview>(
+            "INSERT INTO reviews(user_email, period, start_date, end_date, notes,
+                completed_count, slipped_count, overdue_count, carried_over)
+            VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *"
+        )
+        .bind(user_email)
+        .bind(review.period.as_str())
+        .bind(summary.start_date)
+        .bind(summary.end_date)
+        .bind(&review.notes)
+        .bind(summary.completed.len() as i32)
+        .bind(summary.slipped.len() as i32)
+        .bind(summary.overdue.len() as i32)
+        .bind(carried_over)
+        .fetch_one(&mut *tx).await?;
+
+    tx.commit().await?;
+    Ok(stored)
+}
+
+async fn build_summary(
+    pool: &PgPool,
+    user_email: &str,
+    period: ReviewPeriod,
+    date: NaiveDate
+) -> Result<ReviewSummary, sqlx::Error> {
+    let (start_date, end_date) = period.range(date);
+
+    let tasks = sqlx
+        ::query_as::<_, Task>("SELECT * FROM tasks WHERE user_email = $1")
+        .bind(user_email)
+        .fetch_all(pool).await?;
+    let (completed, slipped, overdue) = classify_tasks(tasks, start_date, end_date);
+
+    let checkins = sqlx
+        ::query_as::<_, HabitCheckins>(
+            "SELECT h.habit_id, h.name, h.created_at, COUNT(c.day) AS days_checked
+            FROM habits h
+            LEFT JOIN habit_checkins c ON c.habit_id = h.habit_id AND c.day BETWEEN $2 AND $3
+            WHERE h.user_email = $1
+            GROUP BY h.habit_id
+            ORDER BY h.habit_id"
+        )
+        .bind(user_email)
+        .bind(start_date)
+        .bind(end_date)
+        .fetch_all(pool).await?;
+    let habits = checkins
+        .into_iter()
+        .filter_map(|habit| habit_adherence(habit, start_date, end_date))
+        .collect();
+
+    Ok(ReviewSummary { period, start_date, end_date, completed, slipped, overdue, habits })
+}
+
+/// Splits tasks into completed, slipped and overdue for the given period.
+/// Undated tasks and tasks scheduled after the period are left out.
+fn classify_tasks(
+    tasks: Vec<Task>,
+    start_date: NaiveDate,
+    end_date: NaiveDate
+) -> (Vec<Task>, Vec<Task>, Vec<Task>) {
+    let mut completed = Vec::new();
+    let mut slipped = Vec::new();
+    let mut overdue = Vec::new();
+
+    for task in tasks {
+        let Some(day) = task.due_date else {
+            continue;
+        };
+        let in_period = day >= start_date && day <= end_date;
+        if task.checked && in_period {
+            completed.push(task);
+        } else if !task.checked && in_period {
+            slipped.push(task);
+        } else if !task.checked && day < start_date {
+            overdue.push(task);
+        }
+    }
+
+    (completed, slipped, overdue)
+}
+
+/// Computes adherence over the days a habit existed within the period.
+/// Habits created after the period are skipped.
+fn habit_adherence(
+    habit: HabitCheckins,
+    start_date: NaiveDate,
+    end_date: NaiveDate
+) -> Option<HabitAdherence> {
+    let first_day = start_date.max(habit.created_at.date_naive());
+    if first_day > end_date {
+        return None;
+    }
+    let days_in_period = (end_date - first_day).num_days() + 1;
+
+    Some(HabitAdherence {
+        habit_id: habit.habit_id,
+        name: habit.name,
+        days_checked: habit.days_checked,
+        days_in_period,
+        adherence: (habit.days_checked as f64) / (days_in_period as f64),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(task_id: i32, due_date: Option<NaiveDate>, checked: bool) -> Task {
+        Task {
+            task_id,
+            user_email: "user@email.com".to_string(),
+            title: "Task".to_string(),
+            description: "".to_string(),
+            checked,
+            due_date,
+            due_time: None,
+            duration: None,
+            priority: None,
+            project: None,
+            overdue: false,
+            blocked_by: Vec::new(),
+            blocked: false,
+        }
+    }
+
+    #[test]
+    fn week_runs_monday_to_sunday() {
+        let wednesday = NaiveDate::from_ymd_opt(2024, 3, 20).unwrap();
+        assert_eq!(
+            ReviewPeriod::Week.range(wednesday),
+            (NaiveDate::from_ymd_opt(2024, 3, 18).unwrap(), NaiveDate::from_ymd_opt(2024, 3, 24).unwrap())
+        );
+    }
+
+    #[test]
+    fn classifies_tasks_for_period() {
+        let day = NaiveDate::from_ymd_opt(2024, 3, 20).unwrap();
+        let date = |day| NaiveDate::from_ymd_opt(2024, 3, day);
+        let tasks = vec![
+            task(1, date(20), true),
+            task(2, date(20), false),
+            task(3, date(18), false),
+            task(4, date(18), true),
+            task(5, date(21), false),
+            task(6, None, false)
+        ];
+        let (completed, slipped, overdue) = classify_tasks(tasks, day, day);
+        let ids = |tasks: Vec<Task>| tasks.iter().map(|task| task.task_id).collect::<Vec<_>>();
+        assert_eq!(ids(completed), vec![1]);
+        assert_eq!(ids(slipped), vec![2]);
+        assert_eq!(ids(overdue), vec![3]);
+    }
+}
@@ -0,0 +1,177 @@
+// Sessions and the refresh tokens that keep them going.
+
+use chrono::{ DateTime, Days, Utc };
+use sqlx::{ prelude::FromRow, PgPool, Postgres, Transaction };
+use crate::server::tokens::{ generate_token, hash_token, new_secret_token, CustomClaims };
+use super::auth::ClientInfo;
+
+/// How long a refresh token stays valid. Every refresh issues a new one.
+const REFRESH_TOKEN_DAYS: u64 = 30;
+
+/// A stored refresh token and the state of its session.
+#[derive(FromRow, Debug)]
+struct StoredRefreshToken {
+    session_id: i32,
+    email: String,
+    token_version: i32,
+    expires_at: DateTime<Utc>,
+    rotated_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+/// The token pair handed to the client on login and refresh.
+pub(crate) struct IssuedTokens {
+    pub(crate) session_id: i32,
+    pub(crate) token: String, // Short-lived access token
+    pub(crate) refresh_token: String, // Long-lived, single-use refresh token
+}
+
+/// Why a refresh was refused.
+pub(crate) enum RefreshError {
+    Invalid,
+    Reused,
+    Database(sqlx::Error),
+    Token,
+}
+
+impl From<sqlx::Error> for RefreshError {
+    fn from(err: sqlx::Error) -> Self {
+        RefreshError::Database(err)
+    }
+}
+
+/// Revokes a session and the access token it was ended with.
+pub(crate) async fn revoke_current(
+    pool: &PgPool,
+    claims: &CustomClaims,
+    expires_at: DateTime<Utc>
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx
+        ::query("UPDATE sessions SET revoked_at = NOW() WHERE session_id = $1 AND revoked_at IS NULL")
+        .bind(claims.session_id)
+        .execute(&mut *tx).await?;
+    sqlx
+        ::query("INSERT INTO revoked_tokens(jti, expires_at) VALUES($1, $2) ON CONFLICT DO NOTHING")
+        .bind(&claims.jti)
+        .bind(expires_at)
+        .execute(&mut *tx).await?;
+    tx.commit().await
+}
+
+/// Revokes every session of a user and returns their new token version.
+pub(crate) async fn revoke_all_sessions(pool: &PgPool, email: &str) -> Result<i32, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let version: i32 = sqlx
+        ::query_scalar(
+            "UPDATE users SET token_version = token_version + 1 WHERE email = $1 RETURNING token_version"
+        )
+        .bind(email)
+        .fetch_one(&mut *tx).await?;
+    sqlx
+        ::query("UPDATE sessions SET revoked_at = NOW() WHERE email = $1 AND revoked_at IS NULL")
+        .bind(email)
+        .execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(version)
+}
+
+/// Starts a new session for a user who just proved who they are, and issues its first tokens.
+pub(crate) async fn start_session(
+    pool: &PgPool,
+    email: &str,
+    client: &ClientInfo
+) -> Result<IssuedTokens, anyhow::Error> {
+    let mut tx = pool.begin().await?;
+
+    let token_version: i32 = sqlx
+        ::query_scalar("SELECT token_version FROM users WHERE email = $1")
+        .bind(email)
+        .fetch_one(&mut *tx).await?;
+    let session_id: i32 = sqlx
+        ::query_scalar("INSERT INTO sessions(email, device, ip) VALUES($1, $2, $3) RETURNING session_id")
+        .bind(email)
+        .bind(&client.device)
+        .bind(&client.ip)
+        .fetch_one(&mut *tx).await?;
+    let refresh_token = insert_refresh_token(&mut tx, session_id).await?;
+
+    tx.commit().await?;
+    Ok(IssuedTokens {
+        session_id,
+        token: generate_token(email, session_id, token_version)?,
+        refresh_token,
+    })
+}
+
+/// Swaps a refresh token for a new token pair, revoking the session if it was already swapped.
+pub(crate) async fn rotate_refresh_token(
+    pool: &PgPool,
+    refresh_token: &str,
+    ip: Option<String>
+) -> Result<IssuedTokens, RefreshError> {
+    let mut tx = pool.begin().await?;
+
+    // Lock the token so two concurrent refreshes cannot both rotate it
+    let stored = sqlx
+        ::query_as::<_, StoredRefreshToken>(
+            "SELECT r.session_id, s.email, u.token_version, r.expires_at, r.rotated_at, s.revoked_at
+            FROM refresh_tokens r
+            JOIN sessions s ON s.session_id = r.session_id
+            JOIN users u ON u.email = s.email
+            WHERE r.token_hash = $1
+            FOR UPDATE OF r, s"
+        )
+        .bind(hash_token(refresh_token))
+        .fetch_optional(&mut *tx).await?;
+
+    let Some(stored) = stored else {
+        return Err(RefreshError::Invalid);
+    };
+    if stored.revoked_at.is_some() || stored.expires_at <= Utc::now() {
+        return Err(RefreshError::Invalid);
+    }
+    if stored.rotated_at.is_some() {
+        // Someone is replaying an old token, so the whole family is no longer trustworthy
+        sqlx
+            ::query("UPDATE sessions SET revoked_at = NOW() WHERE session_id = $1")
+            .bind(stored.session_id)
+            .execute(&mut *tx).await?;
+        tx.commit().await?;
+        return Err(RefreshError::Reused);
+    }
+
+    sqlx
+        ::query("UPDATE refresh_tokens SET rotated_at = NOW() WHERE token_hash = $1")
+        .bind(hash_token(refresh_token))
+        .execute(&mut *tx).await?;
+    sqlx
+        ::query("UPDATE sessions SET last_used_at = NOW(), ip = COALESCE($2, ip) WHERE session_id = $1")
+        .bind(stored.session_id)
+        .bind(ip)
+        .execute(&mut *tx).await?;
+    let refresh_token = insert_refresh_token(&mut tx, stored.session_id).await?;
+
+    tx.commit().await?;
+    let token = generate_token(&stored.email, stored.session_id, stored.token_version).map_err(
+        |_| RefreshError::Token
+    )?;
+    Ok(IssuedTokens { session_id: stored.session_id, token, refresh_token })
+}
+
+/// Stores a fresh refresh token for a session and returns it in plain text.
+async fn insert_refresh_token(
+    tx: &mut Transaction<'_, Postgres>,
+    session_id: i32
+) -> Result<String, sqlx::Error> {
+    let refresh_token = new_secret_token();
+    let expires_at = Utc::now() + Days::new(REFRESH_TOKEN_DAYS);
+
+    sqlx
+        ::query("INSERT INTO refresh_tokens(token_hash, session_id, expires_at) VALUES($1, $2, $3)")
+        .bind(hash_token(&refresh_token))
+        .bind(session_id)
+        .bind(expires_at)
+        .execute(&mut **tx).await?;
+    Ok(refresh_token)
+}
@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+
+use chrono::{ DateTime, NaiveDate, NaiveTime, Utc };
+use chrono_tz::Tz;
+use serde::{ Deserialize, Serialize };
+use sqlx::{ prelude::FromRow, PgPool };
+use utoipa::ToSchema;
+use crate::server::{ deadline, dependencies };
+use super::users::user_timezone;
+use super::account::require_verified;
+use crate::server::validation::{ Checker, Field, Kind, Rule, Schema, Validate, ValidationErrors };
+use super::ServiceError;
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct AddTask {
+    pub user_email: String,
+    pub title: String,
+    pub description: String,
+    pub due_date: Option<NaiveDate>, // YYYY-MM-DD
+    pub due_time: Option<NaiveTime>, // HH:MM in the user's timezone, requires due_date
+    pub duration: Option<i32>,
+    pub priority: Option<i32>,
+    pub project: Option<String>,
+}
+
+/// Rules for new tasks, also served as JSON Schema.
+pub(crate) const ADD_TASK_SCHEMA: Schema = Schema {
+    name: "AddTask",
+    fields: &[
+        Field { name: "user_email", kind: Kind::String, required: true, rules: &[Rule::Email] },
+        Field {
+            name: "title",
+            kind: Kind::String,
+            required: true,
+            rules: &[Rule::NotBlank, Rule::Length { min: 1, max: 200 }],
+        },
+        Field {
+            name: "description",
+            kind: Kind::String,
+            required: true,
+            rules: &[Rule::Length { min: 0, max: 5000 }],
+        },
+        Field { name: "due_date", kind: Kind::Date, required: false, rules: &[] },
+        Field { name: "due_time", kind: Kind::Time, required: false, rules: &[Rule::Requires("due_date")] },
+        Field {
+            name: "duration",
+            kind: Kind::Integer,
+            required: false,
+            rules: &[Rule::Range { min: 1, max: 1440 }], // Minutes, at most a day
+        },
+        Field {
+            name: "priority",
+            kind: Kind::Integer,
+            required: false,
+            rules: &[Rule::Range { min: 1, max: 5 }], // 1 is the most important
+        },
+        Field {
+            name: "project",
+            kind: Kind::String,
+            required: false,
+            rules: &[Rule::NotBlank, Rule::Length { min: 1, max: 100 }],
+        },
+    ],
+};
+
+impl Validate for AddTask {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        Checker::new(&ADD_TASK_SCHEMA)
+            .text("user_email", Some(&self.user_email))
+            .text("title", Some(&self.title))
+            .text("description", Some(&self.description))
+            .set("due_date", self.due_date.is_some())
+            .set("due_time", self.due_time.is_some())
+            .integer("duration", self.duration.map(i64::from))
+            .integer("priority", self.priority.map(i64::from))
+            .text("project", self.project.as_deref())
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct CheckedTask {
+    pub task_id: i32,
+    pub checked: bool,
+    pub unblocked: Vec<i32>, // Dependents with no unfinished blockers left
+}
+
+/// A project's tasks in an order that respects their blocked-by links.
+#[derive(Serialize, ToSchema, Debug)]
+pub struct TaskOrder {
+    pub order: Vec<Task>,
+    pub cyclic: Vec<Task>, // Tasks in or waiting on a dependency cycle
+}
+
+/// A blocked-by link, with whether the blocker is finished.
+#[derive(FromRow, Debug)]
+pub(crate) struct Dependency {
+    pub(crate) task_id: i32,
+    pub(crate) blocked_by: i32,
+    pub(crate) blocker_checked: bool,
+}
+
+#[derive(FromRow, Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Task {
+    pub task_id: i32,
+    pub user_email: String,
+    pub title: String,
+    pub description: String,
+    pub checked: bool,
+    pub due_date: Option<NaiveDate>,
+    pub due_time: Option<NaiveTime>,
+    pub duration: Option<i32>,
+    pub priority: Option<i32>,
+    pub project: Option<String>,
+    #[sqlx(skip)]
+    pub overdue: bool, // Computed against the user's timezone when the task is fetched
+    #[sqlx(skip)]
+    pub blocked_by: Vec<i32>, // Every task this one is linked to as blocked by
+    #[sqlx(skip)]
+    pub blocked: bool, // At least one blocker is unfinished
+}
+
+impl Task {
+    /// Whether the task is unfinished and its deadline has passed for a user in `tz`.
+    pub(crate) fn is_overdue(&self, tz: Tz, now: DateTime<Utc>) -> bool {
+        match self.due_date {
+            Some(date) if !self.checked => deadline::is_overdue(date, self.due_time, tz, now),
+            _ => false,
+        }
+    }
+}
+
+impl From<&Task> for AddTask {
+    /// The task's current fields, as a starting point for editing it.
+    fn from(task: &Task) -> Self {
+        AddTask {
+            user_email: task.user_email.clone(),
+            title: task.title.clone(),
+            description: task.description.clone(),
+            due_date: task.due_date,
+            due_time: task.due_time,
+            duration: task.duration,
+            priority: task.priority,
+            project: task.project.clone(),
+        }
+    }
+}
+
+/// Loads every blocked-by link between a user's tasks.
+pub(crate) async fn load_dependencies(
+    pool: &PgPool,
+    user_email: &str
+) -> Result<Vec<Dependency>, sqlx::Error> {
+    sqlx
+        ::query_as::<_, Dependency>(
+            "SELECT d.task_id, d.blocked_by, b.checked AS blocker_checked
+            FROM task_dependencies d
+            JOIN tasks t ON t.task_id = d.task_id
+            JOIN tasks b ON b.task_id = d.blocked_by
+            WHERE t.user_email = $1"
+        )
+        .bind(user_email)
+        .fetch_all(pool).await
+}
+
+/// Fills in `blocked_by` and `blocked` from the user's links.
+fn flag_blocked(tasks: &mut [Task], dependencies: &[Dependency]) {
+    for task in tasks.iter_mut() {
+        for dependency in dependencies.iter().filter(|d| d.task_id == task.task_id) {
+            task.blocked_by.push(dependency.blocked_by);
+            task.blocked |= !dependency.blocker_checked;
+        }
+    }
+}
+
+/// A user's tasks, flagged as overdue or blocked.
+pub async fn list_tasks(
+    pool: &PgPool,
+    user_email: &str,
+    hide_blocked: bool // Leave out tasks waiting on an unfinished blocker
+) -> Result<Vec<Task>, ServiceError> {
+    let tz = user_timezone(pool, user_email).await?;
+    let dependencies = load_dependencies(pool, user_email).await?;
+    let mut tasks = sqlx
+        ::query_as::<_, Task>("SELECT * FROM tasks WHERE user_email = $1")
+        .bind(user_email)
+        .fetch_all(pool).await?;
+
+    let now = Utc::now();
+    for task in tasks.iter_mut() {
+        task.overdue = task.is_overdue(tz, now);
+    }
+    flag_blocked(&mut tasks, &dependencies);
+    if hide_blocked {
+        tasks.retain(|task| !task.blocked);
+    }
+    Ok(tasks)
+}
+
+/// A project's tasks with every blocker before the tasks it blocks.
+/// Tasks without a project are ordered when `project` is `None`.
+pub async fn task_order(
+    pool: &PgPool,
+    user_email: &str,
+    project: Option<&str>
+) -> Result<TaskOrder, ServiceError> {
+    let dependencies = load_dependencies(pool, user_email).await?;
+    let mut tasks = sqlx
+        ::query_as::<_, Task>(
+            "SELECT * FROM tasks WHERE user_email = $1 AND project IS NOT DISTINCT FROM $2"
+        )
+        .bind(user_email)
+        .bind(project)
+        .fetch_all(pool).await?;
+    flag_blocked(&mut tasks, &dependencies);
+
+    let ids: Vec<i32> = tasks.iter().map(|task| task.task_id).collect();
+    let edges: Vec<(i32, i32)> = dependencies
+        .iter()
+        .map(|d| (d.task_id, d.blocked_by))
+        .collect();
+    let priorities: HashMap<i32, i32> = tasks
+        .iter()
+        .map(|task| (task.task_id, task.priority.unwrap_or(i32::MAX)))
+        .collect();
+    let (order, cyclic) = dependencies::topological_order(&ids, &edges, |id| priorities[&id]);
+
+    let mut by_id: HashMap<i32, Task> = tasks
+        .into_iter()
+        .map(|task| (task.task_id, task))
+        .collect();
+    let mut take = |ids: Vec<i32>| -> Vec<Task> {
+        ids.into_iter().filter_map(|id| by_id.remove(&id)).collect()
+    };
+    let order = take(order);
+    let cyclic = take(cyclic);
+    Ok(TaskOrder { order, cyclic })
+}
+
+/// Checks off (or reopens) one of the user's tasks.
+/// Checking off a blocker reports the dependents it unblocked.
+pub async fn check_task(
+    pool: &PgPool,
+    user_email: &str,
+    task_id: i32,
+    checked: bool
+) -> Result<CheckedTask, ServiceError> {
+    let done = sqlx
+        ::query("UPDATE tasks SET checked = $1 WHERE task_id = $2 AND user_email = $3")
+        .bind(checked)
+        .bind(task_id)
+        .bind(user_email)
+        .execute(pool).await?;
+    if done.rows_affected() == 0 {
+        return Err(ServiceError::NotFound("Task not found"));
+    }
+
+    let mut unblocked = Vec::new();
+    if checked {
+        unblocked = sqlx
+            ::query_scalar(
+                "SELECT d.task_id FROM task_dependencies d
+                WHERE d.blocked_by = $1 AND NOT EXISTS (
+                    SELECT 1 FROM task_dependencies other
+                    JOIN tasks blocker ON blocker.task_id = other.blocked_by
+                    WHERE other.task_id = d.task_id AND blocker.checked = FALSE
+                )
+                ORDER BY d.task_id"
+            )
+            .bind(task_id)
+            .fetch_all(pool).await?;
+    }
+
+    Ok(CheckedTask { task_id, checked, unblocked })
+}
+
+/// Validates and stores a new task for the user, once they have verified their email address.
+pub async fn create_task(pool: &PgPool, user_email: &str, task: &AddTask) -> Result<(), ServiceError> {
+    if !task.user_email.eq_ignore_ascii_case(user_email) {
+        return Err(ServiceError::Forbidden("You can only access your own data"));
+    }
+    // Reject the payload with every invalid field listed
+    task.validate()?;
+    require_verified(pool, user_email).await?;
+
+    // Store task in database
+    let result = sqlx
+        ::query(
+            "INSERT INTO tasks(user_email, title, description, checked, due_date, due_time, duration, priority, project)
+        VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9)"
+        )
+        .bind(user_email)
+        .bind(&task.title)
+        .bind(&task.description)
+        .bind(false) // Assuming checked is always false initially
+        .bind(task.due_date)
+        .bind(task.due_time)
+        .bind(task.duration)
+        .bind(task.priority)
+        .bind(&task.project)
+        .execute(pool).await;
+
+    if let Err(err) = result {
+        return Err(ServiceError::Internal(format!("Failed to store task into database: {}", err)));
+    }
+    Ok(())
+}
+
+/// Validates and replaces the fields of one of the user's tasks.
+/// Whether the task is checked off is left as it was.
+pub async fn update_task(
+    pool: &PgPool,
+    user_email: &str,
+    task_id: i32,
+    task: &AddTask
+) -> Result<(), ServiceError> {
+    if !task.user_email.eq_ignore_ascii_case(user_email) {
+        return Err(ServiceError::Forbidden("You can only access your own data"));
+    }
+    task.validate()?;
+
+    let done = sqlx
+        ::query(
+            "UPDATE tasks SET title = $1, description = $2, due_date = $3, due_time = $4,
+            duration = $5, priority = $6, project = $7
+            WHERE task_id = $8 AND user_email = $9"
+        )
+        .bind(&task.title)
+        .bind(&task.description)
+        .bind(task.due_date)
+        .bind(task.due_time)
+        .bind(task.duration)
+        .bind(task.priority)
+        .bind(&task.project)
+        .bind(task_id)
+        .bind(user_email)
+        .execute(pool).await?;
+    if done.rows_affected() == 0 {
+        return Err(ServiceError::NotFound("Task not found"));
+    }
+    Ok(())
+}
+
+/// Deletes one of the user's tasks.
+pub async fn delete_task(pool: &PgPool, user_email: &str, task_id: i32) -> Result<(), ServiceError> {
+    let done = sqlx
+        ::query("DELETE FROM tasks WHERE task_id = $1 AND user_email = $2")
+        .bind(task_id)
+        .bind(user_email)
+        .execute(pool).await?;
+    if done.rows_affected() == 0 {
+        return Err(ServiceError::NotFound("Task not found"));
+    }
+    Ok(())
+}
@@ -0,0 +1,133 @@
+// The second login step for accounts with two-factor authentication.
+
+use chrono::{ Duration, Utc };
+use sqlx::{ prelude::FromRow, PgPool };
+use crate::server::tokens::{ hash_token, new_secret_token };
+use crate::server::totp;
+
+/// How long the second login step may take.
+const CHALLENGE_MINUTES: i64 = 5;
+
+/// Wrong codes allowed per challenge before the password has to be entered again.
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+
+/// A pending challenge with what is needed to check it.
+#[derive(FromRow, Debug)]
+struct Challenge {
+    email: String,
+    username: String,
+    attempts: i32,
+    totp_secret: Option<String>,
+    totp_last_step: Option<i64>,
+}
+
+/// Outcome of the second login step.
+pub(crate) enum SecondFactor {
+    Passed {
+        email: String,
+        username: String,
+    },
+    Failed {
+        email: String,
+    },
+    UnknownChallenge,
+}
+
+/// Issues a challenge token if the user has 2FA enabled, meaning login needs a second step.
+pub(crate) async fn start_challenge(pool: &PgPool, email: &str) -> Result<Option<String>, sqlx::Error> {
+    let enabled: bool = sqlx
+        ::query_scalar("SELECT totp_enabled_at IS NOT NULL FROM users WHERE email = $1")
+        .bind(email)
+        .fetch_one(pool).await?;
+    if !enabled {
+        return Ok(None);
+    }
+
+    let token = new_secret_token();
+    let expires_at = Utc::now() + Duration::try_minutes(CHALLENGE_MINUTES).unwrap();
+    sqlx
+        ::query("INSERT INTO login_challenges(token_hash, email, expires_at) VALUES($1, $2, $3)")
+        .bind(hash_token(&token))
+        .bind(email)
+        .bind(expires_at)
+        .execute(pool).await?;
+    Ok(Some(token))
+}
+
+/// Checks the second login step, counting wrong codes against the challenge.
+pub(crate) async fn check_second_factor(
+    pool: &PgPool,
+    challenge_token: &str,
+    code: Option<&str>,
+    recovery_code: Option<&str>
+) -> Result<SecondFactor, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Lock the challenge and the user so concurrent guesses are counted and a code is only used once
+    let challenge = sqlx
+        ::query_as::<_, Challenge>(
+            "SELECT c.email, u.username, c.attempts, u.totp_secret, u.totp_last_step
+            FROM login_challenges c
+            JOIN users u ON u.email = c.email
+            WHERE c.token_hash = $1 AND c.expires_at > NOW() AND u.totp_enabled_at IS NOT NULL
+            FOR UPDATE OF c, u"
+        )
+        .bind(hash_token(challenge_token))
+        .fetch_optional(&mut *tx).await?;
+    let Some(challenge) = challenge else {
+        return Ok(SecondFactor::UnknownChallenge);
+    };
+
+    let passed = match (code, recovery_code, &challenge.totp_secret) {
+        (Some(code), _, Some(secret)) => {
+            let step = totp::verify(secret, code, Utc::now().timestamp(), challenge.totp_last_step);
+            if let Some(step) = step {
+                sqlx
+                    ::query("UPDATE users SET totp_last_step = $1 WHERE email = $2")
+                    .bind(step)
+                    .bind(&challenge.email)
+                    .execute(&mut *tx).await?;
+            }
+            step.is_some()
+        }
+        (None, Some(recovery_code), _) => {
+            let used = sqlx
+                ::query(
+                    "UPDATE recovery_codes SET used_at = NOW()
+                    WHERE email = $1 AND code_hash = $2 AND used_at IS NULL"
+                )
+                .bind(&challenge.email)
+                .bind(hash_recovery_code(recovery_code))
+                .execute(&mut *tx).await?;
+            used.rows_affected() == 1
+        }
+        _ => false,
+    };
+
+    let token_hash = hash_token(challenge_token);
+    if passed || challenge.attempts + 1 >= MAX_CHALLENGE_ATTEMPTS {
+        sqlx::query("DELETE FROM login_challenges WHERE token_hash = $1").bind(&token_hash).execute(&mut *tx).await?;
+    } else {
+        sqlx
+            ::query("UPDATE login_challenges SET attempts = attempts + 1 WHERE token_hash = $1")
+            .bind(&token_hash)
+            .execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+
+    Ok(if passed {
+        SecondFactor::Passed { email: challenge.email, username: challenge.username }
+    } else {
+        SecondFactor::Failed { email: challenge.email }
+    })
+}
+
+/// Hashes a recovery code, ignoring dashes, spaces and case as users may type it either way.
+pub(crate) fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    hash_token(&normalized)
+}
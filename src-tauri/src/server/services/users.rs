@@ -0,0 +1,79 @@
+// What every account has: a hashed password, a timezone and the rules its fields follow.
+
+use std::sync::OnceLock;
+
+use argon2::{
+    password_hash::{ rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString },
+    Argon2,
+};
+use chrono_tz::Tz;
+use sqlx::PgPool;
+
+use crate::server::deadline;
+use crate::server::tokens::new_secret_token;
+use crate::server::validation::Rule;
+
+/// Rules every new password has to follow.
+pub(crate) const PASSWORD_RULES: &[Rule] = &[Rule::Length { min: 8, max: 128 }, Rule::Password];
+
+/// Rules every username has to follow.
+pub(crate) const USERNAME_RULES: &[Rule] = &[Rule::NotBlank, Rule::Length { min: 2, max: 50 }];
+
+/// Rules every email address has to follow.
+pub(crate) const EMAIL_RULES: &[Rule] = &[Rule::Email, Rule::Length { min: 3, max: 254 }];
+
+/// Looks up the timezone a user's deadlines are evaluated in.
+/// Falls back to UTC for unknown users.
+pub(crate) async fn user_timezone(pool: &PgPool, email: &str) -> Result<Tz, sqlx::Error> {
+    let timezone: Option<String> = sqlx
+        ::query_scalar("SELECT timezone FROM users WHERE email = $1")
+        .bind(email)
+        .fetch_optional(pool).await?;
+
+    Ok(timezone.and_then(|name| deadline::parse_timezone(&name)).unwrap_or(Tz::UTC))
+}
+
+/// Hashes the user's password using Argon2 algorithm.
+pub(crate) fn hash_user_password(password: String) -> String {
+    // Convert password to bytes
+    let password = password.as_bytes();
+    // Generate a random salt
+    let salt = SaltString::generate(&mut OsRng);
+
+    // Create an Argon2 password hasher
+    let argon2 = Argon2::default();
+
+    // Hash the password
+    let password_hash = argon2.hash_password(password, &salt).unwrap().to_string();
+
+    password_hash
+}
+
+/// A hash of a random password, checked against when no user matches a login
+/// so that Argon2 runs either way.
+pub(crate) fn dummy_password_hash() -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| hash_user_password(new_secret_token()))
+}
+
+/// Verifies whether the provided password matches the hashed password.
+pub(crate) fn verify_password(password: String, hashed_password: String) -> bool {
+    // Convert password to bytes
+    let password = password.as_bytes();
+    // Parse the hashed password
+    let parsed_hash = PasswordHash::new(&hashed_password).unwrap();
+    // Verify the password
+    Argon2::default().verify_password(password, &parsed_hash).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn verify_one_password() {
+        let password = "password".to_string();
+        let hashed_password =
+            "$argon2id$v=19$m=19456,t=2,p=1$mK1zp767ZDsSClJ8HP+qtw$uJdh3qZK9UKyNzL4kO1JSEA8mw0KoQ6YZ+oAId7PmY4".to_string();
+        assert!(verify_password(password, hashed_password));
+    }
+}
@@ -0,0 +1,199 @@
+// Brute-force protection for the authentication endpoints.
+// Failed attempts are counted per client IP (by middleware, from the response status) and
+// per account (by the login handler). After a few free failures each further one doubles
+// the wait before the next attempt, up to a lockout of `MAX_DELAY`.
+
+use std::{ collections::HashMap, future::Future, sync::Mutex, time::{ Duration, Instant } };
+
+use actix_web::{
+    dev::{ Service, ServiceRequest, ServiceResponse },
+    http::StatusCode,
+    web,
+    Error,
+    HttpResponse,
+    ResponseError,
+};
+
+use crate::server::TauriAppState;
+
+/// Failures allowed before any delay applies.
+const FREE_FAILURES: u32 = 5;
+
+/// Delay after the first failure beyond the free ones; doubles with each further failure.
+const BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest lockout.
+const MAX_DELAY: Duration = Duration::from_secs(15 * 60);
+
+/// Failures are forgotten after this long without a new one.
+const FORGET_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Tracked keys before stale ones are pruned.
+const PRUNE_ABOVE: usize = 10_000;
+
+/// Paths where failed requests count against the client's IP.
+const THROTTLED_PATHS: [&str; 8] = [
+    "/auth/login",
+    "/auth/2fa/verify",
+    "/auth/2fa/disable",
+    "/auth/password-reset/confirm",
+    "/auth/verify-email",
+    "/account/password",
+    "/account/email",
+    "/account/delete",
+];
+
+struct Attempts {
+    failures: u32,
+    last_failure: Instant,
+}
+
+/// Exponential backoff on failures, per key.
+#[derive(Default)]
+pub struct Throttle {
+    attempts: Mutex<HashMap<String, Attempts>>,
+}
+
+impl Throttle {
+    /// How long the key still has to wait before its next attempt, if at all.
+    pub fn locked_for(&self, key: &str, now: Instant) -> Option<Duration> {
+        let attempts = self.attempts.lock().unwrap();
+        let attempts = attempts.get(key)?;
+        if now.duration_since(attempts.last_failure) > FORGET_AFTER || attempts.failures < FREE_FAILURES {
+            return None;
+        }
+        let until = attempts.last_failure + delay_after(attempts.failures);
+        (until > now).then(|| until - now)
+    }
+
+    pub fn record_failure(&self, key: &str, now: Instant) {
+        let mut attempts = self.attempts.lock().unwrap();
+        if attempts.len() > PRUNE_ABOVE {
+            attempts.retain(|_, attempts| now.duration_since(attempts.last_failure) <= FORGET_AFTER);
+        }
+        let entry = attempts
+            .entry(key.to_string())
+            .or_insert(Attempts { failures: 0, last_failure: now });
+        if now.duration_since(entry.last_failure) > FORGET_AFTER {
+            entry.failures = 0;
+        }
+        entry.failures += 1;
+        entry.last_failure = now;
+    }
+
+    pub fn record_success(&self, key: &str) {
+        self.attempts.lock().unwrap().remove(key);
+    }
+}
+
+fn delay_after(failures: u32) -> Duration {
+    let doublings = failures.saturating_sub(FREE_FAILURES).min(20);
+    (BASE_DELAY * (1 << doublings)).min(MAX_DELAY)
+}
+
+/// Throttles for the authentication endpoints.
+#[derive(Default)]
+pub struct AuthThrottle {
+    pub ips: Throttle,
+    pub accounts: Throttle, // Keyed by the submitted email, whether or not the account exists
+}
+
+/// Rejection for a client or account that has to wait.
+#[derive(Debug, thiserror::Error)]
+#[error("Too many failed attempts, please try again later")]
+pub struct Throttled {
+    pub retry_after: Duration,
+}
+
+impl ResponseError for Throttled {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::TOO_MANY_REQUESTS
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::TooManyRequests()
+            .insert_header(("Retry-After", self.retry_after.as_secs().max(1).to_string()))
+            .json(self.to_string())
+    }
+}
+
+/// Middleware counting failed requests to `THROTTLED_PATHS` per client IP, for `App::wrap_fn`.
+/// The peer address is used rather than forwarding headers, which clients can forge.
+pub fn limit_by_ip<S, B>(
+    req: ServiceRequest,
+    srv: &S
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>> + 'static
+    where S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>, S::Future: 'static
+{
+    let tracked = THROTTLED_PATHS.contains(&req.path())
+        .then(|| {
+            let state = req.app_data::<web::Data<TauriAppState>>()?.clone();
+            let ip = req.peer_addr()?.ip().to_string();
+            Some((state, ip))
+        })
+        .flatten();
+
+    let locked_for = tracked
+        .as_ref()
+        .and_then(|(state, ip)| state.throttle.ips.locked_for(ip, Instant::now()));
+    let response = locked_for.is_none().then(|| srv.call(req));
+
+    async move {
+        if let Some(retry_after) = locked_for {
+            return Err(Throttled { retry_after }.into());
+        }
+        let response = response.unwrap().await?;
+        if let Some((state, ip)) = tracked {
+            let status = response.status();
+            if
+                status == StatusCode::UNAUTHORIZED ||
+                status == StatusCode::FORBIDDEN ||
+                status == StatusCode::BAD_REQUEST
+            {
+                state.throttle.ips.record_failure(&ip, Instant::now());
+            }
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially_after_free_failures() {
+        let throttle = Throttle::default();
+        let start = Instant::now();
+
+        for _ in 0..FREE_FAILURES - 1 {
+            throttle.record_failure("1.2.3.4", start);
+        }
+        assert_eq!(throttle.locked_for("1.2.3.4", start), None);
+
+        throttle.record_failure("1.2.3.4", start);
+        assert_eq!(throttle.locked_for("1.2.3.4", start), Some(BASE_DELAY));
+        throttle.record_failure("1.2.3.4", start);
+        assert_eq!(throttle.locked_for("1.2.3.4", start), Some(BASE_DELAY * 2));
+        assert_eq!(throttle.locked_for("1.2.3.4", start + BASE_DELAY * 2), None);
+        assert_eq!(throttle.locked_for("5.6.7.8", start), None);
+    }
+
+    #[test]
+    fn caps_lockout_and_forgets_old_failures() {
+        let throttle = Throttle::default();
+        let start = Instant::now();
+        for _ in 0..40 {
+            throttle.record_failure("user@email.com", start);
+        }
+        assert_eq!(throttle.locked_for("user@email.com", start), Some(MAX_DELAY));
+
+        let later = start + FORGET_AFTER + Duration::from_secs(1);
+        assert_eq!(throttle.locked_for("user@email.com", later), None);
+        throttle.record_failure("user@email.com", later);
+        assert_eq!(throttle.locked_for("user@email.com", later), None);
+
+        throttle.record_success("user@email.com");
+        assert_eq!(throttle.locked_for("user@email.com", later), None);
+    }
+}
@@ -0,0 +1,88 @@
+// Signed access tokens and the random opaque tokens stored hashed: refresh tokens,
+// personal access tokens and the ones mailed in links.
+
+use argon2::password_hash::rand_core::{ OsRng, RngCore };
+use chrono::{ Duration, Utc };
+use jwt_compact::{ prelude::*, Token };
+use serde::{ Deserialize, Serialize };
+use sha2::{ Digest, Sha256 };
+
+use crate::server::keyring::keyring;
+
+/// How long an access token is valid for.
+pub const ACCESS_TOKEN_HOURS: i64 = 1;
+
+/// Function to verify a JWT token.
+pub fn verify_token(token_string: &str) -> Result<Token<CustomClaims>, anyhow::Error> {
+    // Validate the token integrity with the key named by its `kid` header.
+    // Unknown or retired keys are an error, which callers turn into a 401.
+    let token: Token<CustomClaims> = keyring()?.verify(token_string)?;
+    // Validate additional conditions.
+    token.claims().validate_expiration(&TimeOptions::default())?;
+    Ok(token)
+}
+
+/// Prefix telling personal access tokens apart from JWTs.
+pub const ACCESS_TOKEN_PREFIX: &str = "kzp_";
+
+/// Whether a bearer token is a personal access token rather than a JWT.
+pub fn is_access_token(token: &str) -> bool {
+    token.starts_with(ACCESS_TOKEN_PREFIX)
+}
+
+/// Function to generate a short-lived JWT access token for a session.
+/// `token_version` is the user's current version; bumping it invalidates every older token.
+pub fn generate_token(
+    email: &str,
+    session_id: i32,
+    token_version: i32
+) -> Result<String, anyhow::Error> {
+    // Choose time-related options for token creation / validation.
+    let time_options = TimeOptions::default();
+    let custom_claims = CustomClaims {
+        email: email.to_owned(),
+        session_id,
+        jti: new_secret_token(), // Unique id, so this one token can be revoked
+        token_version,
+    };
+    let claims = Claims::new(custom_claims) // Create claims with email
+        .set_duration_and_issuance(&time_options, Duration::try_hours(ACCESS_TOKEN_HOURS).unwrap()) // Set token expiration time
+        .set_not_before(Utc::now()); // Set token not before time
+    let token = keyring()?.sign(&claims)?; // Sign with the active key, named in the header
+    Ok(token) // Return generated token
+}
+
+/// Generates a random opaque token, e.g. a refresh token, as 64 hex characters.
+pub fn new_secret_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Generates a new personal access token.
+pub fn new_access_token() -> String {
+    format!("{ACCESS_TOKEN_PREFIX}{}", new_secret_token())
+}
+
+/// Hashes an opaque token for storage. The tokens are random, so a fast hash is enough.
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Custom claims encoded in the token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomClaims {
+    #[serde(rename = "email")]
+    pub email: String, // User's email
+    #[serde(rename = "sid")]
+    pub session_id: i32, // Session the token was issued for
+    pub jti: String, // Token id, checked against the revocation list
+    #[serde(rename = "ver")]
+    pub token_version: i32, // User's token version when the token was issued
+}
@@ -0,0 +1,428 @@
+// Declarative validation rules for request payloads.
+// A payload's rules are written once as a `Schema`; the same rules check incoming
+// requests and are published as JSON Schema so the frontend can enforce them too.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use actix_web::error::{ InternalError, JsonPayloadError };
+use actix_web::{ web, HttpResponse };
+use serde::Serialize;
+use serde_json::{ json, Map, Value };
+use utoipa::ToSchema;
+
+use crate::server::deadline;
+use crate::server::responses::InvalidResponse;
+
+/// The JSON type of a field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    String,
+    Integer,
+    Date, // YYYY-MM-DD
+    Time, // HH:MM:SS
+}
+
+/// A constraint on a field's value. Absent optional fields are not checked.
+#[derive(Debug, Clone, Copy)]
+pub enum Rule {
+    NotBlank,
+    Length {
+        min: usize,
+        max: usize,
+    }, // In characters
+    Range {
+        min: i64,
+        max: i64,
+    },
+    Email,
+    Password, // At least one letter and one digit
+    Timezone, // IANA name, e.g. "Europe/Berlin"
+    Requires(&'static str), // Only allowed when the named field is set too
+}
+
+#[derive(Debug)]
+pub struct Field {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub required: bool,
+    pub rules: &'static [Rule],
+}
+
+/// The rules for one payload type.
+#[derive(Debug)]
+pub struct Schema {
+    pub name: &'static str,
+    pub fields: &'static [Field],
+}
+
+/// One broken rule.
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub code: &'static str, // Stable identifier clients can match on
+    pub message: String,
+}
+
+/// Every broken rule of a payload, sent back as a 422.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    pub fn into_response(self) -> HttpResponse {
+        HttpResponse::UnprocessableEntity().json(InvalidResponse {
+            message: "Some fields are invalid".to_string(),
+            errors: self.errors,
+        })
+    }
+}
+
+/// Extractor settings that answer bodies which are not JSON of the expected shape with a 422,
+/// listing the problem like a broken rule, instead of actix's plain-text 400.
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|err, _req| match err {
+        JsonPayloadError::Overflow { .. } | JsonPayloadError::OverflowKnownLength { .. } => err.into(),
+        err => malformed("body", err),
+    })
+}
+
+/// Same for query strings that do not fit their type.
+pub fn query_config() -> web::QueryConfig {
+    web::QueryConfig::default().error_handler(|err, _req| malformed("query", err))
+}
+
+fn malformed<E: fmt::Debug + fmt::Display + 'static>(field: &'static str, err: E) -> actix_web::Error {
+    let response = (ValidationErrors {
+        errors: vec![FieldError { field, code: "malformed", message: err.to_string() }],
+    }).into_response();
+    InternalError::from_response(err, response).into()
+}
+
+/// Implemented by payloads that have a `Schema`.
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+/// Collects a payload's values and checks them against its schema.
+pub struct Checker {
+    schema: &'static Schema,
+    present: HashSet<&'static str>,
+    errors: Vec<FieldError>,
+}
+
+impl Checker {
+    pub fn new(schema: &'static Schema) -> Self {
+        Checker { schema, present: HashSet::new(), errors: Vec::new() }
+    }
+
+    pub fn text(mut self, name: &str, value: Option<&str>) -> Self {
+        let field = self.field(name);
+        if let Some(value) = value {
+            self.present.insert(field.name);
+            for rule in field.rules {
+                if let Some((code, message)) = check_text(rule, value) {
+                    self.errors.push(FieldError { field: field.name, code, message });
+                }
+            }
+        }
+        self
+    }
+
+    pub fn integer(mut self, name: &str, value: Option<i64>) -> Self {
+        let field = self.field(name);
+        if let Some(value) = value {
+            self.present.insert(field.name);
+            for rule in field.rules {
+                if let Rule::Range { min, max } = rule {
+                    if value < *min || value > *max {
+                        self.errors.push(FieldError {
+                            field: field.name,
+                            code: "out_of_range",
+                            message: format!("Must be between {min} and {max}"),
+                        });
+                    }
+                }
+            }
+        }
+        self
+    }
+
+    /// Records whether a field without value rules, such as a date, is set.
+    pub fn set(mut self, name: &str, is_set: bool) -> Self {
+        let field = self.field(name);
+        if is_set {
+            self.present.insert(field.name);
+        }
+        self
+    }
+
+    pub fn finish(mut self) -> Result<(), ValidationErrors> {
+        for field in self.schema.fields {
+            if !self.present.contains(field.name) {
+                continue;
+            }
+            for rule in field.rules {
+                if let Rule::Requires(other) = rule {
+                    if !self.present.contains(other) {
+                        self.errors.push(FieldError {
+                            field: field.name,
+                            code: "requires",
+                            message: format!("Requires {other} to be set"),
+                        });
+                    }
+                }
+            }
+        }
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors { errors: self.errors })
+        }
+    }
+
+    fn field(&self, name: &str) -> &'static Field {
+        self.schema.fields
+            .iter()
+            .find(|field| field.name == name)
+            .unwrap_or_else(|| panic!("{} has no field {name}", self.schema.name))
+    }
+}
+
+fn check_text(rule: &Rule, value: &str) -> Option<(&'static str, String)> {
+    match rule {
+        Rule::NotBlank if value.trim().is_empty() => Some(("blank", "Cannot be blank".into())),
+        Rule::Length { min, max } => {
+            let length = value.chars().count();
+            if length < *min {
+                Some(("too_short", format!("Must be at least {min} characters")))
+            } else if length > *max {
+                Some(("too_long", format!("Must be at most {max} characters")))
+            } else {
+                None
+            }
+        }
+        Rule::Email if !is_valid_email(value) =>
+            Some(("invalid_email", "Must be an email address like name@example.com".into())),
+        Rule::Password if !is_strong_password(value) =>
+            Some(("weak_password", "Must contain at least one letter and one digit".into())),
+        Rule::Timezone if deadline::parse_timezone(value).is_none() =>
+            Some(("unknown_timezone", format!("Unknown timezone: {value}"))),
+        _ => None,
+    }
+}
+
+/// Checks an address has the shape `local@domain.tld`.
+/// Whether it really exists is only known once the verification link is opened.
+pub fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    !local.is_empty() &&
+        !domain.contains('@') &&
+        !email.chars().any(char::is_whitespace) &&
+        domain.split('.').count() >= 2 &&
+        domain.split('.').all(|label| !label.is_empty())
+}
+
+fn is_strong_password(password: &str) -> bool {
+    password.chars().any(char::is_alphabetic) && password.chars().any(|c| c.is_ascii_digit())
+}
+
+impl Schema {
+    /// Renders the rules as a JSON Schema (draft 2020-12) object.
+    pub fn to_json_schema(&self) -> Value {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        let mut dependent_required = Map::new();
+
+        for field in self.fields {
+            let mut property = Map::new();
+            let json_type = match field.kind {
+                Kind::Integer => "integer",
+                _ => "string",
+            };
+            property.insert(
+                "type".into(),
+                if field.required {
+                    json!(json_type)
+                } else {
+                    json!([json_type, "null"])
+                }
+            );
+            match field.kind {
+                Kind::Date => constrain(&mut property, "format", json!("date")),
+                Kind::Time => constrain(&mut property, "format", json!("time")),
+                _ => {}
+            }
+
+            for rule in field.rules {
+                match rule {
+                    Rule::NotBlank => constrain(&mut property, "pattern", json!("\\S")),
+                    Rule::Length { min, max } => {
+                        property.insert("minLength".into(), json!(min));
+                        property.insert("maxLength".into(), json!(max));
+                    }
+                    Rule::Range { min, max } => {
+                        property.insert("minimum".into(), json!(min));
+                        property.insert("maximum".into(), json!(max));
+                    }
+                    Rule::Email => constrain(&mut property, "format", json!("email")),
+                    Rule::Password => {
+                        constrain(&mut property, "pattern", json!("^(?=.*\\p{L})(?=.*[0-9])"));
+                    }
+                    Rule::Timezone => {
+                        property.insert("description".into(), json!("IANA timezone name"));
+                    }
+                    Rule::Requires(other) => {
+                        dependent_required.insert(field.name.into(), json!([other]));
+                    }
+                }
+            }
+
+            if field.required {
+                required.push(field.name);
+            }
+            properties.insert(field.name.into(), Value::Object(property));
+        }
+
+        let mut schema =
+            json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": self.name,
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        });
+        if !dependent_required.is_empty() {
+            schema["dependentRequired"] = Value::Object(dependent_required);
+        }
+        schema
+    }
+}
+
+/// Adds a keyword to a property. A keyword can only appear once per object, so when several
+/// rules set it, e.g. two patterns, the later ones go into `allOf` and all of them apply.
+fn constrain(property: &mut Map<String, Value>, keyword: &str, value: Value) {
+    if !property.contains_key(keyword) {
+        property.insert(keyword.into(), value);
+        return;
+    }
+    let all_of = property.entry("allOf").or_insert_with(|| json!([]));
+    if let Value::Array(all_of) = all_of {
+        all_of.push(json!({ keyword: value }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: Schema = Schema {
+        name: "Test",
+        fields: &[
+            Field {
+                name: "title",
+                kind: Kind::String,
+                required: true,
+                rules: &[Rule::NotBlank, Rule::Length { min: 1, max: 5 }],
+            },
+            Field { name: "count", kind: Kind::Integer, required: false, rules: &[Rule::Range { min: 0, max: 3 }] },
+            Field { name: "date", kind: Kind::Date, required: false, rules: &[] },
+            Field { name: "time", kind: Kind::Time, required: false, rules: &[Rule::Requires("date")] },
+        ],
+    };
+
+    #[test]
+    fn lists_every_broken_rule() {
+        let result = Checker::new(&SCHEMA)
+            .text("title", Some("      "))
+            .integer("count", Some(-1))
+            .set("date", false)
+            .set("time", true)
+            .finish();
+
+        let codes: Vec<_> = result
+            .unwrap_err()
+            .errors.iter()
+            .map(|error| (error.field, error.code))
+            .collect();
+        assert_eq!(codes, [
+            ("title", "blank"),
+            ("title", "too_long"),
+            ("count", "out_of_range"),
+            ("time", "requires"),
+        ]);
+    }
+
+    #[test]
+    fn accepts_valid_payloads() {
+        let result = Checker::new(&SCHEMA)
+            .text("title", Some("Plan"))
+            .integer("count", None)
+            .set("date", true)
+            .set("time", true)
+            .finish();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn validates_email_shape() {
+        assert!(is_valid_email("user@email.com"));
+        assert!(is_valid_email("first.last@mail.example.org"));
+        assert!(!is_valid_email("user"));
+        assert!(!is_valid_email("user@localhost"));
+        assert!(!is_valid_email("@email.com"));
+        assert!(!is_valid_email("user@@email.com"));
+        assert!(!is_valid_email("us er@email.com"));
+        assert!(!is_valid_email("user@email."));
+    }
+
+    #[test]
+    fn renders_json_schema() {
+        let schema = SCHEMA.to_json_schema();
+        assert_eq!(schema["required"], json!(["title"]));
+        assert_eq!(schema["properties"]["title"]["maxLength"], json!(5));
+        assert_eq!(schema["properties"]["count"]["type"], json!(["integer", "null"]));
+        assert_eq!(schema["dependentRequired"]["time"], json!(["date"]));
+    }
+
+    #[test]
+    fn keeps_every_pattern_of_a_field() {
+        const PASSWORD: Schema = Schema {
+            name: "Password",
+            fields: &[
+                Field { name: "password", kind: Kind::String, required: true, rules: &[Rule::NotBlank, Rule::Password] },
+            ],
+        };
+        let property = &PASSWORD.to_json_schema()["properties"]["password"];
+        assert_eq!(property["pattern"], json!("\\S"));
+        assert_eq!(property["allOf"], json!([{ "pattern": "^(?=.*\\p{L})(?=.*[0-9])" }]));
+    }
+
+    #[actix_web::test]
+    async fn answers_malformed_bodies_with_a_422() {
+        use actix_web::{ http::StatusCode, test, App };
+
+        #[derive(serde::Deserialize)]
+        struct Payload {
+            #[allow(dead_code)]
+            count: i64,
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(json_config())
+                .route("/", web::post().to(|_: web::Json<Payload>| async { HttpResponse::Ok().finish() }))
+        ).await;
+        let request = test::TestRequest::post().uri("/").set_json(json!({"count": "three"})).to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body["errors"][0]["field"], json!("body"));
+        assert_eq!(body["errors"][0]["code"], json!("malformed"));
+    }
+}